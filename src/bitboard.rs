@@ -74,6 +74,43 @@ impl Bitboard {
         Bitboard(1u64 << sq.0)
     }
 
+    /// Parse an 8x8 grid of `X` (set) and `.` (empty), rank 8 first, as printed by `Debug`
+    /// without the coordinates. Whitespace is ignored; returns None for other characters
+    /// or a square count other than 64.
+    pub fn from_ascii(grid: &str) -> Option<Self> {
+        let mut bb = Bitboard::EMPTY;
+        let mut index = 0u8;
+        for c in grid.chars().filter(|c| !c.is_whitespace()) {
+            if index >= 64 {
+                return None;
+            }
+            let sq = Square::from_coords(index % 8, 7 - index / 8);
+            match c {
+                'X' => bb = bb.set(sq),
+                '.' => {}
+                _ => return None,
+            }
+            index += 1;
+        }
+        (index == 64).then_some(bb)
+    }
+
+    /// Print as an 8x8 grid of `X` and `.`, rank 8 first, one rank per line
+    pub fn to_ascii(self) -> String {
+        let mut grid = String::with_capacity(128);
+        for rank in (0..8).rev() {
+            for file in 0..8 {
+                if file > 0 {
+                    grid.push(' ');
+                }
+                let set = self.contains(Square::from_coords(file, rank));
+                grid.push(if set { 'X' } else { '.' });
+            }
+            grid.push('\n');
+        }
+        grid
+    }
+
     #[inline(always)]
     pub const fn is_empty(self) -> bool {
         self.0 == 0
@@ -406,12 +443,12 @@ const fn init_knight_attacks() -> [Bitboard; 64] {
         // Knight moves: +/- 6, 10, 15, 17
         attack |= (bb << 17) & !Bitboard::FILE_A.0;
         attack |= (bb << 15) & !Bitboard::FILE_H.0;
-        attack |= (bb << 10) & !Bitboard::NOT_FILE_GH.0;
-        attack |= (bb << 6) & !Bitboard::NOT_FILE_AB.0;
+        attack |= (bb << 10) & Bitboard::NOT_FILE_AB.0;
+        attack |= (bb << 6) & Bitboard::NOT_FILE_GH.0;
         attack |= (bb >> 17) & !Bitboard::FILE_H.0;
         attack |= (bb >> 15) & !Bitboard::FILE_A.0;
-        attack |= (bb >> 10) & !Bitboard::NOT_FILE_AB.0;
-        attack |= (bb >> 6) & !Bitboard::NOT_FILE_GH.0;
+        attack |= (bb >> 10) & Bitboard::NOT_FILE_GH.0;
+        attack |= (bb >> 6) & Bitboard::NOT_FILE_AB.0;
 
         attacks[sq as usize] = Bitboard(attack);
         sq += 1;
@@ -545,11 +582,7 @@ const fn init_line() -> [[Bitboard; 64]; 64] {
 }
 
 const fn abs_diff(a: u8, b: u8) -> u8 {
-    if a > b {
-        a - b
-    } else {
-        b - a
-    }
+    a.abs_diff(b)
 }
 
 /// Check if three squares are aligned (on same rank, file, or diagonal)
@@ -570,6 +603,29 @@ pub fn line(sq1: Square, sq2: Square) -> Bitboard {
     LINE[sq1.0 as usize][sq2.0 as usize]
 }
 
+/// Get bitboard of squares from `from` (exclusive) through `to` to the board edge,
+/// or empty if the squares are not aligned
+#[inline(always)]
+pub fn ray_toward(from: Square, to: Square) -> Bitboard {
+    // Square indices grow monotonically along any line, so the ray is the part of the
+    // line on `to`'s side of `from`
+    let above = (u64::MAX << from.0) << 1;
+    let side = if to.0 > from.0 {
+        above
+    } else {
+        (1u64 << from.0) - 1
+    };
+    Bitboard(LINE[from.0 as usize][to.0 as usize].0 & side)
+}
+
+/// Get bitboard of squares a piece may move to while pinned to `king` by `pinner`:
+/// the squares between them plus capturing the pinner. Also the block-or-capture mask
+/// against a slider giving check.
+#[inline(always)]
+pub fn pin_mask(king: Square, pinner: Square) -> Bitboard {
+    between(king, pinner) | Bitboard::from_square(pinner)
+}
+
 /// Get knight attacks for a square
 #[inline(always)]
 pub fn knight_attacks(sq: Square) -> Bitboard {
@@ -673,6 +729,76 @@ mod tests {
         assert_eq!(bb.pop_count(), 6); // b1 through g1
     }
 
+    #[test]
+    fn test_ascii_round_trip() {
+        let grid = "\
+            . . . . . . . X
+            . . . . . . X .
+            . . . . . X . .
+            X X X X X X X X
+            . . . X . . . .
+            . . X . . . . .
+            . X . . . . . .
+            X . . . . . . .";
+        let bb = Bitboard::from_ascii(grid).unwrap();
+        assert_eq!(bb.pop_count() as usize, grid.matches('X').count());
+        assert_eq!(
+            bb,
+            Bitboard::from_square(Square::A1)
+                | Bitboard::from_square(Square::H8)
+                | between(Square::A1, Square::H8)
+                | Bitboard::RANK_5
+        );
+
+        let printed = bb.to_ascii();
+        let expected: Vec<&str> = grid.lines().map(str::trim).collect();
+        assert_eq!(printed.lines().collect::<Vec<_>>(), expected);
+        assert_eq!(Bitboard::from_ascii(&printed), Some(bb));
+
+        // Bad characters or the wrong number of squares
+        assert_eq!(Bitboard::from_ascii(&printed.replace('X', "o")), None);
+        assert_eq!(Bitboard::from_ascii(". . X"), None);
+        assert_eq!(Bitboard::from_ascii(&format!("{}.", printed)), None);
+    }
+
+    #[test]
+    fn test_ray_toward() {
+        // From a1 through c3 along the long diagonal to h8
+        let bb = ray_toward(Square::A1, Square::C3);
+        assert_eq!(bb.pop_count(), 7);
+        assert!(bb.contains(Square::B2) && bb.contains(Square::H8));
+        assert!(!bb.contains(Square::A1));
+
+        // Downward along the e-file stops at e1
+        assert_eq!(
+            ray_toward(Square::E4, Square::E3),
+            Bitboard::from_square(Square::E3)
+                | Bitboard::from_square(Square::E2)
+                | Bitboard::from_square(Square::E1)
+        );
+
+        // Edge squares: nothing beyond h8, nothing for unaligned squares
+        assert_eq!(
+            ray_toward(Square::A1, Square::H8),
+            pin_mask(Square::A1, Square::H8)
+        );
+        assert!(ray_toward(Square::H8, Square::G8).contains(Square::A8));
+        assert!(ray_toward(Square::A1, Square::B3).is_empty());
+    }
+
+    #[test]
+    fn test_pin_mask() {
+        let bb = pin_mask(Square::E1, Square::E8);
+        assert_eq!(bb.pop_count(), 7); // e2 through e8
+        assert!(bb.contains(Square::E8) && !bb.contains(Square::E1));
+
+        // Adjacent pinner: only the capture
+        assert_eq!(
+            pin_mask(Square::E1, Square::F2),
+            Bitboard::from_square(Square::F2)
+        );
+    }
+
     #[test]
     fn test_aligned() {
         assert!(aligned(Square::A1, Square::D4, Square::H8)); // Diagonal
@@ -0,0 +1,135 @@
+/// Cuckoo tables for upcoming-repetition detection (Marcel van Kervinck's method)
+///
+/// Every reversible non-pawn move is stored under the Zobrist difference it makes:
+/// both piece-square keys plus the side key. XORing the current hash with an
+/// earlier one and finding the result here means a single move links the two.
+use crate::types::{PieceType, Square};
+use crate::zobrist::ZOBRIST;
+
+/// Number of slots; the 3668 reversible moves fill it to under half
+const CUCKOO_SIZE: usize = 8192;
+
+/// Pieces whose moves can be undone (pawn moves and captures never can)
+const REVERSIBLE_PIECES: [PieceType; 5] = [
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
+
+/// Reversible move keys and the two squares of each move
+pub struct Cuckoo {
+    keys: [u64; CUCKOO_SIZE],
+    squares: [(Square, Square); CUCKOO_SIZE],
+}
+
+/// Global cuckoo table, built at compile time from the Zobrist keys
+pub static CUCKOO: Cuckoo = Cuckoo::new();
+
+impl Cuckoo {
+    const fn new() -> Self {
+        let mut keys = [0u64; CUCKOO_SIZE];
+        let mut squares = [(Square(0), Square(0)); CUCKOO_SIZE];
+
+        let mut color = 0;
+        while color < 2 {
+            let mut p = 0;
+            while p < REVERSIBLE_PIECES.len() {
+                let piece = REVERSIBLE_PIECES[p];
+                let mut s1 = 0;
+                while s1 < 64 {
+                    let mut s2 = s1 + 1;
+                    while s2 < 64 {
+                        if empty_board_attack(piece, s1 as u8, s2 as u8) {
+                            let mut key = ZOBRIST.pieces[color][piece as usize][s1]
+                                ^ ZOBRIST.pieces[color][piece as usize][s2]
+                                ^ ZOBRIST.side;
+                            let mut mv = (Square(s1 as u8), Square(s2 as u8));
+
+                            // Insert, evicting occupants to their other slot until one is free
+                            let mut i = h1(key);
+                            loop {
+                                let evicted_key = keys[i];
+                                let evicted_mv = squares[i];
+                                keys[i] = key;
+                                squares[i] = mv;
+                                if evicted_key == 0 {
+                                    break;
+                                }
+                                key = evicted_key;
+                                mv = evicted_mv;
+                                i = if i == h1(key) { h2(key) } else { h1(key) };
+                            }
+                        }
+                        s2 += 1;
+                    }
+                    s1 += 1;
+                }
+                p += 1;
+            }
+            color += 1;
+        }
+
+        Cuckoo { keys, squares }
+    }
+
+    /// Squares of the reversible move whose Zobrist difference is `key`, if any
+    #[inline(always)]
+    pub fn lookup(&self, key: u64) -> Option<(Square, Square)> {
+        let i = h1(key);
+        if self.keys[i] == key {
+            return Some(self.squares[i]);
+        }
+        let i = h2(key);
+        if self.keys[i] == key {
+            return Some(self.squares[i]);
+        }
+        None
+    }
+}
+
+#[inline(always)]
+const fn h1(key: u64) -> usize {
+    (key & 0x1FFF) as usize
+}
+
+#[inline(always)]
+const fn h2(key: u64) -> usize {
+    ((key >> 16) & 0x1FFF) as usize
+}
+
+/// Whether `piece` on `s1` attacks `s2` on an empty board
+const fn empty_board_attack(piece: PieceType, s1: u8, s2: u8) -> bool {
+    let df = (s1 % 8).abs_diff(s2 % 8);
+    let dr = (s1 / 8).abs_diff(s2 / 8);
+    let straight = df == 0 || dr == 0;
+    let diagonal = df == dr;
+    match piece {
+        PieceType::Knight => (df == 1 && dr == 2) || (df == 2 && dr == 1),
+        PieceType::Bishop => diagonal,
+        PieceType::Rook => straight,
+        PieceType::Queen => straight || diagonal,
+        PieceType::King => df <= 1 && dr <= 1,
+        PieceType::Pawn => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Color;
+
+    #[test]
+    fn test_cuckoo_holds_every_reversible_move() {
+        let filled = CUCKOO.keys.iter().filter(|&&key| key != 0).count();
+        assert_eq!(filled, 3668);
+
+        // Nf3-g1 and Ng1-f3 share one entry
+        let key = ZOBRIST.piece_key(Color::White, PieceType::Knight, Square::G1)
+            ^ ZOBRIST.piece_key(Color::White, PieceType::Knight, Square::F3)
+            ^ ZOBRIST.side_key();
+        assert_eq!(CUCKOO.lookup(key), Some((Square::G1, Square::F3)));
+        assert_eq!(CUCKOO.lookup(key ^ ZOBRIST.side_key()), None);
+    }
+}
@@ -0,0 +1,76 @@
+/// Debug logging, enabled by the UCI "debug on" command
+use std::fmt;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether debug output is enabled
+static DEBUG: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable debug output
+pub fn set_debug(enabled: bool) {
+    DEBUG.store(enabled, Ordering::Relaxed);
+}
+
+/// Check if debug output is enabled
+#[inline(always)]
+pub fn is_debug() -> bool {
+    DEBUG.load(Ordering::Relaxed)
+}
+
+/// Write one debug line to `out` as a UCI "info string"
+pub fn write_line(out: &mut dyn Write, message: fmt::Arguments) {
+    writeln!(out, "info string {}", message).unwrap();
+    out.flush().unwrap();
+}
+
+/// Log a formatted debug line if debug output is enabled:
+/// `debug_info!(out => ...)` writes to `out`, `debug_info!(...)` to stdout
+#[macro_export]
+macro_rules! debug_info {
+    ($out:expr => $($arg:tt)*) => {
+        if $crate::debug::is_debug() {
+            $crate::debug::write_line($out, format_args!($($arg)*));
+        }
+    };
+    ($($arg:tt)*) => {
+        $crate::debug_info!(&mut std::io::stdout() => $($arg)*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::magic::init_magics;
+    use crate::position::Position;
+    use crate::search::SearchInfo;
+    use crate::tt::TranspositionTable;
+    use std::time::Instant;
+
+    #[test]
+    fn test_write_line_formats_info_string() {
+        let mut out = Vec::new();
+        write_line(&mut out, format_args!("nodes={} {}", 42, "e2e4"));
+        assert_eq!(String::from_utf8(out).unwrap(), "info string nodes=42 e2e4\n");
+    }
+
+    #[test]
+    fn test_debug_off_is_silent() {
+        init_magics();
+        // No test turns the global flag on, so a normal search must not log anything
+        assert!(!is_debug());
+        let pos = Position::new();
+        let mut tt = TranspositionTable::new(1);
+        let mut info = SearchInfo::new(Instant::now());
+        info.depth_limit = Some(3);
+        let mut out = Vec::new();
+        pos.search_with_output(&mut tt, &mut info, &mut out);
+
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.contains("info depth 3 "), "{}", output);
+        assert!(!output.contains("info string"), "{}", output);
+
+        let mut out = Vec::new();
+        debug_info!(&mut out => "hidden {}", 1);
+        assert!(out.is_empty());
+    }
+}
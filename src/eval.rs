@@ -1,10 +1,14 @@
 /// Evaluation function with tapered evaluation
-use crate::bitboard::{pawn_attacks, Bitboard};
+use crate::bitboard::{king_attacks, knight_attacks, Bitboard};
+use crate::magic::{bishop_attacks, rook_attacks};
 use crate::position::Position;
-use crate::types::{Color, PieceType, Square};
+use crate::search::MATE_BOUND;
+use crate::see::see_piece_value;
+use crate::types::{CastlingRights, Color, PieceType, Square};
+use std::cell::RefCell;
 
 /// Score with midgame and endgame components
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
 pub struct Score {
     pub mg: i16, // Midgame
     pub eg: i16, // Endgame
@@ -28,6 +32,16 @@ impl std::ops::Add for Score {
     }
 }
 
+impl Score {
+    /// Scale both components by a percentage
+    pub const fn scaled(self, percent: i16) -> Self {
+        Score {
+            mg: (self.mg as i32 * percent as i32 / 100) as i16,
+            eg: (self.eg as i32 * percent as i32 / 100) as i16,
+        }
+    }
+}
+
 impl std::ops::AddAssign for Score {
     fn add_assign(&mut self, rhs: Self) {
         self.mg += rhs.mg;
@@ -72,6 +86,58 @@ impl std::ops::Mul<i16> for Score {
     }
 }
 
+/// Evaluation terms from white's perspective, before tapering
+#[derive(Clone, Copy, Default, Debug)]
+pub struct EvalBreakdown {
+    pub material: Score,
+    pub psqt: Score,
+    pub pawns: Score,
+    pub bishop_pair: Score,
+    pub rooks: Score,
+    pub imbalance: Score,
+    pub kings: Score,
+    pub threats: Score,
+    pub king_safety: Score,
+    /// Game phase, from TOTAL_PHASE (opening) down to 0 (bare kings and pawns)
+    pub phase: i32,
+    /// Endgame scale factor out of SCALE_NORMAL
+    pub scale: i32,
+}
+
+impl EvalBreakdown {
+    /// Named terms, in display order
+    pub fn terms(&self) -> [(&'static str, Score); 9] {
+        [
+            ("Material", self.material),
+            ("PSQT", self.psqt),
+            ("Pawns", self.pawns),
+            ("Bishop pair", self.bishop_pair),
+            ("Rooks", self.rooks),
+            ("Imbalance", self.imbalance),
+            ("Kings", self.kings),
+            ("Threats", self.threats),
+            ("King safety", self.king_safety),
+        ]
+    }
+
+    /// Sum of all terms
+    pub fn total(&self) -> Score {
+        self.terms()
+            .iter()
+            .fold(Score::ZERO, |total, &(_, score)| total + score)
+    }
+
+    /// Final tapered score from white's perspective
+    pub fn tapered(&self) -> i16 {
+        let total = self.total();
+        let mg_phase = self.phase;
+        let eg_phase = TOTAL_PHASE - mg_phase;
+
+        let eg = total.eg as i32 * self.scale / SCALE_NORMAL;
+        ((total.mg as i32 * mg_phase + eg * eg_phase) / TOTAL_PHASE) as i16
+    }
+}
+
 /// Piece values (PeSTO-style)
 pub const PIECE_VALUES: [Score; 6] = [
     Score::new(82, 94),    // Pawn
@@ -84,7 +150,7 @@ pub const PIECE_VALUES: [Score; 6] = [
 
 /// Phase values for tapered evaluation
 const PHASE_VALUES: [i32; 6] = [0, 1, 1, 2, 4, 0];
-const TOTAL_PHASE: i32 = 24; // 2*(1+1+2+4) + 2*(1+1+2+4) = 16 knights/bishops + 4 rooks + 2 queens
+pub const TOTAL_PHASE: i32 = 24; // 2*(1+1+2+4) + 2*(1+1+2+4) = 16 knights/bishops + 4 rooks + 2 queens
 
 /// Piece-square tables (from white's perspective)
 /// Index by [PieceType][Square]
@@ -242,24 +308,191 @@ const PASSED_PAWN_BONUS: [Score; 8] = [
 ];
 const ROOK_OPEN_FILE: Score = Score::new(20, 10);
 const ROOK_SEMI_OPEN_FILE: Score = Score::new(10, 5);
+const QUEEN_OPEN_FILE: Score = Score::new(6, 3);
+const QUEEN_SEMI_OPEN_FILE: Score = Score::new(3, 2);
+/// Rook boxed in on the back rank by its own king after castling rights are gone
+const TRAPPED_ROOK: Score = Score::new(-45, -10);
+
+/// Penalty for a piece attacked by the opponent and not defended, by piece type
+const HANGING_PIECE: [Score; 6] = [
+    Score::new(-5, -10),   // Pawn
+    Score::new(-30, -20),  // Knight
+    Score::new(-30, -20),  // Bishop
+    Score::new(-40, -30),  // Rook
+    Score::new(-60, -40),  // Queen
+    Score::new(0, 0),      // King (checks are left to the search)
+];
+/// Middlegame bonus for the nearest friendly pawn in front of the king on each of the
+/// three files around it, by rank distance (index 0: no pawn on the file)
+const KING_SHELTER: [i16; 8] = [-25, 30, 15, 5, 0, 0, 0, 0];
+/// Middlegame penalty for the nearest enemy pawn advancing on those files, by rank distance
+/// (halved when our shelter pawn blocks it)
+const PAWN_STORM: [i16; 8] = [0, -10, -40, -25, -10, 0, 0, 0];
+
+/// Bonus for attacking a more valuable enemy piece, by attacked piece type
+const THREAT_BY_PAWN: [Score; 6] = [
+    Score::new(0, 0),
+    Score::new(60, 35),
+    Score::new(60, 35),
+    Score::new(80, 50),
+    Score::new(90, 60),
+    Score::new(0, 0),
+];
+const THREAT_BY_MINOR: [Score; 6] = [
+    Score::new(0, 0),
+    Score::new(0, 0),
+    Score::new(0, 0),
+    Score::new(45, 35),
+    Score::new(55, 45),
+    Score::new(0, 0),
+];
+
+/// Endgame king terms (per square of distance)
+const KING_CENTRALIZATION: i16 = 10;
+const MOP_UP_EDGE: i16 = 10;
+const MOP_UP_PROXIMITY: i16 = 4;
+/// Endgame material lead needed before mop-up terms apply
+const MOP_UP_MARGIN: i16 = 400;
+/// Penalty for leaving a bare losing king with no or one safe square, by square count
+const STALEMATE_RISK: [i16; 2] = [40, 15];
+
+/// Material imbalance coefficients (Stockfish-style, in 1/16ths), indexed by
+/// [bishop pair, pawn, knight, bishop, rook, queen]. The bishop pair's own
+/// value is left to BISHOP_PAIR.
+const IMBALANCE_OURS: [[i32; 6]; 6] = [
+    [0, 0, 0, 0, 0, 0],
+    [40, 38, 0, 0, 0, 0],
+    [32, 255, -62, 0, 0, 0],
+    [0, 104, 4, 0, 0, 0],
+    [-26, -2, 47, 105, -208, 0],
+    [-189, 24, 117, 133, -134, -6],
+];
+const IMBALANCE_THEIRS: [[i32; 6]; 6] = [
+    [0, 0, 0, 0, 0, 0],
+    [36, 0, 0, 0, 0, 0],
+    [9, 63, 0, 0, 0, 0],
+    [59, 65, 42, 0, 0, 0],
+    [46, 39, 24, -24, 0, 0],
+    [97, 100, -42, 137, 268, 0],
+];
+/// Converts imbalance units to centipawns (1/16ths, then Stockfish to PeSTO pawns)
+const IMBALANCE_DIVISOR: i32 = 23;
+
+const IMBALANCE_CACHE_SIZE: usize = 4096;
+
+thread_local! {
+    /// Imbalance scores keyed by material key (material is rarely unique)
+    static IMBALANCE_CACHE: RefCell<Vec<(u64, i16)>> =
+        RefCell::new(vec![(u64::MAX, 0); IMBALANCE_CACHE_SIZE]);
+}
+
+/// Endgame scale factors (out of SCALE_NORMAL) for drawish material
+pub const SCALE_NORMAL: i32 = 64;
+const SCALE_OCB_PURE: i32 = 20;
+const SCALE_OCB_WITH_PIECES: i32 = 46;
+const SCALE_DRAW: i32 = 0;
+const SCALE_KRP_VS_KR_BLOCKADED: i32 = 16;
+/// Opposite-colored bishops only scale down with at most this many pawns per side
+const OCB_MAX_PAWNS: u32 = 6;
+
+/// Evaluation weights that can be overridden at runtime (e.g. by the tuner)
+#[derive(Clone, Debug)]
+pub struct EvalParams {
+    pub piece_values: [Score; 6],
+    pub psqt_mg: [[i16; 64]; 6],
+    pub psqt_eg: [[i16; 64]; 6],
+    pub bishop_pair: Score,
+    pub doubled_pawn: Score,
+    pub isolated_pawn: Score,
+    pub passed_pawn: [Score; 8],
+    pub rook_open_file: Score,
+    pub rook_semi_open_file: Score,
+    pub queen_open_file: Score,
+    pub queen_semi_open_file: Score,
+    pub trapped_rook: Score,
+    pub hanging_piece: [Score; 6],
+    pub threat_by_pawn: [Score; 6],
+    pub threat_by_minor: [Score; 6],
+    pub king_shelter: [i16; 8],
+    pub pawn_storm: [i16; 8],
+}
+
+impl EvalParams {
+    /// The built-in weights
+    pub const DEFAULT: EvalParams = EvalParams {
+        piece_values: PIECE_VALUES,
+        psqt_mg: PSQT_MG,
+        psqt_eg: PSQT_EG,
+        bishop_pair: BISHOP_PAIR,
+        doubled_pawn: DOUBLED_PAWN,
+        isolated_pawn: ISOLATED_PAWN,
+        passed_pawn: PASSED_PAWN_BONUS,
+        rook_open_file: ROOK_OPEN_FILE,
+        rook_semi_open_file: ROOK_SEMI_OPEN_FILE,
+        queen_open_file: QUEEN_OPEN_FILE,
+        queen_semi_open_file: QUEEN_SEMI_OPEN_FILE,
+        trapped_rook: TRAPPED_ROOK,
+        hanging_piece: HANGING_PIECE,
+        threat_by_pawn: THREAT_BY_PAWN,
+        threat_by_minor: THREAT_BY_MINOR,
+        king_shelter: KING_SHELTER,
+        pawn_storm: PAWN_STORM,
+    };
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams::DEFAULT
+    }
+}
 
 impl Position {
     /// Evaluate the position from the side to move's perspective
     pub fn evaluate(&self) -> i16 {
-        let mut score = Score::ZERO;
-        let mut phase = 0i32;
+        self.evaluate_with(&EvalParams::DEFAULT)
+    }
+
+    /// Evaluate the position from the side to move's perspective with custom weights.
+    /// Always strictly inside the mate bounds, so a static score is never taken for a mate.
+    pub fn evaluate_with(&self, params: &EvalParams) -> i16 {
+        // Dead draws score exactly zero, whatever the PSQT thinks of the pieces
+        if self.is_insufficient_material() {
+            return 0;
+        }
+
+        let tapered = self
+            .evaluate_detailed_with(params)
+            .tapered()
+            .clamp(-MATE_BOUND + 1, MATE_BOUND - 1);
+
+        // Return from side to move perspective
+        if self.side_to_move == Color::White {
+            tapered
+        } else {
+            -tapered
+        }
+    }
+
+    /// Evaluate each term separately (white's perspective, before tapering)
+    pub fn evaluate_detailed(&self) -> EvalBreakdown {
+        self.evaluate_detailed_with(&EvalParams::DEFAULT)
+    }
+
+    /// Evaluate each term separately with custom weights
+    pub fn evaluate_detailed_with(&self, params: &EvalParams) -> EvalBreakdown {
+        let mut eval = EvalBreakdown::default();
 
         // Material and PSQT
-        for color in [Color::White, Color::Black] {
+        for color in Color::ALL {
             let sign = if color == Color::White { 1i16 } else { -1i16 };
 
-            for piece_type in 0..6 {
-                let pt = unsafe { std::mem::transmute::<u8, PieceType>(piece_type as u8) };
+            for pt in PieceType::ALL {
+                let piece_type = pt.index();
                 let bb = self.piece_bb(color, pt);
                 let count = bb.pop_count() as i16;
 
                 // Material
-                score += PIECE_VALUES[piece_type] * (sign * count);
+                eval.material += params.piece_values[piece_type] * (sign * count);
 
                 // PSQT
                 for sq in bb {
@@ -269,49 +502,49 @@ impl Position {
                         sq.flip_rank().0 as usize
                     };
 
-                    score.mg += sign * PSQT_MG[piece_type][psqt_sq];
-                    score.eg += sign * PSQT_EG[piece_type][psqt_sq];
+                    eval.psqt.mg += sign * params.psqt_mg[piece_type][psqt_sq];
+                    eval.psqt.eg += sign * params.psqt_eg[piece_type][psqt_sq];
                 }
-
-                // Phase
-                phase += PHASE_VALUES[piece_type] * bb.pop_count() as i32;
             }
         }
+        eval.phase = self.game_phase();
 
         // Pawn structure
-        score += self.evaluate_pawns();
+        eval.pawns = self.evaluate_pawns(params);
 
         // Bishop pair
         if self.piece_bb(Color::White, PieceType::Bishop).pop_count() >= 2 {
-            score += BISHOP_PAIR;
+            eval.bishop_pair += params.bishop_pair;
         }
         if self.piece_bb(Color::Black, PieceType::Bishop).pop_count() >= 2 {
-            score -= BISHOP_PAIR;
+            eval.bishop_pair -= params.bishop_pair;
         }
 
-        // Rook on open/semi-open files
-        score += self.evaluate_rooks();
+        // Rooks and queens on open/semi-open files, trapped rooks
+        eval.rooks = self.evaluate_rooks(params);
 
-        // Tapered evaluation
-        let mg_phase = phase.min(TOTAL_PHASE);
-        let eg_phase = TOTAL_PHASE - mg_phase;
+        // Material imbalance
+        let imbalance = self.imbalance();
+        eval.imbalance = Score::new(imbalance, imbalance);
 
-        let tapered =
-            (score.mg as i32 * mg_phase + score.eg as i32 * eg_phase) / TOTAL_PHASE;
+        // King activity (endgame only)
+        eval.kings = self.evaluate_endgame_kings();
 
-        // Return from side to move perspective
-        if self.side_to_move == Color::White {
-            tapered as i16
-        } else {
-            -tapered as i16
-        }
+        // Hanging pieces and attacks on more valuable pieces
+        eval.threats = self.evaluate_threats(params);
+
+        // Pawn shelter and storms around the kings
+        eval.king_safety = self.evaluate_king_shelter(params);
+
+        eval.scale = self.endgame_scale();
+        eval
     }
 
     /// Evaluate pawn structure
-    fn evaluate_pawns(&self) -> Score {
+    fn evaluate_pawns(&self, params: &EvalParams) -> Score {
         let mut score = Score::ZERO;
 
-        for color in [Color::White, Color::Black] {
+        for color in Color::ALL {
             let sign = if color == Color::White { 1i16 } else { -1i16 };
             let our_pawns = self.piece_bb(color, PieceType::Pawn);
             let their_pawns = self.piece_bb(color.flip(), PieceType::Pawn);
@@ -329,12 +562,12 @@ impl Position {
 
                 // Doubled pawns
                 if (our_pawns & file_mask).pop_count() > 1 {
-                    score += DOUBLED_PAWN * sign;
+                    score += params.doubled_pawn * sign;
                 }
 
                 // Isolated pawns
                 if (our_pawns & adjacent_files).is_empty() {
-                    score += ISOLATED_PAWN * sign;
+                    score += params.isolated_pawn * sign;
                 }
 
                 // Passed pawns
@@ -348,7 +581,7 @@ impl Position {
                     }
                     Color::Black => {
                         let mut span = Bitboard::EMPTY;
-                        for r in 0..rank {
+                        for r in (rank + 1)..8 {
                             span |= Bitboard::RANKS[(7 - r) as usize];
                         }
                         span & (file_mask | adjacent_files)
@@ -356,7 +589,7 @@ impl Position {
                 };
 
                 if (their_pawns & front_span).is_empty() {
-                    score += PASSED_PAWN_BONUS[rank as usize] * sign;
+                    score += params.passed_pawn[rank as usize] * sign;
                 }
             }
         }
@@ -364,31 +597,453 @@ impl Position {
         score
     }
 
-    /// Evaluate rooks on open/semi-open files
-    fn evaluate_rooks(&self) -> Score {
+    /// Key identifying the piece counts of both sides (4 bits per piece type)
+    pub fn material_key(&self) -> u64 {
+        let mut key = 0u64;
+        for color in Color::ALL {
+            for pt in [
+                PieceType::Pawn,
+                PieceType::Knight,
+                PieceType::Bishop,
+                PieceType::Rook,
+                PieceType::Queen,
+            ] {
+                key = (key << 4) | self.piece_bb(color, pt).pop_count().min(15) as u64;
+            }
+        }
+        key
+    }
+
+    /// Material imbalance from white's perspective, cached by material key
+    fn imbalance(&self) -> i16 {
+        let key = self.material_key();
+        let index = key as usize % IMBALANCE_CACHE_SIZE;
+
+        if let Some(score) = IMBALANCE_CACHE.with(|cache| {
+            let (cached_key, score) = cache.borrow()[index];
+            (cached_key == key).then_some(score)
+        }) {
+            return score;
+        }
+
+        let score = self.compute_imbalance();
+        IMBALANCE_CACHE.with(|cache| cache.borrow_mut()[index] = (key, score));
+        score
+    }
+
+    /// Material imbalance from white's perspective, computed from scratch
+    fn compute_imbalance(&self) -> i16 {
+        let counts = |color| {
+            let count = |pt| self.piece_bb(color, pt).pop_count() as i32;
+            [
+                (count(PieceType::Bishop) > 1) as i32,
+                count(PieceType::Pawn),
+                count(PieceType::Knight),
+                count(PieceType::Bishop),
+                count(PieceType::Rook),
+                count(PieceType::Queen),
+            ]
+        };
+        let white = counts(Color::White);
+        let black = counts(Color::Black);
+
+        let side = |ours: &[i32; 6], theirs: &[i32; 6]| {
+            let mut total = 0;
+            for pt1 in 0..6 {
+                if ours[pt1] == 0 {
+                    continue;
+                }
+                let mut value = 0;
+                for pt2 in 0..=pt1 {
+                    value += IMBALANCE_OURS[pt1][pt2] * ours[pt2]
+                        + IMBALANCE_THEIRS[pt1][pt2] * theirs[pt2];
+                }
+                total += ours[pt1] * value;
+            }
+            total
+        };
+
+        ((side(&white, &black) - side(&black, &white)) / IMBALANCE_DIVISOR) as i16
+    }
+
+    /// Scale factor for the endgame score in drawish material configurations
+    fn endgame_scale(&self) -> i32 {
+        for strong in Color::ALL {
+            if self.is_wrong_bishop_rook_pawn(strong) {
+                return SCALE_DRAW;
+            }
+            if self.is_blockaded_krp_vs_kr(strong) {
+                return SCALE_KRP_VS_KR_BLOCKADED;
+            }
+        }
+        self.opposite_bishops_scale()
+    }
+
+    /// KB plus rook pawns vs bare K, where the bishop misses the promotion square
+    /// and the defending king already stands by the corner
+    fn is_wrong_bishop_rook_pawn(&self, strong: Color) -> bool {
+        let weak = strong.flip();
+        let pawns = self.piece_bb(strong, PieceType::Pawn);
+        let bishops = self.piece_bb(strong, PieceType::Bishop);
+        if pawns.is_empty()
+            || bishops.is_empty()
+            || (pawns | bishops).pop_count() + 1 != self.occupied[strong as usize].pop_count()
+            || !self.occupied[weak as usize].exactly_one()
+        {
+            return false;
+        }
+
+        let file = if (pawns & !Bitboard::FILE_A).is_empty() {
+            0
+        } else if (pawns & !Bitboard::FILE_H).is_empty() {
+            7
+        } else {
+            return false;
+        };
+        let promotion = Square::from_coords(file, if strong == Color::White { 7 } else { 0 });
+
+        let light_bishops = bishops & Bitboard::LIGHT_SQUARES;
+        let promotion_light = Bitboard::LIGHT_SQUARES.contains(promotion);
+        let wrong_bishop = if promotion_light {
+            light_bishops.is_empty()
+        } else {
+            light_bishops == bishops
+        };
+
+        wrong_bishop && self.king_sq[weak as usize].chebyshev_distance(promotion) <= 1
+    }
+
+    /// KRP vs KR with the defending king on the pawn's file in front of it, on any file
+    fn is_blockaded_krp_vs_kr(&self, strong: Color) -> bool {
+        let weak = strong.flip();
+        let pawns = self.piece_bb(strong, PieceType::Pawn);
+        if !pawns.exactly_one()
+            || !self.piece_bb(strong, PieceType::Rook).exactly_one()
+            || self.occupied[strong as usize].pop_count() != 3
+            || !self.piece_bb(weak, PieceType::Rook).exactly_one()
+            || self.occupied[weak as usize].pop_count() != 2
+        {
+            return false;
+        }
+
+        let pawn = pawns.lsb();
+        let king = self.king_sq[weak as usize];
+        let ahead = match strong {
+            Color::White => king.rank() > pawn.rank(),
+            Color::Black => king.rank() < pawn.rank(),
+        };
+        king.file() == pawn.file() && ahead
+    }
+
+    /// Opposite-colored bishops scale down with few pawns, more so without other pieces
+    fn opposite_bishops_scale(&self) -> i32 {
+        let white_bishops = self.piece_bb(Color::White, PieceType::Bishop);
+        let black_bishops = self.piece_bb(Color::Black, PieceType::Bishop);
+
+        if !white_bishops.exactly_one() || !black_bishops.exactly_one() {
+            return SCALE_NORMAL;
+        }
+
+        // Opposite-colored bishops: exactly one of the two stands on a light square
+        let white_light = (white_bishops & Bitboard::LIGHT_SQUARES).is_not_empty();
+        let black_light = (black_bishops & Bitboard::LIGHT_SQUARES).is_not_empty();
+        if white_light == black_light {
+            return SCALE_NORMAL;
+        }
+
+        let pawns = |color| self.piece_count(color, PieceType::Pawn);
+        if pawns(Color::White) > OCB_MAX_PAWNS || pawns(Color::Black) > OCB_MAX_PAWNS {
+            return SCALE_NORMAL;
+        }
+
+        // Each side has its bishop; any more material means other pieces
+        let bishop = see_piece_value(PieceType::Bishop);
+        let other_pieces = self.non_pawn_material(Color::White) > bishop
+            || self.non_pawn_material(Color::Black) > bishop;
+
+        if other_pieces {
+            SCALE_OCB_WITH_PIECES
+        } else {
+            SCALE_OCB_PURE
+        }
+    }
+
+    /// Reward central kings and, when well ahead, driving the enemy king to the edge
+    fn evaluate_endgame_kings(&self) -> Score {
+        let white_king = self.king_sq[Color::White as usize];
+        let black_king = self.king_sq[Color::Black as usize];
+
+        let mut eg = KING_CENTRALIZATION
+            * (black_king.center_distance() as i16 - white_king.center_distance() as i16);
+
+        let mut material = 0i16;
+        for pt in [
+            PieceType::Pawn,
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Rook,
+            PieceType::Queen,
+        ] {
+            let diff = self.piece_bb(Color::White, pt).pop_count() as i16
+                - self.piece_bb(Color::Black, pt).pop_count() as i16;
+            material += PIECE_VALUES[pt as usize].eg * diff;
+        }
+
+        if material.abs() >= MOP_UP_MARGIN {
+            let (sign, losing_king) = if material > 0 {
+                (1, black_king)
+            } else {
+                (-1, white_king)
+            };
+            let proximity = 14 - white_king.manhattan_distance(black_king) as i16;
+            eg += sign
+                * (MOP_UP_EDGE * losing_king.center_distance() as i16
+                    + MOP_UP_PROXIMITY * proximity);
+        }
+
+        eg -= self.stalemate_risk();
+
+        Score::new(0, eg)
+    }
+
+    /// Penalty (white's perspective) when a bare king to move is nearly out of squares
+    /// without being in check, so mop-up play steers clear of stalemate traps
+    fn stalemate_risk(&self) -> i16 {
+        let losing = self.side_to_move;
+        if !self.pieces_of(losing).exactly_one() || self.is_in_check() {
+            return 0;
+        }
+
+        let attacked = self
+            .attack_maps(losing.flip())
+            .iter()
+            .fold(Bitboard::EMPTY, |all, &bb| all | bb);
+        let safe = (king_attacks(self.king_sq[losing as usize]) & !attacked).pop_count() as usize;
+        let sign = if losing == Color::White { -1 } else { 1 };
+        STALEMATE_RISK.get(safe).map_or(0, |&penalty| sign * penalty)
+    }
+
+    /// Squares attacked by each piece type of `color`
+    fn attack_maps(&self, color: Color) -> [Bitboard; 6] {
+        let occupied = self.all_occupied;
+        let mut maps = [Bitboard::EMPTY; 6];
+
+        let pawns = self.piece_bb(color, PieceType::Pawn);
+        maps[PieceType::Pawn as usize] = match color {
+            Color::White => pawns.north_east() | pawns.north_west(),
+            Color::Black => pawns.south_east() | pawns.south_west(),
+        };
+        for sq in self.piece_bb(color, PieceType::Knight) {
+            maps[PieceType::Knight as usize] |= knight_attacks(sq);
+        }
+        for sq in self.piece_bb(color, PieceType::Bishop) {
+            maps[PieceType::Bishop as usize] |= bishop_attacks(sq, occupied);
+        }
+        for sq in self.piece_bb(color, PieceType::Rook) {
+            maps[PieceType::Rook as usize] |= rook_attacks(sq, occupied);
+        }
+        for sq in self.piece_bb(color, PieceType::Queen) {
+            maps[PieceType::Queen as usize] |=
+                bishop_attacks(sq, occupied) | rook_attacks(sq, occupied);
+        }
+        maps[PieceType::King as usize] = king_attacks(self.king_sq[color as usize]);
+
+        maps
+    }
+
+    /// Game phase from the non-pawn material, TOTAL_PHASE (opening) down to 0 (pawn endgame)
+    pub fn game_phase(&self) -> i32 {
+        let mut phase = 0;
+        for (&pt, &value) in PieceType::ALL.iter().zip(PHASE_VALUES.iter()) {
+            let pieces = self.piece_bb(Color::White, pt) | self.piece_bb(Color::Black, pt);
+            phase += value * pieces.pop_count() as i32;
+        }
+        phase.min(TOTAL_PHASE)
+    }
+
+    /// Whether a piece of `color` (not a pawn) is attacked and undefended, or attacked by
+    /// a cheaper enemy piece
+    pub fn has_hanging_piece(&self, color: Color) -> bool {
+        let union = |maps: &[Bitboard; 6]| maps.iter().fold(Bitboard::EMPTY, |acc, &bb| acc | bb);
+        let ours = self.attack_maps(color);
+        let theirs = self.attack_maps(color.flip());
+
+        let by_pawn = theirs[PieceType::Pawn as usize];
+        let by_minor = by_pawn | theirs[PieceType::Knight as usize] | theirs[PieceType::Bishop as usize];
+        let by_rook = by_minor | theirs[PieceType::Rook as usize];
+
+        let minors = self.piece_bb(color, PieceType::Knight) | self.piece_bb(color, PieceType::Bishop);
+        let rooks = self.piece_bb(color, PieceType::Rook);
+        let queens = self.piece_bb(color, PieceType::Queen);
+        let threatened = (minors & by_pawn) | (rooks & by_minor) | (queens & by_rook);
+
+        let undefended = (minors | rooks | queens) & union(&theirs) & !union(&ours);
+        (threatened | undefended).is_not_empty()
+    }
+
+    /// Evaluate hanging pieces and threats against more valuable pieces
+    fn evaluate_threats(&self, params: &EvalParams) -> Score {
+        let mut score = Score::ZERO;
+        let union = |maps: &[Bitboard; 6]| maps.iter().fold(Bitboard::EMPTY, |acc, &bb| acc | bb);
+        let attacks = [
+            self.attack_maps(Color::White),
+            self.attack_maps(Color::Black),
+        ];
+
+        for color in Color::ALL {
+            let sign = if color == Color::White { 1i16 } else { -1i16 };
+            let ours = &attacks[color as usize];
+            let theirs = &attacks[color.flip() as usize];
+            let defended = union(ours);
+            let attacked = union(theirs);
+            let minor_attacks =
+                theirs[PieceType::Knight as usize] | theirs[PieceType::Bishop as usize];
+
+            for pt in [
+                PieceType::Pawn,
+                PieceType::Knight,
+                PieceType::Bishop,
+                PieceType::Rook,
+                PieceType::Queen,
+            ] {
+                let pieces = self.piece_bb(color, pt);
+
+                // Our pieces the opponent can take for free
+                let hanging = (pieces & attacked & !defended).pop_count() as i16;
+                score += params.hanging_piece[pt as usize] * (sign * hanging);
+
+                // Our pieces attacked by cheaper enemy pieces (bonus for them)
+                let by_pawn = (pieces & theirs[PieceType::Pawn as usize]).pop_count() as i16;
+                let by_minor = (pieces & minor_attacks).pop_count() as i16;
+                score -= params.threat_by_pawn[pt as usize] * (sign * by_pawn);
+                score -= params.threat_by_minor[pt as usize] * (sign * by_minor);
+            }
+        }
+
+        score
+    }
+
+    /// Evaluate the pawn shield in front of each king and enemy pawns storming it
+    fn evaluate_king_shelter(&self, params: &EvalParams) -> Score {
+        let mut mg = 0i16;
+
+        for color in Color::ALL {
+            let sign = if color == Color::White { 1i16 } else { -1i16 };
+            let king = self.king_sq[color as usize];
+            let ahead = Bitboard::RANKS[king.rank() as usize].front_span(color);
+            let our_pawns = self.piece_bb(color, PieceType::Pawn) & ahead;
+            let their_pawns = self.piece_bb(color.flip(), PieceType::Pawn) & ahead;
+
+            // Rank distance from the king to the nearest pawn in `pawns` (0 if none)
+            let nearest = |pawns: Bitboard| -> usize {
+                if pawns.is_empty() {
+                    return 0;
+                }
+                let sq = match color {
+                    Color::White => pawns.lsb(),
+                    Color::Black => pawns.msb(),
+                };
+                sq.rank().abs_diff(king.rank()) as usize
+            };
+
+            // Keep the three files on the board for kings on the edge
+            let center = king.file().clamp(1, 6);
+            for file in (center - 1)..=(center + 1) {
+                let file_mask = Bitboard::FILES[file as usize];
+                let ours = nearest(our_pawns & file_mask);
+                let theirs = nearest(their_pawns & file_mask);
+
+                mg += sign * params.king_shelter[ours];
+
+                let mut storm = params.pawn_storm[theirs];
+                if ours != 0 && theirs == ours + 1 {
+                    storm /= 2;
+                }
+                mg += sign * storm;
+            }
+        }
+
+        Score::new(mg, 0)
+    }
+
+    /// Evaluate rooks and queens on open/semi-open files and rooks trapped by their king
+    fn evaluate_rooks(&self, params: &EvalParams) -> Score {
         let mut score = Score::ZERO;
 
-        for color in [Color::White, Color::Black] {
+        for color in Color::ALL {
             let sign = if color == Color::White { 1i16 } else { -1i16 };
             let our_pawns = self.piece_bb(color, PieceType::Pawn);
             let their_pawns = self.piece_bb(color.flip(), PieceType::Pawn);
             let all_pawns = our_pawns | their_pawns;
+            let king = self.king_sq[color as usize];
 
             for sq in self.piece_bb(color, PieceType::Rook) {
-                let file_mask = Bitboard::FILES[sq.file() as usize];
-
-                if (all_pawns & file_mask).is_empty() {
-                    // Open file
-                    score += ROOK_OPEN_FILE * sign;
-                } else if (our_pawns & file_mask).is_empty() {
-                    // Semi-open file
-                    score += ROOK_SEMI_OPEN_FILE * sign;
+                score += open_file_bonus(
+                    sq,
+                    our_pawns,
+                    all_pawns,
+                    params.rook_open_file,
+                    params.rook_semi_open_file,
+                ) * sign;
+
+                if self.is_rook_trapped(color, sq, king) {
+                    score += params.trapped_rook * sign;
                 }
             }
+
+            for sq in self.piece_bb(color, PieceType::Queen) {
+                score += open_file_bonus(
+                    sq,
+                    our_pawns,
+                    all_pawns,
+                    params.queen_open_file,
+                    params.queen_semi_open_file,
+                ) * sign;
+            }
         }
 
         score
     }
+
+    /// A rook shut in on the back rank between its uncastled king and the corner
+    fn is_rook_trapped(&self, color: Color, rook: Square, king: Square) -> bool {
+        let back_rank = color.back_rank();
+        if rook.rank() != back_rank || king.rank() != back_rank {
+            return false;
+        }
+
+        let trapped_side = match king.file() {
+            5 | 6 => rook.file() > king.file()
+                && !self.castling.contains(CastlingRights::kingside(color)),
+            1..=3 => rook.file() < king.file()
+                && !self.castling.contains(CastlingRights::queenside(color)),
+            _ => false,
+        };
+
+        trapped_side
+            && (rook_attacks(rook, self.all_occupied) & !self.occupied[color as usize]).pop_count()
+                <= 3
+    }
+}
+
+/// Bonus for a piece standing on an open or semi-open file
+#[inline(always)]
+fn open_file_bonus(
+    sq: Square,
+    our_pawns: Bitboard,
+    all_pawns: Bitboard,
+    open: Score,
+    semi_open: Score,
+) -> Score {
+    let file_mask = Bitboard::FILES[sq.file() as usize];
+
+    if (all_pawns & file_mask).is_empty() {
+        open
+    } else if (our_pawns & file_mask).is_empty() {
+        semi_open
+    } else {
+        Score::ZERO
+    }
 }
 
 #[cfg(test)]
@@ -403,6 +1058,20 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_eval_stays_below_mate_bound() {
+        setup();
+        let mut params = EvalParams::DEFAULT;
+        params.piece_values[PieceType::Queen.index()] = Score::new(8000, 8000);
+
+        let white = Position::from_fen("k7/pp6/8/8/8/8/QQQQ4/K7 w - - 0 1").unwrap();
+        assert!(white.evaluate_detailed_with(&params).tapered() >= MATE_BOUND);
+        assert_eq!(white.evaluate_with(&params), MATE_BOUND - 1);
+
+        let black = Position::from_fen("k7/pp6/8/8/8/8/QQQQ4/K7 b - - 0 1").unwrap();
+        assert_eq!(black.evaluate_with(&params), -MATE_BOUND + 1);
+    }
+
     #[test]
     fn test_startpos_evaluation() {
         setup();
@@ -436,6 +1105,234 @@ mod tests {
         assert_eq!(eval1, -eval2);
     }
 
+    #[test]
+    fn test_evaluation_mirror_symmetry() {
+        setup();
+        let fens = [
+            Position::STARTPOS,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP3PPP/R2QKB1R w KQ - 0 8",
+            "r2q1rk1/1b1nbppp/p2ppn2/1p6/3NP3/1BN1BP2/PPPQ2PP/2KR3R b - - 2 11",
+            "2r3k1/pp3ppp/4p3/3pP3/3P1P2/P1R3P1/1P5P/6K1 w - - 0 30",
+            "8/5pk1/6p1/1p1P4/1P3P2/6PP/5K2/8 b - - 0 45",
+        ];
+
+        for fen in fens {
+            let pos = Position::from_fen(fen).unwrap();
+            assert_eq!(
+                pos.evaluate(),
+                pos.mirror().evaluate(),
+                "Asymmetric evaluation for: {}",
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn test_mop_up_drives_king_to_edge() {
+        setup();
+        let cornered = Position::from_fen("k7/8/8/8/8/8/8/1Q2K3 w - - 0 1").unwrap();
+        let centralized = Position::from_fen("8/8/8/3k4/8/8/8/1Q2K3 w - - 0 1").unwrap();
+        assert!(cornered.evaluate() > centralized.evaluate());
+
+        // Bringing the king closer helps too
+        let close = Position::from_fen("k7/8/2K5/8/8/8/8/1Q6 w - - 0 1").unwrap();
+        assert!(close.evaluate() > cornered.evaluate());
+    }
+
+    #[test]
+    fn test_stalemate_risk_penalty() {
+        setup();
+        // Black to move: Qb1 leaves the king only a7, Qc1 leaves a7 and b8. The king
+        // squares are identical, so only the stalemate term separates the two.
+        let boxed_in = Position::from_fen("k7/8/2K5/8/8/8/8/1Q6 b - - 0 1").unwrap();
+        let free = Position::from_fen("k7/8/2K5/8/8/8/8/2Q5 b - - 0 1").unwrap();
+        assert!(boxed_in.evaluate_detailed().kings.eg < free.evaluate_detailed().kings.eg);
+
+        // With white to move the same net is just good mop-up
+        let white_to_move = Position::from_fen("k7/8/2K5/8/8/8/8/1Q6 w - - 0 1").unwrap();
+        assert_eq!(
+            white_to_move.evaluate_detailed().kings.eg,
+            free.evaluate_detailed().kings.eg
+        );
+    }
+
+    #[test]
+    fn test_opposite_colored_bishops_scale_down() {
+        setup();
+        // White is a pawn up; c4 and e6 are light squares, d6 is dark
+        let ocb = Position::from_fen("4k3/5p2/3b2p1/8/2B5/6P1/5PP1/4K3 w - - 0 1").unwrap();
+        let same = Position::from_fen("4k3/5p2/4b1p1/8/2B5/6P1/5PP1/4K3 w - - 0 1").unwrap();
+
+        assert!(ocb.evaluate() > 0);
+        assert!(
+            ocb.evaluate() * 2 < same.evaluate(),
+            "OCB {} vs same-colored {}",
+            ocb.evaluate(),
+            same.evaluate()
+        );
+    }
+
+    #[test]
+    fn test_wrong_bishop_rook_pawn_is_drawn() {
+        setup();
+        // The e2 bishop is light-squared and h8 is dark; Black's king holds the corner
+        let wrong = Position::from_fen("7k/8/8/8/7P/8/4B3/K7 w - - 0 1").unwrap();
+        let right = Position::from_fen("7k/8/8/8/7P/8/3B4/K7 w - - 0 1").unwrap();
+
+        assert!(wrong.evaluate().abs() <= 30, "wrong bishop: {}", wrong.evaluate());
+        assert!(right.evaluate() > 300, "right bishop: {}", right.evaluate());
+        assert_eq!(wrong.mirror().evaluate(), wrong.evaluate());
+    }
+
+    #[test]
+    fn test_blockaded_krp_vs_kr_scales_down() {
+        setup();
+        // Black's king on e8 blockades the e4 pawn; on a8 it is cut off
+        let blocked = Position::from_fen("4k3/8/8/8/4P3/8/r7/4K2R w - - 0 1").unwrap();
+        let open = Position::from_fen("k7/8/8/8/4P3/8/r7/4K2R w - - 0 1").unwrap();
+
+        assert!(blocked.evaluate() > 0);
+        assert!(blocked.evaluate() * 2 < open.evaluate(), "{} vs {}", blocked.evaluate(), open.evaluate());
+    }
+
+    #[test]
+    fn test_imbalance_knight_likes_pawns() {
+        setup();
+        // White knight against black bishop, with many and with few pawns
+        let many = Position::from_fen("4k3/pppppppp/8/3b4/3N4/8/PPPPPPPP/4K3 w - - 0 1").unwrap();
+        let few = Position::from_fen("4k3/pp6/8/3b4/3N4/8/PP6/4K3 w - - 0 1").unwrap();
+
+        // Symmetric material has no imbalance
+        assert_eq!(Position::new().imbalance(), 0);
+
+        // The knight gains relative to the bishop as pawns are added
+        assert!(many.imbalance() > few.imbalance());
+
+        // The cached values, now hits, match a computation from scratch
+        assert_ne!(many.material_key(), few.material_key());
+        assert_eq!(many.imbalance(), many.compute_imbalance());
+        assert_eq!(few.imbalance(), few.compute_imbalance());
+        assert_ne!(many.compute_imbalance(), few.compute_imbalance());
+    }
+
+    #[test]
+    fn test_hanging_queen_penalty() {
+        setup();
+        // The rook on d8 attacks the queen; only the b3 knight defends it
+        let defended = Position::from_fen("3rk3/8/8/8/3Q4/1N6/8/4K3 w - - 0 1").unwrap();
+        let hanging = Position::from_fen("3rk3/8/8/8/3Q4/8/8/N3K3 w - - 0 1").unwrap();
+
+        let threats = |pos: &Position| pos.evaluate_detailed().threats;
+        assert!(threats(&hanging).mg < threats(&defended).mg);
+        assert!(
+            hanging.evaluate() + 50 < defended.evaluate(),
+            "hanging {} vs defended {}",
+            hanging.evaluate(),
+            defended.evaluate()
+        );
+    }
+
+    #[test]
+    fn test_pawn_threat_bonus() {
+        setup();
+        // The e5 pawn forks the knight on d6 and the rook on f6
+        let fork = Position::from_fen("4k3/8/3n1r2/4P3/8/8/8/4K3 b - - 0 1").unwrap();
+        let threats = fork.evaluate_detailed().threats;
+        assert!(threats.mg >= THREAT_BY_PAWN[1].mg + THREAT_BY_PAWN[3].mg);
+    }
+
+    #[test]
+    fn test_king_shelter() {
+        setup();
+        let intact = Position::from_fen("r5k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let pushed = Position::from_fen("r5k1/5ppp/8/8/5PPP/8/8/R5K1 w - - 0 1").unwrap();
+        let missing = Position::from_fen("r5k1/5ppp/8/8/8/8/PPP5/R5K1 w - - 0 1").unwrap();
+        let stormed = Position::from_fen("r5k1/5ppp/8/8/5ppp/8/5PPP/R5K1 w - - 0 1").unwrap();
+
+        let shelter = |pos: &Position| pos.evaluate_detailed().king_safety;
+        assert_eq!(shelter(&intact).mg, 0);
+        assert!(shelter(&pushed).mg < shelter(&intact).mg);
+        assert!(shelter(&missing).mg < shelter(&pushed).mg);
+        assert!(shelter(&stormed).mg < shelter(&intact).mg);
+
+        // Shelter only matters in the middlegame
+        assert_eq!(shelter(&missing).eg, 0);
+    }
+
+    #[test]
+    fn test_queen_on_open_file() {
+        setup();
+        // The d-file is open, the c-file is closed by the c2 pawn
+        let open = Position::from_fen("4k3/pp3ppp/8/8/8/8/PPP2PPP/3QK3 w - - 0 1").unwrap();
+        let closed = Position::from_fen("4k3/pp3ppp/8/8/8/8/PPP2PPP/2Q1K3 w - - 0 1").unwrap();
+
+        let rooks = |pos: &Position| pos.evaluate_detailed().rooks;
+        let gain = rooks(&open) - rooks(&closed);
+        assert_eq!((gain.mg, gain.eg), (QUEEN_OPEN_FILE.mg, QUEEN_OPEN_FILE.eg));
+        assert!(gain.mg > 0 && gain.mg < ROOK_OPEN_FILE.mg);
+    }
+
+    #[test]
+    fn test_trapped_rook() {
+        setup();
+        // The king walked to f1 instead of castling, boxing in the h1 rook
+        let trapped = Position::from_fen("4k3/8/8/8/8/8/5PPP/5K1R w - - 0 1").unwrap();
+        let castled = Position::from_fen("4k3/8/8/8/8/8/5PPP/5RK1 w - - 0 1").unwrap();
+        // Still allowed to castle, so the king hasn't moved yet
+        let home = Position::from_fen("4k3/8/8/8/8/8/5PPP/4K2R w K - 0 1").unwrap();
+
+        let rooks = |pos: &Position| pos.evaluate_detailed().rooks;
+        assert_eq!(rooks(&trapped).mg, TRAPPED_ROOK.mg);
+        assert_eq!(rooks(&castled).mg, 0);
+        assert_eq!(rooks(&home).mg, 0);
+    }
+
+    #[test]
+    fn test_evaluate_detailed_known_terms() {
+        setup();
+        let start = Position::from_fen(Position::STARTPOS).unwrap().evaluate_detailed();
+        for (name, score) in start.terms() {
+            assert_eq!(score, Score::ZERO, "{}", name);
+        }
+        assert_eq!(start.phase, TOTAL_PHASE);
+        assert_eq!(start.scale, SCALE_NORMAL);
+
+        // Two bishops and an isolated, passed a2 pawn against a bare king
+        let pos = Position::from_fen("4k3/8/8/8/8/8/P7/2B1KB2 w - - 0 1").unwrap();
+        let detailed = pos.evaluate_detailed();
+        assert_eq!(detailed.material, PIECE_VALUES[2] * 2 + PIECE_VALUES[0]);
+        assert_eq!(detailed.psqt, Score::new(-26, 158));
+        assert_eq!(detailed.pawns, ISOLATED_PAWN + PASSED_PAWN_BONUS[1]);
+        assert_eq!(detailed.bishop_pair, BISHOP_PAIR);
+        assert_eq!(detailed.rooks, Score::ZERO);
+        assert_eq!(detailed.imbalance, Score::new(12, 12));
+        assert_eq!(detailed.kings, Score::new(0, 58));
+        assert_eq!(detailed.threats, Score::ZERO);
+        assert_eq!(detailed.king_safety, Score::ZERO);
+        assert_eq!(detailed.phase, 2);
+        assert_eq!(detailed.scale, SCALE_NORMAL);
+
+        // (818 * 2 + 956 * 22) / 24
+        assert_eq!(detailed.total(), Score::new(818, 956));
+        assert_eq!(detailed.tapered(), 944);
+        assert_eq!(pos.evaluate(), 944);
+    }
+
+    #[test]
+    fn test_insufficient_material_evaluates_to_zero() {
+        setup();
+        for bishop in ["a1", "d4", "h8", "b7", "e6"] {
+            for side in ["w", "b"] {
+                let mut pos = Position::from_fen(&format!("4k3/8/8/8/8/8/8/4K3 {} - - 0 1", side))
+                    .unwrap();
+                let sq = Square::from_algebraic(bishop).unwrap();
+                pos.put_piece(sq, crate::types::Piece::new(Color::White, PieceType::Bishop));
+                assert_eq!(pos.evaluate(), 0, "KBvK with bishop on {}", bishop);
+            }
+        }
+    }
+
     #[test]
     fn test_passed_pawn_bonus() {
         setup();
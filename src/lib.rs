@@ -1,3 +1,11 @@
+/// Engine name reported to GUIs (UCI "id name", XBoard "myname")
+pub const ENGINE_NAME: &str = "Kai";
+/// Engine version reported alongside the name
+pub const ENGINE_VERSION: &str = "1.0";
+/// Engine author reported by UCI "id author"
+pub const ENGINE_AUTHOR: &str = "Sahith Jagarlamudi";
+
+pub mod debug;
 pub mod types;
 pub mod bitboard;
 pub mod magic;
@@ -6,11 +14,19 @@ pub mod moves;
 pub mod movegen;
 pub mod make_move;
 pub mod zobrist;
+pub mod cuckoo;
 pub mod tt;
 pub mod eval;
 pub mod search;
+pub mod timeman;
 pub mod qsearch;
 pub mod see;
 pub mod ordering;
 pub mod uci;
+pub mod xboard;
 pub mod perft;
+pub mod pgn;
+#[cfg(test)]
+mod test_util;
+#[cfg(feature = "tuner")]
+pub mod tuning;
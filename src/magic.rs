@@ -1,6 +1,8 @@
 /// Magic bitboard implementation for sliding piece attack generation
 use crate::bitboard::Bitboard;
 use crate::types::Square;
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+use std::sync::atomic::{AtomicBool, Ordering};
 
 /// Magic entry for a single square
 #[derive(Clone, Copy)]
@@ -20,6 +22,17 @@ pub static BISHOP_MAGICS: [Magic; 64] = init_bishop_magics();
 pub static mut BISHOP_ATTACKS: [Bitboard; 5248] = [Bitboard::EMPTY; 5248];
 static BISHOP_OFFSETS: [usize; 64] = init_bishop_offsets();
 
+/// Magics searched for at init, replacing the precomputed ones
+#[cfg(feature = "generated-magics")]
+static mut GENERATED_ROOK_MAGICS: [Magic; 64] = init_rook_magics();
+#[cfg(feature = "generated-magics")]
+static mut GENERATED_BISHOP_MAGICS: [Magic; 64] = init_bishop_magics();
+
+/// Set during init when the CPU supports BMI2; the attack tables are then laid out
+/// in PEXT order instead of magic order
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+static USE_PEXT: AtomicBool = AtomicBool::new(false);
+
 /// Number of relevant bits for rook at each square
 const ROOK_BITS: [u8; 64] = [
     12, 11, 11, 11, 11, 11, 11, 12,
@@ -89,21 +102,71 @@ pub fn init_magics() {
     use std::sync::Once;
     static INIT: Once = Once::new();
     INIT.call_once(|| {
+        #[cfg(feature = "generated-magics")]
+        init_magics_generated();
+
+        #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+        USE_PEXT.store(is_x86_feature_detected!("bmi2"), Ordering::Relaxed);
+
         init_rook_attacks();
         init_bishop_attacks();
     });
 }
 
+/// Replace the hardcoded magic numbers with ones searched for at startup
+#[cfg(feature = "generated-magics")]
+fn init_magics_generated() {
+    let mut rng = MagicRng(MAGIC_SEED);
+    for sq in 0..64 {
+        let rook = find_magic(
+            Square(sq as u8),
+            ROOK_MAGICS[sq].mask,
+            ROOK_BITS[sq],
+            slow_rook_attacks,
+            &mut rng,
+        );
+        let bishop = find_magic(
+            Square(sq as u8),
+            BISHOP_MAGICS[sq].mask,
+            BISHOP_BITS[sq],
+            slow_bishop_attacks,
+            &mut rng,
+        );
+        unsafe {
+            GENERATED_ROOK_MAGICS[sq].magic = rook;
+            GENERATED_BISHOP_MAGICS[sq].magic = bishop;
+        }
+    }
+}
+
+/// Magic entry used for rook lookups on `sq`
+#[inline(always)]
+fn rook_magic(sq: usize) -> Magic {
+    #[cfg(feature = "generated-magics")]
+    return unsafe { GENERATED_ROOK_MAGICS[sq] };
+    #[cfg(not(feature = "generated-magics"))]
+    ROOK_MAGICS[sq]
+}
+
+/// Magic entry used for bishop lookups on `sq`
+#[inline(always)]
+fn bishop_magic(sq: usize) -> Magic {
+    #[cfg(feature = "generated-magics")]
+    return unsafe { GENERATED_BISHOP_MAGICS[sq] };
+    #[cfg(not(feature = "generated-magics"))]
+    BISHOP_MAGICS[sq]
+}
+
 fn init_rook_attacks() {
     for sq in 0..64 {
-        let magic = &ROOK_MAGICS[sq];
+        let magic = rook_magic(sq);
         let mask = magic.mask;
         let n = mask.pop_count();
         let num_occupancies = 1 << n;
 
         for i in 0..num_occupancies {
             let occupied = index_to_occupancy(i, mask);
-            let index = magic_index(occupied, magic.magic, magic.shift);
+            let index = table_index(i, occupied, magic);
             let attacks = slow_rook_attacks(Square(sq as u8), occupied);
             unsafe {
                 ROOK_ATTACKS[ROOK_OFFSETS[sq] + index] = attacks;
@@ -114,14 +177,14 @@ fn init_rook_attacks() {
 
 fn init_bishop_attacks() {
     for sq in 0..64 {
-        let magic = &BISHOP_MAGICS[sq];
+        let magic = bishop_magic(sq);
         let mask = magic.mask;
         let n = mask.pop_count();
         let num_occupancies = 1 << n;
 
         for i in 0..num_occupancies {
             let occupied = index_to_occupancy(i, mask);
-            let index = magic_index(occupied, magic.magic, magic.shift);
+            let index = table_index(i, occupied, magic);
             let attacks = slow_bishop_attacks(Square(sq as u8), occupied);
             unsafe {
                 BISHOP_ATTACKS[BISHOP_OFFSETS[sq] + index] = attacks;
@@ -130,24 +193,75 @@ fn init_bishop_attacks() {
     }
 }
 
+/// Slot within a square's table for `occupied`, the `i`-th occupancy of the mask. With
+/// PEXT the slot is `i` itself, as PEXT over the mask undoes `index_to_occupancy`; the
+/// dense per-square offsets fit both layouts.
+#[inline(always)]
+fn table_index(i: usize, occupied: Bitboard, magic: Magic) -> usize {
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    if USE_PEXT.load(Ordering::Relaxed) {
+        return i;
+    }
+    let _ = i;
+    magic_index(occupied, magic.magic, magic.shift)
+}
+
 /// Get rook attacks for a square given an occupancy bitboard
 #[inline(always)]
 pub fn rook_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
-    let magic = &ROOK_MAGICS[sq.0 as usize];
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    if USE_PEXT.load(Ordering::Relaxed) {
+        return unsafe { pext_rook_attacks(sq, occupied) };
+    }
+    magic_rook_attacks(sq, occupied)
+}
+
+/// Get bishop attacks for a square given an occupancy bitboard
+#[inline(always)]
+pub fn bishop_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    if USE_PEXT.load(Ordering::Relaxed) {
+        return unsafe { pext_bishop_attacks(sq, occupied) };
+    }
+    magic_bishop_attacks(sq, occupied)
+}
+
+/// Rook attacks via magic multiply-shift indexing
+#[inline(always)]
+fn magic_rook_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    let magic = rook_magic(sq.0 as usize);
     let blockers = occupied & magic.mask;
     let index = magic_index(blockers, magic.magic, magic.shift);
     unsafe { ROOK_ATTACKS[ROOK_OFFSETS[sq.0 as usize] + index] }
 }
 
-/// Get bishop attacks for a square given an occupancy bitboard
+/// Bishop attacks via magic multiply-shift indexing
 #[inline(always)]
-pub fn bishop_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
-    let magic = &BISHOP_MAGICS[sq.0 as usize];
+fn magic_bishop_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    let magic = bishop_magic(sq.0 as usize);
     let blockers = occupied & magic.mask;
     let index = magic_index(blockers, magic.magic, magic.shift);
     unsafe { BISHOP_ATTACKS[BISHOP_OFFSETS[sq.0 as usize] + index] }
 }
 
+/// Rook attacks via PEXT indexing (caller must ensure BMI2 is available)
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+#[target_feature(enable = "bmi2")]
+unsafe fn pext_rook_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    let mask = ROOK_MAGICS[sq.0 as usize].mask;
+    let index = std::arch::x86_64::_pext_u64(occupied.0, mask.0) as usize;
+    ROOK_ATTACKS[ROOK_OFFSETS[sq.0 as usize] + index]
+}
+
+/// Bishop attacks via PEXT indexing (caller must ensure BMI2 is available)
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+#[target_feature(enable = "bmi2")]
+unsafe fn pext_bishop_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    let mask = BISHOP_MAGICS[sq.0 as usize].mask;
+    let index = std::arch::x86_64::_pext_u64(occupied.0, mask.0) as usize;
+    BISHOP_ATTACKS[BISHOP_OFFSETS[sq.0 as usize] + index]
+}
+
 /// Get queen attacks (combination of rook and bishop)
 #[inline(always)]
 pub fn queen_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
@@ -160,6 +274,80 @@ fn magic_index(blockers: Bitboard, magic: u64, shift: u8) -> usize {
     ((blockers.0.wrapping_mul(magic)) >> shift) as usize
 }
 
+/// Seed for runtime magic generation (fixed so generated tables are reproducible)
+#[cfg(feature = "generated-magics")]
+const MAGIC_SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+/// Attack generator signature shared by the slow rook/bishop functions
+#[cfg(feature = "generated-magics")]
+type AttackFn = fn(Square, Bitboard) -> Bitboard;
+
+/// Xorshift64 PRNG used for magic generation
+#[cfg(feature = "generated-magics")]
+struct MagicRng(u64);
+
+#[cfg(feature = "generated-magics")]
+impl MagicRng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Random number with few bits set (better magic candidates)
+    fn sparse(&mut self) -> u64 {
+        self.next() & self.next() & self.next()
+    }
+}
+
+/// Search for a magic that indexes every occupancy of `mask` into `bits` bits
+/// without destructive collisions, verified against the slow attack generator
+#[cfg(feature = "generated-magics")]
+fn find_magic(
+    sq: Square,
+    mask: Bitboard,
+    bits: u8,
+    slow_attacks: AttackFn,
+    rng: &mut MagicRng,
+) -> u64 {
+    let num_occupancies = 1usize << mask.pop_count();
+    let occupancies: Vec<Bitboard> = (0..num_occupancies)
+        .map(|i| index_to_occupancy(i, mask))
+        .collect();
+    let attacks: Vec<Bitboard> = occupancies
+        .iter()
+        .map(|&occupied| slow_attacks(sq, occupied))
+        .collect();
+    let shift = 64 - bits;
+    let mut used: Vec<Option<Bitboard>> = vec![None; 1 << bits];
+
+    loop {
+        let magic = rng.sparse();
+
+        // Quick reject: the high bits of mask * magic must be well populated
+        if (mask.0.wrapping_mul(magic) & 0xFF00_0000_0000_0000).count_ones() < 6 {
+            continue;
+        }
+
+        used.fill(None);
+        let valid = occupancies.iter().zip(&attacks).all(|(&occupied, &attack)| {
+            let index = magic_index(occupied, magic, shift);
+            match used[index] {
+                None => {
+                    used[index] = Some(attack);
+                    true
+                }
+                Some(existing) => existing == attack,
+            }
+        });
+
+        if valid {
+            return magic;
+        }
+    }
+}
+
 /// Convert an index to an occupancy bitboard (for generating all occupancy patterns)
 fn index_to_occupancy(index: usize, mask: Bitboard) -> Bitboard {
     let mut result = Bitboard::EMPTY;
@@ -328,7 +516,7 @@ const fn bishop_mask(sq: u8) -> Bitboard {
     // NW diagonal
     f = file.wrapping_sub(1);
     r = rank + 1;
-    while f < 7 && r < 7 && f < 8 {
+    while f > 0 && f < 7 && r < 7 {
         mask |= 1u64 << (r * 8 + f);
         f = f.wrapping_sub(1);
         r += 1;
@@ -337,7 +525,7 @@ const fn bishop_mask(sq: u8) -> Bitboard {
     // SE diagonal
     f = file + 1;
     r = rank.wrapping_sub(1);
-    while f < 7 && r < 7 && r < 8 {
+    while f < 7 && r > 0 && r < 7 {
         mask |= 1u64 << (r * 8 + f);
         f += 1;
         r = r.wrapping_sub(1);
@@ -346,7 +534,7 @@ const fn bishop_mask(sq: u8) -> Bitboard {
     // SW diagonal
     f = file.wrapping_sub(1);
     r = rank.wrapping_sub(1);
-    while f < 7 && r < 7 && f < 8 && r < 8 {
+    while f > 0 && f < 7 && r > 0 && r < 7 {
         mask |= 1u64 << (r * 8 + f);
         f = f.wrapping_sub(1);
         r = r.wrapping_sub(1);
@@ -426,6 +614,8 @@ const fn init_bishop_offsets() -> [usize; 64] {
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    use crate::test_util::TestRng;
 
     fn setup() {
         static INIT: std::sync::Once = std::sync::Once::new();
@@ -483,4 +673,75 @@ mod tests {
         let attacks = bishop_attacks(Square::A1, Bitboard::EMPTY);
         assert_eq!(attacks.pop_count(), 7);
     }
+
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    #[test]
+    fn test_pext_matches_slow_attacks() {
+        setup();
+        if !is_x86_feature_detected!("bmi2") {
+            return;
+        }
+        // The shared tables are in PEXT order, so check lookups against the slow generators
+        assert!(USE_PEXT.load(Ordering::Relaxed));
+
+        let mut rng = TestRng::default();
+        for sq in [0u8, 7, 27, 36, 45, 56, 63] {
+            let sq = Square(sq);
+            for _ in 0..1000 {
+                let occupied = Bitboard(rng.next_u64() & rng.next_u64());
+                unsafe {
+                    assert_eq!(pext_rook_attacks(sq, occupied), slow_rook_attacks(sq, occupied));
+                    assert_eq!(
+                        pext_bishop_attacks(sq, occupied),
+                        slow_bishop_attacks(sq, occupied)
+                    );
+                }
+            }
+        }
+    }
+
+    /// Runs the full magic search, so only with `--features generated-magics`
+    #[cfg(feature = "generated-magics")]
+    #[test]
+    fn test_generated_and_hardcoded_magics_match_slow_attacks() {
+        setup();
+        let mut rng = MagicRng(MAGIC_SEED);
+        let mut occupancy_rng = MagicRng(0x9E37_79B9_7F4A_7C15);
+
+        for sq in 0..64 {
+            let square = Square(sq as u8);
+            let (rook, bishop) = (ROOK_MAGICS[sq], BISHOP_MAGICS[sq]);
+            let cases: [(Magic, u8, AttackFn, AttackFn); 2] = [
+                (rook, ROOK_BITS[sq], slow_rook_attacks, rook_attacks),
+                (bishop, BISHOP_BITS[sq], slow_bishop_attacks, bishop_attacks),
+            ];
+
+            for (hardcoded, bits, slow, lookup) in cases {
+                let generated = find_magic(square, hardcoded.mask, bits, slow, &mut rng);
+                let shift = 64 - bits;
+
+                // Build a table for each magic independently of the live lookup tables
+                let build = |magic: u64, shift: u8| {
+                    let mut table = vec![Bitboard::EMPTY; 1 << (64 - shift)];
+                    for i in 0..(1usize << hardcoded.mask.pop_count()) {
+                        let occupied = index_to_occupancy(i, hardcoded.mask);
+                        table[magic_index(occupied, magic, shift)] = slow(square, occupied);
+                    }
+                    table
+                };
+                let hardcoded_table = build(hardcoded.magic, hardcoded.shift);
+                let generated_table = build(generated, shift);
+
+                for _ in 0..256 {
+                    let occupied = Bitboard(occupancy_rng.next());
+                    let blockers = occupied & hardcoded.mask;
+                    let expected = slow(square, occupied);
+                    let hardcoded_index = magic_index(blockers, hardcoded.magic, hardcoded.shift);
+                    assert_eq!(hardcoded_table[hardcoded_index], expected, "square {}", sq);
+                    assert_eq!(generated_table[magic_index(blockers, generated, shift)], expected, "square {}", sq);
+                    assert_eq!(lookup(square, occupied), expected, "square {}", sq);
+                }
+            }
+        }
+    }
 }
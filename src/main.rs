@@ -1,29 +1,11 @@
-/// Kai Chess Engine - Main entry point
-///
-/// Supports both UCI (Universal Chess Interface) and XBoard/WinBoard protocols.
-/// The protocol is auto-detected based on the first command received.
-
-mod bitboard;
-mod eval;
-mod magic;
-mod make_move;
-mod movegen;
-mod moves;
-mod ordering;
-mod perft;
-mod position;
-mod qsearch;
-mod search;
-mod see;
-mod tt;
-mod types;
-mod uci;
-mod xboard;
-mod zobrist;
+//! Kai Chess Engine - Main entry point
+//!
+//! Supports both UCI (Universal Chess Interface) and XBoard/WinBoard protocols.
+//! The protocol is auto-detected based on the first command received.
 
+use kai::uci::UciEngine;
+use kai::xboard::XBoardEngine;
 use std::io::{self, BufRead, Write};
-use uci::UciEngine;
-use xboard::XBoardEngine;
 
 /// Protocol type
 enum Protocol {
@@ -32,6 +14,15 @@ enum Protocol {
 }
 
 fn main() {
+    #[cfg(feature = "tuner")]
+    {
+        let args: Vec<String> = std::env::args().collect();
+        if args.get(1).map(String::as_str) == Some("tune") {
+            kai::tuning::run_cli(&args[2..]);
+            return;
+        }
+    }
+
     let stdin = io::stdin();
     let mut stdout = io::stdout();
 
@@ -54,7 +45,7 @@ fn main() {
     } else {
         // Default to UCI for unrecognized first commands
         // But handle common cases
-        match first_cmd {
+        match first_cmd.split_whitespace().next().unwrap_or("") {
             // XBoard-style commands
             "protover" | "new" | "force" | "go" | "quit" | "random" | "post" | "nopost"
             | "hard" | "easy" | "ping" | "draw" | "result" | "setboard" | "edit"
@@ -79,16 +70,10 @@ fn main() {
 
             // Handle the first command that was already read
             if first_cmd == "uci" {
-                // Process uci command
-                writeln!(stdout, "id name Kai 1.0").unwrap();
-                writeln!(stdout, "id author Sahith Jagarlamudi").unwrap();
-                writeln!(stdout).unwrap();
-                writeln!(
-                    stdout,
-                    "option name Hash type spin default 64 min 1 max 4096"
-                ).unwrap();
-                writeln!(stdout, "uciok").unwrap();
-                stdout.flush().unwrap();
+                engine.cmd_uci(&mut stdout);
+            } else if first_cmd.starts_with("bench") {
+                let tokens: Vec<&str> = first_cmd.split_whitespace().collect();
+                engine.cmd_bench(&tokens[1..]);
             }
 
             // Continue with the UCI loop
@@ -104,8 +89,7 @@ fn main() {
                 stdout.flush().unwrap();
             } else if first_cmd.starts_with("protover") {
                 // Handle protover immediately
-                let tokens: Vec<&str> = first_cmd.split_whitespace().collect();
-                handle_protover(&tokens[1..], &mut stdout);
+                engine.cmd_protover(&mut stdout);
             }
 
             // Continue with the XBoard loop
@@ -113,32 +97,3 @@ fn main() {
         }
     }
 }
-
-/// Handle protover command for initial detection
-fn handle_protover(_tokens: &[&str], stdout: &mut io::Stdout) {
-    writeln!(stdout, "feature done=0").unwrap();
-    writeln!(stdout, "feature myname=\"Kai 1.0\"").unwrap();
-    writeln!(stdout, "feature variants=\"normal\"").unwrap();
-    writeln!(stdout, "feature setboard=1").unwrap();
-    writeln!(stdout, "feature ping=1").unwrap();
-    writeln!(stdout, "feature playother=1").unwrap();
-    writeln!(stdout, "feature san=0").unwrap();
-    writeln!(stdout, "feature usermove=1").unwrap();
-    writeln!(stdout, "feature time=1").unwrap();
-    writeln!(stdout, "feature draw=1").unwrap();
-    writeln!(stdout, "feature sigint=0").unwrap();
-    writeln!(stdout, "feature sigterm=0").unwrap();
-    writeln!(stdout, "feature reuse=1").unwrap();
-    writeln!(stdout, "feature analyze=1").unwrap();
-    writeln!(stdout, "feature colors=0").unwrap();
-    writeln!(stdout, "feature ics=0").unwrap();
-    writeln!(stdout, "feature name=1").unwrap();
-    writeln!(stdout, "feature pause=0").unwrap();
-    writeln!(stdout, "feature nps=0").unwrap();
-    writeln!(stdout, "feature debug=1").unwrap();
-    writeln!(stdout, "feature memory=1").unwrap();
-    writeln!(stdout, "feature smp=0").unwrap();
-    writeln!(stdout, "feature egt=\"\"").unwrap();
-    writeln!(stdout, "feature done=1").unwrap();
-    stdout.flush().unwrap();
-}
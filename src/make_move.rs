@@ -1,37 +1,110 @@
-/// Make move implementation (copy-make approach)
+/// Make move implementation (copy-make and make/unmake)
+use crate::bitboard::Bitboard;
 use crate::moves::Move;
-use crate::position::Position;
+use crate::position::{CheckInfo, Position};
 use crate::types::{CastlingRights, Color, Piece, PieceType, Square};
-use crate::zobrist::ZOBRIST;
 
-/// Castling rights update table - indexed by square
-/// When a piece moves from or to a square, AND with this mask
-const CASTLING_RIGHTS_UPDATE: [u8; 64] = {
-    let mut table = [0x0Fu8; 64]; // All rights preserved by default
-
-    // White pieces
-    table[Square::A1.0 as usize] = 0x0D; // Remove white queenside
-    table[Square::E1.0 as usize] = 0x0C; // Remove both white
-    table[Square::H1.0 as usize] = 0x0E; // Remove white kingside
-
-    // Black pieces
-    table[Square::A8.0 as usize] = 0x07; // Remove black queenside
-    table[Square::E8.0 as usize] = 0x03; // Remove both black
-    table[Square::H8.0 as usize] = 0x0B; // Remove black kingside
-
-    table
-};
+/// State needed to reverse a move applied with `apply_move`
+#[derive(Debug, Clone, Copy)]
+pub struct UndoInfo {
+    /// Move that was applied (NULL if the source square was empty)
+    pub mv: Move,
+    pub captured: Option<Piece>,
+    pub castling: CastlingRights,
+    pub en_passant: Option<Square>,
+    pub halfmove_clock: u8,
+    pub plies_from_null: u8,
+    pub hash: u64,
+    pub checkers: Bitboard,
+    pub check_info: CheckInfo,
+}
 
 impl Position {
     /// Make a move and return the new position (copy-make approach)
     pub fn make_move(&self, mv: Move) -> Self {
         let mut new = self.clone();
-        new.apply_move(mv);
+        new.do_move(mv);
         new
     }
 
-    /// Apply a move to the position (modifies in place)
-    fn apply_move(&mut self, mv: Move) {
+    /// Apply a move in place, pushing an undo record onto `undo_stack`
+    pub fn apply_move(&mut self, mv: Move, undo_stack: &mut Vec<UndoInfo>) {
+        let mut undo = UndoInfo {
+            mv: if self.board[mv.from_sq().0 as usize].is_some() {
+                mv
+            } else {
+                Move::NULL
+            },
+            captured: None,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            plies_from_null: self.plies_from_null,
+            hash: self.hash,
+            checkers: self.checkers,
+            check_info: self.check_info,
+        };
+        undo.captured = self.do_move(mv);
+        undo_stack.push(undo);
+    }
+
+    /// Reverse the last move recorded on `undo_stack`
+    pub fn undo_move(&mut self, undo_stack: &mut Vec<UndoInfo>) {
+        let undo = undo_stack
+            .pop()
+            .expect("undo_move called with an empty undo stack");
+        let us = self.side_to_move.flip();
+        let mv = undo.mv;
+
+        if !mv.is_null() {
+            let from = mv.from_sq();
+            let to = mv.to_sq();
+
+            // Lift both castling pieces before restoring either, as their squares may overlap
+            if mv.is_castle() {
+                let (king_to, rook_to) = mv.castling_destinations();
+                self.remove_piece(king_to);
+                self.remove_piece(rook_to);
+                self.put_piece(from, Piece::new(us, PieceType::King));
+                self.put_piece(to, Piece::new(us, PieceType::Rook));
+            } else {
+                let moved = self.remove_piece(to);
+                let piece_type = if mv.is_promotion() {
+                    PieceType::Pawn
+                } else {
+                    moved.map_or(PieceType::Pawn, |p| p.piece_type())
+                };
+                self.put_piece(from, Piece::new(us, piece_type));
+
+                if let Some(captured) = undo.captured {
+                    let captured_sq = if mv.is_en_passant() {
+                        Square((to.0 as i8 + if us == Color::White { -8 } else { 8 }) as u8)
+                    } else {
+                        to
+                    };
+                    self.put_piece(captured_sq, captured);
+                }
+            }
+        }
+
+        if us == Color::Black {
+            self.fullmove_number -= 1;
+        }
+        self.side_to_move = us;
+        self.castling = undo.castling;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.plies_from_null = undo.plies_from_null;
+        self.hash = undo.hash;
+        self.checkers = undo.checkers;
+        self.check_info = undo.check_info;
+
+        debug_assert_eq!(self.hash, self.compute_hash(), "hash diverged undoing {}", mv.to_uci());
+        debug_assert_eq!(self.checkers, self.compute_checkers(), "checkers diverged undoing {}", mv.to_uci());
+    }
+
+    /// Apply a move to the position in place, returning the captured piece
+    fn do_move(&mut self, mv: Move) -> Option<Piece> {
         let us = self.side_to_move;
         let them = us.flip();
         let from = mv.from_sq();
@@ -45,15 +118,15 @@ impl Position {
                 // Invalid move - just flip the side to move and return
                 // This shouldn't happen but prevents crashes
                 self.side_to_move = them;
-                self.hash ^= crate::zobrist::ZOBRIST.side_key();
-                return;
+                self.toggle_side_hash();
+                return None;
             }
         };
         let piece_type = piece.piece_type();
 
         // Update en passant (remove old EP square from hash)
         if let Some(ep_sq) = self.en_passant {
-            self.hash ^= ZOBRIST.en_passant_key(ep_sq.file());
+            self.toggle_en_passant_hash(ep_sq);
         }
         self.en_passant = None;
 
@@ -75,53 +148,51 @@ impl Position {
             None
         };
 
-        // Move the piece
-        self.remove_piece_internal(from, us, piece_type);
-
-        // Handle promotions
-        let final_piece_type = if mv.is_promotion() {
-            mv.promotion_piece()
-        } else {
-            piece_type
-        };
-
-        self.put_piece_internal(to, us, final_piece_type);
-
-        // Handle castling
         if mv.is_castle() {
-            let (rook_from, rook_to) = if mv.is_kingside_castle() {
-                match us {
-                    Color::White => (Square::H1, Square::F1),
-                    Color::Black => (Square::H8, Square::F8),
-                }
+            // The king "captures" its own rook: lift both, then drop them on their targets
+            let (king_to, rook_to) = mv.castling_destinations();
+            self.remove_piece_internal(from, us, PieceType::King);
+            self.remove_piece_internal(to, us, PieceType::Rook);
+            self.put_piece_internal(king_to, us, PieceType::King);
+            self.put_piece_internal(rook_to, us, PieceType::Rook);
+        } else {
+            // Move the piece, promoting if needed
+            self.remove_piece_internal(from, us, piece_type);
+            let final_piece_type = if mv.is_promotion() {
+                mv.promotion_piece()
             } else {
-                match us {
-                    Color::White => (Square::A1, Square::D1),
-                    Color::Black => (Square::A8, Square::D8),
-                }
+                piece_type
             };
-
-            self.remove_piece_internal(rook_from, us, PieceType::Rook);
-            self.put_piece_internal(rook_to, us, PieceType::Rook);
+            self.put_piece_internal(to, us, final_piece_type);
         }
 
-        // Update castling rights
-        let old_castling = self.castling;
-        self.castling = CastlingRights(
-            self.castling.0
-                & CASTLING_RIGHTS_UPDATE[from.0 as usize]
-                & CASTLING_RIGHTS_UPDATE[to.0 as usize],
-        );
-        if self.castling != old_castling {
-            self.hash ^= ZOBRIST.castling_key(old_castling);
-            self.hash ^= ZOBRIST.castling_key(self.castling);
+        // Update castling rights: a king move loses both, and touching a castling rook's
+        // home square (moving from it or capturing on it) loses that rook's right
+        if !self.castling.is_empty() {
+            let old_castling = self.castling;
+            let mut lost = if piece_type == PieceType::King {
+                CastlingRights::both(us)
+            } else {
+                CastlingRights::NONE
+            };
+            for right in CastlingRights::SINGLE {
+                let rook_sq = self.castling_rook(right);
+                if rook_sq == from || rook_sq == to {
+                    lost |= right;
+                }
+            }
+            self.castling = self.castling.remove(lost);
+            if self.castling != old_castling {
+                self.toggle_castling_hash(old_castling);
+                self.toggle_castling_hash(self.castling);
+            }
         }
 
         // Set en passant square for double pawn pushes
         if mv.is_double_push() {
             let ep_sq = Square((from.0 as i8 + if us == Color::White { 8 } else { -8 }) as u8);
             self.en_passant = Some(ep_sq);
-            self.hash ^= ZOBRIST.en_passant_key(ep_sq.file());
+            self.toggle_en_passant_hash(ep_sq);
         }
 
         // Update halfmove clock
@@ -130,6 +201,7 @@ impl Position {
         } else {
             self.halfmove_clock += 1;
         }
+        self.plies_from_null = self.plies_from_null.saturating_add(1);
 
         // Update fullmove number
         if us == Color::Black {
@@ -138,10 +210,22 @@ impl Position {
 
         // Switch side to move
         self.side_to_move = them;
-        self.hash ^= ZOBRIST.side_key();
+        self.toggle_side_hash();
 
-        // Update checkers
+        // Update checkers, pins and check squares
         self.checkers = self.compute_checkers();
+        self.check_info = self.compute_check_info();
+
+        // Debug builds (and so the tests) verify the incremental hash on every move
+        debug_assert_eq!(
+            self.hash,
+            self.compute_hash(),
+            "incremental hash diverged after {} in {}",
+            mv.to_uci(),
+            self.to_fen()
+        );
+
+        captured
     }
 
     /// Make a null move (pass) - for null move pruning
@@ -150,16 +234,22 @@ impl Position {
 
         // Remove en passant from hash
         if let Some(ep_sq) = new.en_passant {
-            new.hash ^= ZOBRIST.en_passant_key(ep_sq.file());
+            new.toggle_en_passant_hash(ep_sq);
         }
         new.en_passant = None;
 
+        // Nothing before a null move can be repeated after it
+        new.plies_from_null = 0;
+
         // Switch side
         new.side_to_move = new.side_to_move.flip();
-        new.hash ^= ZOBRIST.side_key();
+        new.toggle_side_hash();
 
         // Update checkers (should be empty after null move if legal)
         new.checkers = new.compute_checkers();
+        new.check_info = new.compute_check_info();
+
+        debug_assert_eq!(new.hash, new.compute_hash(), "incremental hash diverged after null move");
 
         new
     }
@@ -173,7 +263,7 @@ impl Position {
         self.board[sq.0 as usize] = None;
 
         // Update hash
-        self.hash ^= ZOBRIST.piece_key(color, piece_type, sq);
+        self.toggle_piece_hash(color, piece_type, sq);
     }
 
     /// Internal helper to put a piece and update hash
@@ -190,7 +280,7 @@ impl Position {
         }
 
         // Update hash
-        self.hash ^= ZOBRIST.piece_key(color, piece_type, sq);
+        self.toggle_piece_hash(color, piece_type, sq);
     }
 
     /// Parse and make a move from UCI notation
@@ -199,7 +289,8 @@ impl Position {
         Some(self.make_move(mv))
     }
 
-    /// Parse a UCI move string
+    /// Parse a UCI move string. Castling is accepted both as the king's two-square move
+    /// ("e1g1") and as king-takes-rook ("e1h1", the Chess960 form).
     pub fn parse_uci_move(&self, uci: &str) -> Option<Move> {
         if uci.len() < 4 {
             return None;
@@ -233,7 +324,11 @@ impl Position {
             }
         }
 
-        None
+        // Standard castling notation names the king's destination instead of the rook
+        let castle = list
+            .iter()
+            .find(|mv| mv.is_castle() && mv.from_sq() == from && mv.castling_destinations().0 == to);
+        castle
     }
 }
 
@@ -242,6 +337,7 @@ impl Position {
 mod tests {
     use super::*;
     use crate::magic::init_magics;
+    use crate::test_util::{random_move, TestRng};
 
     fn setup() {
         static INIT: std::sync::Once = std::sync::Once::new();
@@ -354,6 +450,47 @@ mod tests {
         assert_eq!(new_pos.hash, new_pos.compute_hash());
     }
 
+    /// Check the checkers and pins kept by make_move against a position parsed afresh
+    fn assert_matches_fresh(pos: &Position, after: &str) {
+        let fresh = Position::from_fen(&pos.to_fen()).unwrap();
+        assert_eq!(pos.checkers, fresh.checkers, "checkers after {} in {}", after, pos.to_fen());
+        assert_eq!(pos.check_info, fresh.check_info, "pins after {} in {}", after, pos.to_fen());
+    }
+
+    #[test]
+    fn test_random_games_keep_hash_and_checkers() {
+        setup();
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ];
+
+        let mut rng = TestRng::default();
+
+        for fen in fens {
+            for _ in 0..25 {
+                let mut pos = Position::from_fen(fen).unwrap();
+                for _ in 0..80 {
+                    let Some(mv) = random_move(&pos, &mut rng) else {
+                        break;
+                    };
+                    pos = pos.make_move(mv);
+                    assert_eq!(pos.hash, pos.compute_hash(), "after {}", mv.to_uci());
+                    assert_matches_fresh(&pos, &mv.to_uci());
+
+                    // Now and then pass instead, as null-move pruning does
+                    if !pos.is_in_check() && rng.below(8) == 0 {
+                        pos = pos.make_null_move();
+                        assert_eq!(pos.hash, pos.compute_hash(), "after null move");
+                        assert_matches_fresh(&pos, "null move");
+                    }
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_hash_changes_on_move() {
         setup();
@@ -363,6 +500,54 @@ mod tests {
         assert_ne!(pos.hash, new_pos.hash);
     }
 
+    #[test]
+    fn test_apply_undo_restores_position() {
+        setup();
+        let cases = [
+            // Quiet moves, double push, and capture
+            (Position::STARTPOS, vec!["e2e4", "d7d5", "e4d5", "g8f6"]),
+            // En passant
+            ("rnbqkbnr/pppp1ppp/8/4pP2/8/8/PPPPP1PP/RNBQKBNR w KQkq e6 0 1", vec!["f5e6"]),
+            // Castling both ways, and a rook capture removing castling rights
+            ("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1", vec!["e1g1", "e8c8"]),
+            (
+                "r3k2r/1p4p1/8/8/8/8/1P4P1/R3K2R w KQkq - 0 1",
+                vec!["a1a8", "e8e7", "h1h8"],
+            ),
+            // Chess960 castling where king and rook swap squares, or the king stays put
+            ("4k3/8/8/8/8/8/8/5KR1 w G - 0 1", vec!["f1g1"]),
+            ("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1", vec!["g1b1", "g8h8"]),
+            // Quiet promotion and capture promotion
+            ("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", vec!["a7a8q", "e8d7", "a8b8"]),
+            ("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", vec!["a7b8n"]),
+        ];
+
+        for (fen, moves) in cases {
+            let original = Position::from_fen(fen).unwrap();
+            let mut pos = original.clone();
+            let mut undo_stack = Vec::new();
+            let mut history = vec![original.clone()];
+
+            for uci in &moves {
+                let mv = pos
+                    .parse_uci_move(uci)
+                    .unwrap_or_else(|| panic!("illegal move {} in {}", uci, fen));
+                let expected = pos.make_move(mv);
+                pos.apply_move(mv, &mut undo_stack);
+                assert_eq!(pos, expected, "apply_move {} differs from make_move", uci);
+                history.push(pos.clone());
+            }
+
+            history.pop();
+            while let Some(before) = history.pop() {
+                pos.undo_move(&mut undo_stack);
+                assert_eq!(pos, before, "undo_move failed in {}", fen);
+                assert_eq!(pos.hash, pos.compute_hash());
+            }
+            assert!(undo_stack.is_empty());
+        }
+    }
+
     #[test]
     fn test_null_move() {
         setup();
@@ -371,5 +556,13 @@ mod tests {
 
         assert_eq!(null_pos.side_to_move, Color::Black);
         assert!(null_pos.en_passant.is_none());
+
+        // The 50-move count and move number carry over
+        let pos = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 37 60").unwrap();
+        let null_pos = pos.make_null_move();
+        assert_eq!(null_pos.halfmove_clock, 37);
+        assert_eq!(null_pos.plies_from_null, 0);
+        assert_eq!(null_pos.to_fen(), "4k3/8/8/8/8/8/8/R3K3 b - - 37 60");
+        assert_eq!(null_pos.make_move(Move::quiet(Square::E8, Square::E7)).plies_from_null, 1);
     }
 }
@@ -1,5 +1,9 @@
 /// Move generation
-use crate::bitboard::{aligned, between, king_attacks, knight_attacks, pawn_attacks, Bitboard};
+use crate::bitboard::{
+    aligned, between, king_attacks, knight_attacks, line, pawn_attacks, pin_mask, ray_toward,
+    Bitboard,
+};
+use crate::debug_info;
 use crate::magic::{bishop_attacks, rook_attacks};
 use crate::moves::{Move, MoveList};
 use crate::position::Position;
@@ -10,14 +14,12 @@ impl Position {
     pub fn generate_legal_moves(&self, list: &mut MoveList) {
         let initial_len = list.len();
 
-        if self.checkers.is_empty() {
-            self.generate_moves::<false>(list);
-        } else if self.checkers.exactly_one() {
+        match self.num_checkers() {
+            0 => self.generate_moves::<false>(list),
             // Single check - can block or capture checker
-            self.generate_moves::<true>(list);
-        } else {
+            1 => self.generate_moves::<true>(list),
             // Double check - only king moves are legal
-            self.generate_king_moves(list);
+            _ => self.generate_king_moves(list),
         }
 
         // Runtime validation: all generated moves should be for the side to move
@@ -25,18 +27,131 @@ impl Position {
             let mv = list.get(i);
             if let Some(piece) = self.piece_at(mv.from_sq()) {
                 if piece.color() != self.side_to_move {
-                    eprintln!(
+                    debug_info!(
                         "BUG: generate_legal_moves produced {} for {:?} but side is {:?}",
                         mv.to_uci(),
                         piece.color(),
                         self.side_to_move
                     );
-                    eprintln!("Position: {}", self.to_fen());
+                    debug_info!("Position: {}", self.to_fen());
                 }
             }
         }
     }
 
+    /// All legal moves as a Vec (convenience for tools and tests)
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut list = MoveList::new();
+        self.generate_legal_moves(&mut list);
+        list.iter().collect()
+    }
+
+    /// Side to move is in check and has no legal moves
+    pub fn is_checkmate(&self) -> bool {
+        self.is_in_check() && self.generate_legal_count() == 0
+    }
+
+    /// Side to move is not in check but has no legal moves
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_in_check() && self.generate_legal_count() == 0
+    }
+
+    /// Count legal moves without building a move list (perft leaves)
+    pub fn generate_legal_count(&self) -> usize {
+        if self.num_checkers() > 1 {
+            // Double check - only king moves are legal
+            return self.count_king_moves();
+        }
+
+        let us = self.side_to_move;
+        let our_pieces = self.occupied[us as usize];
+        let king_sq = self.king_sq[us as usize];
+        let evasions = self.checkers.is_not_empty();
+
+        // Target squares for non-king pieces
+        let target = if evasions {
+            pin_mask(king_sq, self.checkers.lsb())
+        } else {
+            !our_pieces
+        };
+
+        let pinned = self.pinned(us);
+        let pawns = self.piece_bb(us, PieceType::Pawn);
+
+        // Unpinned pawns in bulk, pinned pawns restricted to their pin ray
+        let mut count = self.count_pawn_moves(pawns & !pinned, target);
+        for from in pawns & pinned {
+            count += self.count_pawn_moves(
+                Bitboard::from_square(from),
+                target & crate::bitboard::line(from, king_sq),
+            );
+        }
+
+        // En passant
+        if let Some(ep_sq) = self.en_passant {
+            for from in pawn_attacks(us.flip(), ep_sq) & pawns {
+                count += self.is_ep_legal(from, ep_sq) as usize;
+            }
+        }
+
+        // Pinned knights can't move
+        for from in self.piece_bb(us, PieceType::Knight) & !pinned {
+            count += (knight_attacks(from) & target).pop_count() as usize;
+        }
+
+        for from in self.diagonal_sliders(us) {
+            let mut attacks = bishop_attacks(from, self.all_occupied) & target;
+            if pinned.contains(from) {
+                attacks &= crate::bitboard::line(from, king_sq);
+            }
+            count += attacks.pop_count() as usize;
+        }
+
+        for from in self.orthogonal_sliders(us) {
+            let mut attacks = rook_attacks(from, self.all_occupied) & target;
+            if pinned.contains(from) {
+                attacks &= crate::bitboard::line(from, king_sq);
+            }
+            count += attacks.pop_count() as usize;
+        }
+
+        count += self.count_king_moves();
+
+        if !evasions {
+            count += self.castling_moves().into_iter().flatten().count();
+        }
+
+        count
+    }
+
+    /// Count pawn pushes and captures (excluding en passant) for a set of pawns
+    fn count_pawn_moves(&self, pawns: Bitboard, target: Bitboard) -> usize {
+        let us = self.side_to_move;
+        let their_pieces = self.occupied[us.flip() as usize] & target;
+        let empty = !self.all_occupied;
+
+        let (left, right, double_rank, last_rank) = match us {
+            Color::White => {
+                (pawns.north_west(), pawns.north_east(), Bitboard::RANK_3, Bitboard::RANK_8)
+            }
+            Color::Black => {
+                (pawns.south_west(), pawns.south_east(), Bitboard::RANK_6, Bitboard::RANK_1)
+            }
+        };
+
+        let single_push = pawns.pawn_push(us) & empty;
+        let double_push = (single_push & double_rank).pawn_push(us) & empty & target;
+
+        // Moves onto the last rank are promotions, each worth four moves
+        let with_promotions =
+            |bb: Bitboard| (bb & !last_rank).pop_count() + 4 * (bb & last_rank).pop_count();
+
+        (with_promotions(single_push & target)
+            + double_push.pop_count()
+            + with_promotions(left & their_pieces)
+            + with_promotions(right & their_pieces)) as usize
+    }
+
     /// Generate all pseudo-legal moves (for perft without legality check)
     pub fn generate_pseudo_legal_moves(&self, list: &mut MoveList) {
         self.generate_moves::<false>(list);
@@ -52,8 +167,6 @@ impl Position {
         self.generate_pawn_captures(list, their_pieces);
 
         // Generate piece captures
-        let our_pieces = self.occupied[us as usize];
-
         // Knights
         for from in self.piece_bb(us, PieceType::Knight) {
             let attacks = knight_attacks(from) & their_pieces;
@@ -96,6 +209,142 @@ impl Position {
         }
     }
 
+    /// Generate non-capturing, non-promoting moves, castling included: the complement of
+    /// `generate_captures`. Moves are pseudo-legal, except castling which is fully checked.
+    pub fn generate_quiets(&self, list: &mut MoveList) {
+        let us = self.side_to_move;
+        let empty = !self.all_occupied;
+        let pawns = self.piece_bb(us, PieceType::Pawn);
+
+        let (push_dir, promo_rank, double_rank): (i8, Bitboard, Bitboard) = match us {
+            Color::White => (8, Bitboard::RANK_7, Bitboard::RANK_3),
+            Color::Black => (-8, Bitboard::RANK_2, Bitboard::RANK_6),
+        };
+
+        // Pawn pushes (promotions are generated with the captures)
+        let single_push = (pawns & !promo_rank).pawn_push(us) & empty;
+        for to in single_push {
+            let from = Square((to.0 as i8 - push_dir) as u8);
+            list.push(Move::quiet(from, to));
+        }
+        for to in (single_push & double_rank).pawn_push(us) & empty {
+            let from = Square((to.0 as i8 - 2 * push_dir) as u8);
+            list.push(Move::double_push(from, to));
+        }
+
+        // Knights
+        for from in self.piece_bb(us, PieceType::Knight) {
+            for to in knight_attacks(from) & empty {
+                list.push(Move::quiet(from, to));
+            }
+        }
+
+        // Bishops
+        for from in self.piece_bb(us, PieceType::Bishop) {
+            for to in bishop_attacks(from, self.all_occupied) & empty {
+                list.push(Move::quiet(from, to));
+            }
+        }
+
+        // Rooks
+        for from in self.piece_bb(us, PieceType::Rook) {
+            for to in rook_attacks(from, self.all_occupied) & empty {
+                list.push(Move::quiet(from, to));
+            }
+        }
+
+        // Queens
+        for from in self.piece_bb(us, PieceType::Queen) {
+            let attacks =
+                bishop_attacks(from, self.all_occupied) | rook_attacks(from, self.all_occupied);
+            for to in attacks & empty {
+                list.push(Move::quiet(from, to));
+            }
+        }
+
+        // King moves and castling
+        let king_sq = self.king_sq[us as usize];
+        for to in king_attacks(king_sq) & empty {
+            list.push(Move::quiet(king_sq, to));
+        }
+        self.generate_castling(list);
+    }
+
+    /// Generate non-capturing, non-promoting moves that give check, direct or discovered
+    /// (for the first quiescence ply). Moves are pseudo-legal.
+    ///
+    /// Direct checks land on the cached check squares; discovered checks move a blocker
+    /// of the enemy king off its line. Nothing else is generated.
+    pub fn generate_quiet_checks(&self, list: &mut MoveList) {
+        let us = self.side_to_move;
+        let them = us.flip();
+        let enemy_king = self.king_sq[them as usize];
+        let empty = !self.all_occupied;
+        let check_squares = &self.check_info.check_squares;
+        let discoverers = self.check_info.blockers[them as usize] & self.occupied[us as usize];
+
+        // Pawn pushes (promotions are generated with the captures)
+        let (push_dir, promo_rank, double_rank): (i8, Bitboard, Bitboard) = match us {
+            Color::White => (8, Bitboard::RANK_7, Bitboard::RANK_3),
+            Color::Black => (-8, Bitboard::RANK_2, Bitboard::RANK_6),
+        };
+        let single_push = (self.piece_bb(us, PieceType::Pawn) & !promo_rank).pawn_push(us) & empty;
+        let double_push = (single_push & double_rank).pawn_push(us) & empty;
+        let pawn_checks = |from: Square, to: Square| {
+            check_squares[PieceType::Pawn as usize].contains(to)
+                || (discoverers.contains(from) && !aligned(from, to, enemy_king))
+        };
+        for to in single_push {
+            let from = Square((to.0 as i8 - push_dir) as u8);
+            if pawn_checks(from, to) {
+                list.push(Move::quiet(from, to));
+            }
+        }
+        for to in double_push {
+            let from = Square((to.0 as i8 - 2 * push_dir) as u8);
+            if pawn_checks(from, to) {
+                list.push(Move::double_push(from, to));
+            }
+        }
+
+        // Pieces: onto a check square, or anywhere off the line if they uncover one
+        let occupied = self.all_occupied;
+        let pieces = [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen];
+        for piece_type in pieces {
+            for from in self.piece_bb(us, piece_type) {
+                let attacks = match piece_type {
+                    PieceType::Knight => knight_attacks(from),
+                    PieceType::Bishop => bishop_attacks(from, occupied),
+                    PieceType::Rook => rook_attacks(from, occupied),
+                    _ => bishop_attacks(from, occupied) | rook_attacks(from, occupied),
+                } & empty;
+
+                let mut targets = attacks & check_squares[piece_type as usize];
+                if discoverers.contains(from) {
+                    targets |= attacks & !line(from, enemy_king);
+                }
+                for to in targets {
+                    list.push(Move::quiet(from, to));
+                }
+            }
+        }
+
+        // The king can only uncover a check
+        let king_sq = self.king_sq[us as usize];
+        if discoverers.contains(king_sq) {
+            for to in king_attacks(king_sq) & empty & !line(king_sq, enemy_king) {
+                list.push(Move::quiet(king_sq, to));
+            }
+        }
+
+        // Castling checks with the rook
+        for mv in self.castling_moves().into_iter().flatten() {
+            if self.gives_check(mv) {
+                list.push(mv);
+            }
+        }
+    }
+
     /// Generate moves with optional evasion mode
     fn generate_moves<const EVASIONS: bool>(&self, list: &mut MoveList) {
         let us = self.side_to_move;
@@ -109,12 +358,12 @@ impl Position {
             // In check: can only capture the checker or block
             let checker_sq = self.checkers.lsb();
             let king_sq = self.king_sq[us as usize];
-            between(king_sq, checker_sq) | self.checkers
+            pin_mask(king_sq, checker_sq)
         } else {
             !our_pieces
         };
 
-        let pinned = self.pinned_pieces(us);
+        let pinned = self.pinned(us);
         let king_sq = self.king_sq[us as usize];
 
         // Generate pawn moves
@@ -207,9 +456,9 @@ impl Position {
         let their_pieces = self.occupied[them as usize];
         let empty = !self.all_occupied;
 
-        let (push_dir, start_rank, promo_rank): (i8, Bitboard, Bitboard) = match us {
-            Color::White => (8, Bitboard::RANK_2, Bitboard::RANK_7),
-            Color::Black => (-8, Bitboard::RANK_7, Bitboard::RANK_2),
+        let (push_dir, promo_rank): (i8, Bitboard) = match us {
+            Color::White => (8, Bitboard::RANK_7),
+            Color::Black => (-8, Bitboard::RANK_2),
         };
 
         let promo_pawns = pawns & promo_rank;
@@ -417,58 +666,73 @@ impl Position {
         }
     }
 
+    /// Count legal king moves (excluding castling)
+    fn count_king_moves(&self) -> usize {
+        let us = self.side_to_move;
+        let king_sq = self.king_sq[us as usize];
+        let occupied_without_king = self.all_occupied.clear(king_sq);
+
+        (king_attacks(king_sq) & !self.occupied[us as usize])
+            .into_iter()
+            .filter(|&to| !self.is_square_attacked(to, us.flip(), occupied_without_king))
+            .count()
+    }
+
     /// Generate castling moves
     fn generate_castling(&self, list: &mut MoveList) {
+        for mv in self.castling_moves().into_iter().flatten() {
+            list.push(mv);
+        }
+    }
+
+    /// Legal kingside and queenside castling moves, if any. Handles Chess960 too: the king
+    /// always lands on the g- or c-file and the rook beside it on the f- or d-file.
+    fn castling_moves(&self) -> [Option<Move>; 2] {
         let us = self.side_to_move;
         let them = us.flip();
+        let king_sq = self.king_sq[us as usize];
+        let mut moves = [None; 2];
 
-        let (king_sq, ks_target, qs_target, ks_path, qs_path, ks_check_path, qs_check_path) =
-            match us {
-                Color::White => (
-                    Square::E1,
-                    Square::G1,
-                    Square::C1,
-                    Bitboard::new(0x60),               // f1, g1
-                    Bitboard::new(0x0E),               // b1, c1, d1
-                    Bitboard::new(0x60),               // f1, g1
-                    Bitboard::new(0x0C),               // c1, d1
-                ),
-                Color::Black => (
-                    Square::E8,
-                    Square::G8,
-                    Square::C8,
-                    Bitboard::new(0x6000000000000000), // f8, g8
-                    Bitboard::new(0x0E00000000000000), // b8, c8, d8
-                    Bitboard::new(0x6000000000000000), // f8, g8
-                    Bitboard::new(0x0C00000000000000), // c8, d8
-                ),
-            };
+        if self.checkers.is_not_empty() {
+            return moves;
+        }
 
-        // Kingside castling
-        if self.castling.contains(CastlingRights::kingside(us)) {
-            // Path must be clear
-            if (self.all_occupied & ks_path).is_empty() {
-                // King and path must not be attacked
-                if !self.is_attacked_by(king_sq, them)
-                    && !self.any_attacked(ks_check_path, them)
-                {
-                    list.push(Move::king_castle(king_sq, ks_target));
-                }
+        let rights = [CastlingRights::kingside(us), CastlingRights::queenside(us)];
+        for (slot, right) in moves.iter_mut().zip(rights) {
+            if !self.castling.contains(right) {
+                continue;
             }
-        }
 
-        // Queenside castling
-        if self.castling.contains(CastlingRights::queenside(us)) {
-            // Path must be clear
-            if (self.all_occupied & qs_path).is_empty() {
-                // King and path must not be attacked
-                if !self.is_attacked_by(king_sq, them)
-                    && !self.any_attacked(qs_check_path, them)
-                {
-                    list.push(Move::queen_castle(king_sq, qs_target));
-                }
+            let rook_sq = self.castling_rook(right);
+            let mv = if right == CastlingRights::kingside(us) {
+                Move::king_castle(king_sq, rook_sq)
+            } else {
+                Move::queen_castle(king_sq, rook_sq)
+            };
+            let (king_to, rook_to) = mv.castling_destinations();
+
+            // Every square either piece crosses or lands on must be empty, apart from
+            // the king and rook themselves
+            let movers = Bitboard::from_square(king_sq) | Bitboard::from_square(rook_sq);
+            let king_path = between(king_sq, king_to) | Bitboard::from_square(king_to);
+            let rook_path = between(rook_sq, rook_to) | Bitboard::from_square(rook_to);
+            if ((king_path | rook_path) & self.all_occupied & !movers).is_not_empty() {
+                continue;
             }
+
+            // The king may not cross an attacked square, nor land on one once the rook
+            // has left a square where it might have been blocking
+            let occupied_after = (self.all_occupied & !movers) | Bitboard::from_square(rook_to);
+            if self.any_attacked(king_path, them)
+                || self.is_square_attacked(king_to, them, occupied_after)
+            {
+                continue;
+            }
+
+            *slot = Some(mv);
         }
+
+        moves
     }
 
     /// Check if any square in a bitboard is attacked by a color
@@ -486,7 +750,7 @@ impl Position {
         self.attackers_to_by(sq, by_color, occupied).is_not_empty()
     }
 
-    /// Check if en passant is legal (doesn't reveal check)
+    /// Check if en passant is legal (doesn't reveal or leave a check)
     fn is_ep_legal(&self, from: Square, ep_sq: Square) -> bool {
         let us = self.side_to_move;
         let them = us.flip();
@@ -495,6 +759,19 @@ impl Position {
         // The captured pawn square
         let captured_sq = Square((ep_sq.0 as i8 + if us == Color::White { -8 } else { 8 }) as u8);
 
+        // A knight check survives the capture; slider checks are re-tested below
+        let sliders = self.orthogonal_sliders(them) | self.diagonal_sliders(them);
+        if (self.checkers.clear(captured_sq) & !sliders).is_not_empty() {
+            return false;
+        }
+
+        // Only sliders already giving check or lined up behind a vacated square can attack
+        // the king afterwards
+        let exposed = ray_toward(king_sq, from) | ray_toward(king_sq, captured_sq) | self.checkers;
+        if (sliders & exposed).is_empty() {
+            return true;
+        }
+
         // Remove both pawns and add capturing pawn at destination
         let occupied = self
             .all_occupied
@@ -536,7 +813,7 @@ impl Position {
         }
 
         // Non-king moves: check if piece is pinned
-        let pinned = self.pinned_pieces(us);
+        let pinned = self.pinned(us);
         if pinned.contains(from) {
             // Pinned piece can only move along pin ray
             return aligned(from, to, king_sq);
@@ -545,8 +822,7 @@ impl Position {
         // If in check, verify move blocks or captures
         if self.checkers.is_not_empty() {
             let checker_sq = self.checkers.lsb();
-            let block_mask = between(king_sq, checker_sq) | self.checkers;
-            return block_mask.contains(to);
+            return pin_mask(king_sq, checker_sq).contains(to);
         }
 
         true
@@ -557,6 +833,7 @@ impl Position {
 mod tests {
     use super::*;
     use crate::magic::init_magics;
+    use crate::test_util::TestRng;
 
     fn setup() {
         static INIT: std::sync::Once = std::sync::Once::new();
@@ -574,6 +851,28 @@ mod tests {
         assert_eq!(list.len(), 20); // 16 pawn moves + 4 knight moves
     }
 
+    #[test]
+    fn test_legal_moves_and_game_end() {
+        setup();
+        let pos = Position::new();
+        assert_eq!(pos.legal_moves().len(), 20);
+        assert!(!pos.is_checkmate());
+        assert!(!pos.is_stalemate());
+
+        // Fool's mate
+        let mate =
+            Position::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+                .unwrap();
+        assert!(mate.legal_moves().is_empty());
+        assert!(mate.is_checkmate());
+        assert!(!mate.is_stalemate());
+
+        let stalemate = Position::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(stalemate.legal_moves().is_empty());
+        assert!(stalemate.is_stalemate());
+        assert!(!stalemate.is_checkmate());
+    }
+
     #[test]
     fn test_kiwipete_moves() {
         setup();
@@ -628,6 +927,92 @@ mod tests {
         assert!(has_ep, "Should have en passant capture");
     }
 
+    #[test]
+    fn test_quiet_checks() {
+        setup();
+        // Every bishop move uncovers the rook; Bb5+ also checks directly
+        let pos = Position::from_fen("4k3/8/8/8/8/8/4B3/4R1K1 w - - 0 1").unwrap();
+        let mut list = MoveList::new();
+        pos.generate_quiet_checks(&mut list);
+
+        assert_eq!(list.len(), 9);
+        for mv in &list {
+            assert!(!mv.is_capture());
+            assert_eq!(pos.piece_at(mv.from_sq()).unwrap().piece_type(), PieceType::Bishop);
+            assert!(pos.make_move(mv).is_in_check());
+        }
+    }
+
+    #[test]
+    fn test_quiet_checks_match_legal_checking_moves() {
+        setup();
+        let fens = [
+            Position::STARTPOS,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            // Discovered checks by knight, pawn and king, and direct pawn checks
+            "4k3/8/8/8/8/4N3/8/4R1K1 w - - 0 1",
+            "7k/8/6P1/8/8/2P5/8/B5K1 w - - 0 1",
+            "k7/8/8/8/8/5K2/8/7B w - - 0 1",
+            "8/8/8/3k4/8/8/4P3/4K3 w - - 0 1",
+            "7k/8/8/8/3P4/8/1Q6/K7 w - - 0 1",
+            // Castling gives check along the f-file
+            "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+        ];
+
+        for fen in fens {
+            let pos = Position::from_fen(fen).unwrap();
+            let mut legal = MoveList::new();
+            pos.generate_legal_moves(&mut legal);
+            let mut expected: Vec<Move> = legal
+                .iter()
+                .filter(|&mv| !mv.is_capture() && !mv.is_promotion() && pos.gives_check(mv))
+                .collect();
+
+            let mut checks = MoveList::new();
+            pos.generate_quiet_checks(&mut checks);
+            let mut generated: Vec<Move> = checks.iter().filter(|&mv| pos.is_legal(mv)).collect();
+
+            expected.sort_by_key(|mv| mv.0);
+            generated.sort_by_key(|mv| mv.0);
+            assert_eq!(generated, expected, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_captures_and_quiets_partition_legal_moves() {
+        setup();
+        let fens = [
+            Position::STARTPOS,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ];
+
+        for fen in fens {
+            let pos = Position::from_fen(fen).unwrap();
+            let mut legal = MoveList::new();
+            pos.generate_legal_moves(&mut legal);
+
+            let mut captures = MoveList::new();
+            pos.generate_captures(&mut captures);
+            let mut quiets = MoveList::new();
+            pos.generate_quiets(&mut quiets);
+            assert!(quiets.iter().all(|mv| !mv.is_capture() && !mv.is_promotion()));
+
+            let mut union: Vec<Move> =
+                captures.iter().chain(quiets.iter()).filter(|&mv| pos.is_legal(mv)).collect();
+            let mut expected: Vec<Move> = legal.iter().collect();
+            union.sort_by_key(|mv| mv.0);
+            expected.sort_by_key(|mv| mv.0);
+            assert_eq!(union, expected, "{}", fen);
+        }
+    }
+
     #[test]
     fn test_promotion() {
         setup();
@@ -639,13 +1024,86 @@ mod tests {
         assert_eq!(promos.len(), 4, "Should have 4 promotion options");
     }
 
+    #[test]
+    fn test_legal_count_matches_move_list() {
+        setup();
+        fn check(pos: &Position, depth: u32) {
+            let mut list = MoveList::new();
+            pos.generate_legal_moves(&mut list);
+            assert_eq!(pos.generate_legal_count(), list.len(), "Count mismatch: {}", pos.to_fen());
+            if depth > 1 {
+                for mv in list.iter() {
+                    check(&pos.make_move(mv), depth - 1);
+                }
+            }
+        }
+
+        let fens = [
+            Position::STARTPOS,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        ];
+        for fen in fens {
+            check(&Position::from_fen(fen).unwrap(), 3);
+        }
+    }
+
+    #[test]
+    fn test_random_move_sequences() {
+        setup();
+        let mut rng = TestRng::new(0x9E37_79B9_7F4A_7C15);
+
+        for _ in 0..4000 {
+            let mut pos = Position::new();
+            for _ in 0..60 {
+                let moves = pos.legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+
+                // Every generated move must leave the mover's king safe
+                let us = pos.side_to_move;
+                for &mv in &moves {
+                    let child = pos.make_move(mv);
+                    assert!(
+                        !child.is_attacked_by(child.king_sq[us as usize], !us),
+                        "{} leaves king in check in {}",
+                        mv.to_uci(),
+                        pos.to_fen()
+                    );
+                }
+
+                let mv = moves[rng.below(moves.len())];
+                let ctx = format!("after {} from {}", mv.to_uci(), pos.to_fen());
+                pos = pos.make_move(mv);
+
+                assert_eq!(pos.side_to_move, !us, "{ctx}");
+                for color in Color::ALL {
+                    let mut union = Bitboard::EMPTY;
+                    for pt in PieceType::ALL {
+                        union |= pos.piece_bb(color, pt);
+                    }
+                    assert_eq!(pos.occupied[color as usize], union, "{ctx}");
+                    let king = pos.piece_bb(color, PieceType::King);
+                    assert_eq!(king.pop_count(), 1, "{ctx}");
+                    assert_eq!(pos.king_sq[color as usize], king.lsb(), "{ctx}");
+                }
+                assert_eq!(pos.all_occupied, pos.occupied[0] | pos.occupied[1], "{ctx}");
+                assert_eq!(pos.checkers, pos.compute_checkers(), "{ctx}");
+                assert_eq!(pos.hash, pos.compute_hash(), "{ctx}");
+            }
+        }
+    }
+
     #[test]
     fn test_double_check() {
         setup();
-        // Double check position - only king moves are legal
-        let pos =
-            Position::from_fen("r1bqk2r/pppp1Npp/2n2n2/2b1p3/2B1P3/8/PPPP1PPP/RNBQK2R b KQkq - 0 1")
-                .unwrap();
+        // Double check position (knight d6 and rook e1) - only king moves are legal
+        let pos = Position::from_fen("4k3/8/3N4/8/8/8/8/4R1K1 b - - 0 1").unwrap();
+        assert!(pos.checkers.more_than_one());
         let mut list = MoveList::new();
         pos.generate_legal_moves(&mut list);
 
@@ -60,16 +60,16 @@ impl Move {
         Self::new(from, to, Self::FLAG_EP_CAPTURE)
     }
 
-    /// Create a kingside castle
+    /// Create a kingside castle, encoded as the king taking its own rook on `rook`
     #[inline(always)]
-    pub const fn king_castle(from: Square, to: Square) -> Self {
-        Self::new(from, to, Self::FLAG_KING_CASTLE)
+    pub const fn king_castle(from: Square, rook: Square) -> Self {
+        Self::new(from, rook, Self::FLAG_KING_CASTLE)
     }
 
-    /// Create a queenside castle
+    /// Create a queenside castle, encoded as the king taking its own rook on `rook`
     #[inline(always)]
-    pub const fn queen_castle(from: Square, to: Square) -> Self {
-        Self::new(from, to, Self::FLAG_QUEEN_CASTLE)
+    pub const fn queen_castle(from: Square, rook: Square) -> Self {
+        Self::new(from, rook, Self::FLAG_QUEEN_CASTLE)
     }
 
     /// Create a promotion move
@@ -115,6 +115,18 @@ impl Move {
         (self.flags() & 0b1000) != 0
     }
 
+    /// Check if this promotes to a queen
+    #[inline(always)]
+    pub const fn is_queen_promotion(self) -> bool {
+        (self.flags() & 0b1011) == Self::FLAG_PROMO_QUEEN
+    }
+
+    /// Check if this promotes to a knight, bishop or rook
+    #[inline(always)]
+    pub const fn is_underpromotion(self) -> bool {
+        self.is_promotion() && (self.flags() & 0b0011) != 0b0011
+    }
+
     /// Check if this is a tactical move (capture or promotion)
     #[inline(always)]
     pub const fn is_tactical(self) -> bool {
@@ -146,6 +158,18 @@ impl Move {
         self.flags() == Self::FLAG_QUEEN_CASTLE
     }
 
+    /// Final king and rook squares of a castling move: g- and f-file when castling
+    /// kingside, c- and d-file queenside, on the king's rank
+    #[inline(always)]
+    pub const fn castling_destinations(self) -> (Square, Square) {
+        let rank = self.from_sq().rank();
+        if self.is_kingside_castle() {
+            (Square::from_coords(6, rank), Square::from_coords(5, rank))
+        } else {
+            (Square::from_coords(2, rank), Square::from_coords(3, rank))
+        }
+    }
+
     /// Check if this is a double pawn push
     #[inline(always)]
     pub const fn is_double_push(self) -> bool {
@@ -175,14 +199,23 @@ impl Move {
         }
     }
 
-    /// Convert to UCI string (e.g., "e2e4", "e7e8q")
+    /// Convert to UCI string (e.g., "e2e4", "e7e8q"), castling as the king's two-square move
     pub fn to_uci(self) -> String {
+        self.to_uci_with(false)
+    }
+
+    /// Convert to UCI string; in Chess960 mode castling is written king-takes-rook ("e1h1")
+    pub fn to_uci_with(self, chess960: bool) -> String {
         if self.is_null() {
             return "0000".to_string();
         }
 
         let from = self.from_sq().to_algebraic();
-        let to = self.to_sq().to_algebraic();
+        let to = if self.is_castle() && !chess960 {
+            self.castling_destinations().0.to_algebraic()
+        } else {
+            self.to_sq().to_algebraic()
+        };
 
         if self.is_promotion() {
             let promo = match self.promotion_piece() {
@@ -311,6 +344,40 @@ impl MoveList {
     pub fn contains(&self, mv: Move) -> bool {
         self.moves[..self.len].contains(&mv)
     }
+
+    /// Keep only the moves for which `f` returns true, preserving order and scores
+    pub fn retain(&mut self, f: impl Fn(Move) -> bool) {
+        let mut kept = 0;
+        for i in 0..self.len {
+            if f(self.moves[i]) {
+                self.moves[kept] = self.moves[i];
+                self.scores[kept] = self.scores[i];
+                kept += 1;
+            }
+        }
+        self.len = kept;
+    }
+
+    /// Sort all moves by descending score (stable, so ties keep generation order)
+    pub fn sort_by_score(&mut self) {
+        // Insertion sort: lists are short and this avoids allocating
+        for i in 1..self.len {
+            let mut j = i;
+            while j > 0 && self.scores[j - 1] < self.scores[j] {
+                self.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = Move;
+    type IntoIter = std::iter::Copied<std::slice::Iter<'a, Move>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.moves[..self.len].iter().copied()
+    }
 }
 
 impl Default for MoveList {
@@ -376,17 +443,44 @@ mod tests {
         assert_eq!(mv_cap.to_uci(), "e7d8n");
     }
 
+    #[test]
+    fn test_underpromotion() {
+        for capture in [false, true] {
+            let promo = |piece| Move::promotion(Square::B7, Square::B8, piece, capture);
+
+            assert!(promo(PieceType::Queen).is_queen_promotion());
+            assert!(!promo(PieceType::Queen).is_underpromotion());
+            for piece in [PieceType::Rook, PieceType::Bishop, PieceType::Knight] {
+                assert!(promo(piece).is_underpromotion());
+                assert!(!promo(piece).is_queen_promotion());
+            }
+        }
+
+        for mv in [
+            Move::quiet(Square::E2, Square::E4),
+            Move::capture(Square::E4, Square::D5),
+            Move::king_castle(Square::E1, Square::H1),
+            Move::queen_castle(Square::E1, Square::A1),
+            Move::en_passant(Square::E5, Square::D6),
+        ] {
+            assert!(!mv.is_underpromotion());
+            assert!(!mv.is_queen_promotion());
+        }
+    }
+
     #[test]
     fn test_move_castle() {
-        let ks = Move::king_castle(Square::E1, Square::G1);
+        let ks = Move::king_castle(Square::E1, Square::H1);
         assert!(ks.is_castle());
         assert!(ks.is_kingside_castle());
         assert!(!ks.is_queenside_castle());
+        assert_eq!(ks.castling_destinations(), (Square::G1, Square::F1));
 
-        let qs = Move::queen_castle(Square::E1, Square::C1);
+        let qs = Move::queen_castle(Square::E8, Square::A8);
         assert!(qs.is_castle());
         assert!(!qs.is_kingside_castle());
         assert!(qs.is_queenside_castle());
+        assert_eq!(qs.castling_destinations(), (Square::C8, Square::D8));
     }
 
     #[test]
@@ -402,11 +496,54 @@ mod tests {
         assert_eq!(moves.len(), 2);
     }
 
+    #[test]
+    fn test_move_list_retain() {
+        let mut list = MoveList::new();
+        list.push_scored(Move::quiet(Square::E2, Square::E4), 1);
+        list.push_scored(Move::capture(Square::E4, Square::D5), 2);
+        list.push_scored(Move::quiet(Square::D2, Square::D4), 3);
+        list.push_scored(Move::capture(Square::C4, Square::D5), 4);
+
+        list.retain(|mv| !mv.is_capture());
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.get(0), Move::quiet(Square::E2, Square::E4));
+        assert_eq!(list.get(1), Move::quiet(Square::D2, Square::D4));
+        assert_eq!((list.score(0), list.score(1)), (1, 3));
+    }
+
+    #[test]
+    fn test_move_list_sort_by_score() {
+        let mut list = MoveList::new();
+        list.push_scored(Move::quiet(Square::A2, Square::A3), 10);
+        list.push_scored(Move::quiet(Square::B2, Square::B3), 30);
+        list.push_scored(Move::quiet(Square::C2, Square::C3), -5);
+        list.push_scored(Move::quiet(Square::D2, Square::D3), 30);
+
+        list.sort_by_score();
+        let scores: Vec<i32> = (0..list.len()).map(|i| list.score(i)).collect();
+        assert_eq!(scores, vec![30, 30, 10, -5]);
+        assert_eq!(list.get(0), Move::quiet(Square::B2, Square::B3));
+        assert_eq!(list.get(1), Move::quiet(Square::D2, Square::D3));
+
+        let mut count = 0;
+        for mv in &list {
+            assert!(list.contains(mv));
+            count += 1;
+        }
+        assert_eq!(count, 4);
+    }
+
     #[test]
     fn test_uci_format() {
         assert_eq!(Move::quiet(Square::E2, Square::E4).to_uci(), "e2e4");
         assert_eq!(Move::capture(Square::E4, Square::D5).to_uci(), "e4d5");
-        assert_eq!(Move::king_castle(Square::E1, Square::G1).to_uci(), "e1g1");
+        assert_eq!(Move::king_castle(Square::E1, Square::H1).to_uci(), "e1g1");
+        assert_eq!(Move::queen_castle(Square::E8, Square::A8).to_uci(), "e8c8");
+
+        // Chess960 notation: the king takes its own rook
+        assert_eq!(Move::king_castle(Square::E1, Square::H1).to_uci_with(true), "e1h1");
+        assert_eq!(Move::queen_castle(Square::B1, Square::A1).to_uci_with(true), "b1a1");
+        assert_eq!(Move::quiet(Square::E2, Square::E4).to_uci_with(true), "e2e4");
         assert_eq!(Move::NULL.to_uci(), "0000");
     }
 }
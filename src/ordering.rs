@@ -1,8 +1,8 @@
 /// Move ordering for search
-use crate::moves::{Move, MoveList, MAX_MOVES};
+use crate::moves::{Move, MoveList};
 use crate::position::Position;
 use crate::see::see_piece_value;
-use crate::types::{Color, PieceType, Square};
+use crate::types::{Color, PieceType};
 
 /// Move ordering scores
 const TT_MOVE_SCORE: i32 = 10_000_000;
@@ -10,17 +10,26 @@ const GOOD_CAPTURE_BASE: i32 = 8_000_000;
 const KILLER_SCORE_1: i32 = 6_000_000;
 const KILLER_SCORE_2: i32 = 5_000_000;
 const COUNTER_MOVE_SCORE: i32 = 4_000_000;
-const BAD_CAPTURE_BASE: i32 = -2_000_000;
+/// Added to the history score of quiet checks, which often force the line
+const QUIET_CHECK_BONUS: i32 = 8192;
+/// Below any history score, so underpromotions are tried last
+const UNDERPROMOTION_SCORE: i32 = -1_000_000;
+
+/// Killer slots per ply
+pub const NUM_KILLERS: usize = 2;
 
 /// Maximum ply for killer/history storage
 pub const MAX_PLY: usize = 128;
 
-/// Search heuristics for move ordering
-pub struct SearchHeuristics {
-    /// Killer moves (2 per ply)
-    pub killers: [[Move; 2]; MAX_PLY],
+/// History scores stay within ±MAX_HISTORY, well below the killer and countermove scores
+pub const MAX_HISTORY: i32 = 16384;
+
+/// Search heuristics for move ordering, with `K` killer slots per ply
+pub struct SearchHeuristics<const K: usize = NUM_KILLERS> {
+    /// Killer moves (K per ply, most recent first)
+    pub killers: [[Move; K]; MAX_PLY],
 
-    /// History heuristic [color][from][to]
+    /// History heuristic [color][from][to], within ±MAX_HISTORY
     pub history: [[[i32; 64]; 64]; 2],
 
     /// Counter move heuristic [from][to]
@@ -32,17 +41,14 @@ pub struct SearchHeuristics {
 
 impl SearchHeuristics {
     pub fn new() -> Self {
-        SearchHeuristics {
-            killers: [[Move::NULL; 2]; MAX_PLY],
-            history: [[[0; 64]; 64]; 2],
-            countermoves: [[Move::NULL; 64]; 64],
-            prev_move: Move::NULL,
-        }
+        Self::default()
     }
+}
 
+impl<const K: usize> SearchHeuristics<K> {
     /// Clear all heuristics
     pub fn clear(&mut self) {
-        self.killers = [[Move::NULL; 2]; MAX_PLY];
+        self.killers = [[Move::NULL; K]; MAX_PLY];
         self.history = [[[0; 64]; 64]; 2];
         self.countermoves = [[Move::NULL; 64]; 64];
         self.prev_move = Move::NULL;
@@ -57,13 +63,11 @@ impl SearchHeuristics {
         if mv.is_capture() {
             return;
         }
-        // Don't add if already the first killer
-        if self.killers[ply][0] == mv {
-            return;
-        }
-        // Shift killers
-        self.killers[ply][1] = self.killers[ply][0];
-        self.killers[ply][0] = mv;
+        // Shift the killers before this one (or all of them) down a slot
+        let slots = &mut self.killers[ply];
+        let end = slots.iter().position(|&k| k == mv).unwrap_or(K - 1);
+        slots.copy_within(0..end, 1);
+        slots[0] = mv;
     }
 
     /// Update history heuristic
@@ -74,11 +78,20 @@ impl SearchHeuristics {
 
         let from = mv.from_sq().0 as usize;
         let to = mv.to_sq().0 as usize;
-        let bonus = if is_good { depth * depth } else { -(depth * depth) };
+        let bonus = (depth * depth).min(MAX_HISTORY);
+        let bonus = if is_good { bonus } else { -bonus };
 
-        // Gravity formula to prevent overflow
+        // Gravity formula: the closer to the limit, the smaller the step towards it
         let history = &mut self.history[color as usize][from][to];
-        *history += bonus - (*history * bonus.abs() / 16384);
+        *history += bonus - (*history * bonus.abs() / MAX_HISTORY);
+        *history = (*history).clamp(-MAX_HISTORY, MAX_HISTORY);
+    }
+
+    /// Halve all history scores, so a new search favours what it learns itself
+    pub fn age_history(&mut self) {
+        for score in self.history.iter_mut().flatten().flatten() {
+            *score /= 2;
+        }
     }
 
     /// Update countermove heuristic
@@ -103,13 +116,7 @@ impl SearchHeuristics {
         if ply >= MAX_PLY {
             return None;
         }
-        if self.killers[ply][0] == mv {
-            Some(0)
-        } else if self.killers[ply][1] == mv {
-            Some(1)
-        } else {
-            None
-        }
+        self.killers[ply].iter().position(|&k| k == mv).map(|i| i as u8)
     }
 
     /// Check if move is the countermove
@@ -123,9 +130,14 @@ impl SearchHeuristics {
     }
 }
 
-impl Default for SearchHeuristics {
+impl<const K: usize> Default for SearchHeuristics<K> {
     fn default() -> Self {
-        Self::new()
+        SearchHeuristics {
+            killers: [[Move::NULL; K]; MAX_PLY],
+            history: [[[0; 64]; 64]; 2],
+            countermoves: [[Move::NULL; 64]; 64],
+            prev_move: Move::NULL,
+        }
     }
 }
 
@@ -174,6 +186,11 @@ fn score_move(
         return TT_MOVE_SCORE;
     }
 
+    // Underpromotions are rarely best
+    if mv.is_underpromotion() {
+        return UNDERPROMOTION_SCORE;
+    }
+
     // Captures use MVV-LVA only (SEE deferred to search loop for lazy evaluation)
     if mv.is_capture() {
         let victim = if mv.is_en_passant() {
@@ -204,7 +221,7 @@ fn score_move(
         return if killer_idx == 0 {
             KILLER_SCORE_1
         } else {
-            KILLER_SCORE_2
+            KILLER_SCORE_2 - (killer_idx as i32 - 1)
         };
     }
 
@@ -213,8 +230,13 @@ fn score_move(
         return COUNTER_MOVE_SCORE;
     }
 
-    // History heuristic
-    heuristics.get_history(pos.side_to_move, mv)
+    // History heuristic, with a bonus for checks
+    let history = heuristics.get_history(pos.side_to_move, mv);
+    if pos.gives_check(mv) {
+        history + QUIET_CHECK_BONUS
+    } else {
+        history
+    }
 }
 
 /// Pick the best move from the remaining moves (selection sort)
@@ -291,6 +313,7 @@ fn score_capture(pos: &Position, mv: Move) -> i32 {
 mod tests {
     use super::*;
     use crate::magic::init_magics;
+    use crate::types::Square;
 
     fn setup() {
         static INIT: std::sync::Once = std::sync::Once::new();
@@ -316,6 +339,24 @@ mod tests {
         assert_eq!(h.is_killer(mv1, 0), Some(1));
     }
 
+    #[test]
+    fn test_three_killer_slots() {
+        let mut h = SearchHeuristics::<3>::default();
+        let mv1 = Move::quiet(Square::E2, Square::E4);
+        let mv2 = Move::quiet(Square::D2, Square::D4);
+        let mv3 = Move::quiet(Square::G1, Square::F3);
+
+        h.update_killer(mv1, 5);
+        h.update_killer(mv2, 5);
+        h.update_killer(mv3, 5);
+        assert_eq!(h.killers[5], [mv3, mv2, mv1]);
+        assert_eq!(h.is_killer(mv1, 5), Some(2));
+
+        // A repeated cutoff moves to the front without evicting anything
+        h.update_killer(mv1, 5);
+        assert_eq!(h.killers[5], [mv1, mv3, mv2]);
+    }
+
     #[test]
     fn test_history_update() {
         let mut h = SearchHeuristics::new();
@@ -328,6 +369,25 @@ mod tests {
         // Should decrease but formula prevents going too negative
     }
 
+    #[test]
+    fn test_history_saturates() {
+        let mut h = SearchHeuristics::new();
+        let mv = Move::quiet(Square::E2, Square::E4);
+
+        for _ in 0..10_000 {
+            h.update_history(Color::White, mv, 200, true);
+        }
+        assert_eq!(h.get_history(Color::White, mv), MAX_HISTORY);
+
+        for _ in 0..10_000 {
+            h.update_history(Color::White, mv, 200, false);
+        }
+        assert_eq!(h.get_history(Color::White, mv), -MAX_HISTORY);
+
+        h.age_history();
+        assert_eq!(h.get_history(Color::White, mv), -MAX_HISTORY / 2);
+    }
+
     #[test]
     fn test_mvv_lva() {
         setup();
@@ -353,4 +413,38 @@ mod tests {
             assert!(list.score(i) <= 0);
         }
     }
+
+    #[test]
+    fn test_underpromotions_ordered_last() {
+        setup();
+        let pos = Position::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let mut list = MoveList::new();
+        pos.generate_legal_moves(&mut list);
+
+        let heuristics = SearchHeuristics::new();
+        score_moves(&mut list, &pos, Move::NULL, &heuristics, 0);
+        list.sort_by_score();
+
+        assert!(list.get(0).is_queen_promotion());
+        let first_under = (0..list.len()).position(|i| list.get(i).is_underpromotion()).unwrap();
+        assert_eq!(first_under, list.len() - 3);
+    }
+
+    #[test]
+    fn test_quiet_checks_ordered_before_quiets() {
+        setup();
+        let pos = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        let check = pos.parse_uci_move("a1a8").unwrap();
+        let quiet = pos.parse_uci_move("a1a7").unwrap();
+
+        let mut list = MoveList::new();
+        pos.generate_legal_moves(&mut list);
+        let heuristics = SearchHeuristics::new();
+        score_moves(&mut list, &pos, Move::NULL, &heuristics, 0);
+        let score_of = |mv: Move| list.score((0..list.len()).find(|&i| list.get(i) == mv).unwrap());
+
+        // Equal (zero) history: the check comes first, but still below countermoves
+        assert!(score_of(check) > score_of(quiet));
+        assert!(score_of(check) < COUNTER_MOVE_SCORE);
+    }
 }
@@ -1,6 +1,8 @@
 /// Perft (performance test) for move generation validation
-use crate::moves::MoveList;
+use crate::magic::init_magics;
+use crate::moves::{Move, MoveList};
 use crate::position::Position;
+use std::io::Write;
 
 /// Run perft and return the node count
 pub fn perft(pos: &Position, depth: u32) -> u64 {
@@ -8,14 +10,14 @@ pub fn perft(pos: &Position, depth: u32) -> u64 {
         return 1;
     }
 
-    let mut moves = MoveList::new();
-    pos.generate_legal_moves(&mut moves);
-
-    // Leaf node optimization
+    // Leaf node optimization: count without building the move list
     if depth == 1 {
-        return moves.len() as u64;
+        return pos.generate_legal_count() as u64;
     }
 
+    let mut moves = MoveList::new();
+    pos.generate_legal_moves(&mut moves);
+
     let mut nodes = 0u64;
 
     for mv in moves.iter() {
@@ -26,21 +28,190 @@ pub fn perft(pos: &Position, depth: u32) -> u64 {
     nodes
 }
 
-/// Run perft with divide output (shows nodes per move)
-pub fn perft_divide(pos: &Position, depth: u32) -> u64 {
+/// Perft transposition table entry
+#[derive(Clone, Copy, Default)]
+struct PerftEntry {
+    hash: u64,
+    depth: u32,
+    nodes: u64,
+}
+
+/// Transposition table mapping (hash, depth) to perft node counts
+pub struct PerftTable {
+    table: Vec<PerftEntry>,
+    mask: usize,
+}
+
+impl PerftTable {
+    /// Create a new perft table with the given size in MB
+    pub fn new(size_mb: usize) -> Self {
+        let size_bytes = size_mb * 1024 * 1024;
+        let entry_size = std::mem::size_of::<PerftEntry>();
+        let num_entries = (size_bytes / entry_size).max(1).next_power_of_two();
+
+        PerftTable {
+            table: vec![PerftEntry::default(); num_entries],
+            mask: num_entries - 1,
+        }
+    }
+
+    /// Look up the node count for a position at a given depth
+    #[inline(always)]
+    fn probe(&self, hash: u64, depth: u32) -> Option<u64> {
+        let entry = &self.table[hash as usize & self.mask];
+        (entry.hash == hash && entry.depth == depth).then_some(entry.nodes)
+    }
+
+    /// Store a node count (always replace)
+    #[inline(always)]
+    fn store(&mut self, hash: u64, depth: u32, nodes: u64) {
+        self.table[hash as usize & self.mask] = PerftEntry { hash, depth, nodes };
+    }
+}
+
+/// Run perft, caching subtree counts of transposing positions
+pub fn perft_hashed(pos: &Position, depth: u32, table: &mut PerftTable) -> u64 {
     if depth == 0 {
         return 1;
     }
 
+    // Leaf node optimization: count without building the move list
+    if depth == 1 {
+        return pos.generate_legal_count() as u64;
+    }
+
+    if let Some(nodes) = table.probe(pos.hash, depth) {
+        return nodes;
+    }
+
     let mut moves = MoveList::new();
     pos.generate_legal_moves(&mut moves);
 
-    let mut total = 0u64;
+    let mut nodes = 0u64;
 
     for mv in moves.iter() {
         let new_pos = pos.make_move(mv);
-        let nodes = perft(&new_pos, depth - 1);
-        println!("{}: {}", mv.to_uci(), nodes);
+        nodes += perft_hashed(&new_pos, depth - 1, table);
+    }
+
+    table.store(pos.hash, depth, nodes);
+    nodes
+}
+
+/// Run perft with the root moves split across `threads` worker threads
+pub fn perft_parallel(pos: &Position, depth: u32, threads: usize) -> u64 {
+    if depth <= 1 {
+        return perft(pos, depth);
+    }
+
+    // Attack tables must exist before any worker touches them
+    init_magics();
+
+    let mut moves = MoveList::new();
+    pos.generate_legal_moves(&mut moves);
+    let root_moves: Vec<Move> = moves.iter().collect();
+
+    let threads = threads.clamp(1, root_moves.len().max(1));
+    let chunk_size = root_moves.len().div_ceil(threads);
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = root_moves
+            .chunks(chunk_size.max(1))
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|&mv| perft(&pos.make_move(mv), depth - 1))
+                        .sum::<u64>()
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("perft worker panicked"))
+            .sum()
+    })
+}
+
+/// Perft node counts broken down by the type of the last move
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PerftStats {
+    pub nodes: u64,
+    pub captures: u64,
+    pub en_passants: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    pub checks: u64,
+    pub checkmates: u64,
+}
+
+/// Run perft and classify every leaf move
+pub fn perft_detailed(pos: &Position, depth: u32) -> PerftStats {
+    let mut stats = PerftStats::default();
+    if depth == 0 {
+        stats.nodes = 1;
+    } else {
+        perft_detailed_inner(pos, depth, &mut stats);
+    }
+    stats
+}
+
+fn perft_detailed_inner(pos: &Position, depth: u32, stats: &mut PerftStats) {
+    let mut moves = MoveList::new();
+    pos.generate_legal_moves(&mut moves);
+
+    for mv in moves.iter() {
+        if depth > 1 {
+            perft_detailed_inner(&pos.make_move(mv), depth - 1, stats);
+            continue;
+        }
+
+        stats.nodes += 1;
+        stats.captures += mv.is_capture() as u64;
+        stats.en_passants += mv.is_en_passant() as u64;
+        stats.castles += mv.is_castle() as u64;
+        stats.promotions += mv.is_promotion() as u64;
+
+        if pos.gives_check(mv) {
+            stats.checks += 1;
+            let mut replies = MoveList::new();
+            pos.make_move(mv).generate_legal_moves(&mut replies);
+            stats.checkmates += replies.is_empty() as u64;
+        }
+    }
+}
+
+/// Run perft for each root move, returning the per-move node counts
+pub fn perft_divide(pos: &Position, depth: u32) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    let mut moves = MoveList::new();
+    pos.generate_legal_moves(&mut moves);
+
+    // Each root move is one leaf: no need to build the child positions
+    if depth == 1 {
+        return moves.iter().map(|mv| (mv, 1)).collect();
+    }
+
+    // Below this, perft bulk-counts the last ply itself
+    moves
+        .iter()
+        .map(|mv| (mv, perft(&pos.make_move(mv), depth - 1)))
+        .collect()
+}
+
+/// Print divide output (nodes per move) and return the total
+pub fn print_divide<W: Write + ?Sized>(pos: &Position, depth: u32, out: &mut W) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let mut total = 0u64;
+    for (mv, nodes) in perft_divide(pos, depth) {
+        writeln!(out, "{}: {}", mv.to_uci(), nodes).unwrap();
         total += nodes;
     }
 
@@ -87,6 +258,121 @@ mod tests {
         // assert_eq!(perft(&pos, 4), 4085603, "Kiwipete depth 4 failed");
     }
 
+    #[test]
+    fn test_perft_hashed_matches_perft() {
+        setup();
+        let kiwipete = Position::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+
+        for pos in [Position::new(), kiwipete] {
+            let mut table = PerftTable::new(16);
+            for depth in 1..=4 {
+                assert_eq!(
+                    perft_hashed(&pos, depth, &mut table),
+                    perft(&pos, depth),
+                    "Hashed perft mismatch at depth {} for {}",
+                    depth,
+                    pos.to_fen()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_perft_parallel_matches_perft() {
+        setup();
+        let pos = Position::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+
+        let nodes = perft_parallel(&pos, 4, 4);
+        assert_eq!(nodes, perft(&pos, 4));
+        assert_eq!(nodes, 4085603);
+    }
+
+    #[test]
+    fn test_perft_detailed_kiwipete() {
+        setup();
+        let pos = Position::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+
+        let depth1 = PerftStats {
+            nodes: 48,
+            captures: 8,
+            en_passants: 0,
+            castles: 2,
+            promotions: 0,
+            checks: 0,
+            checkmates: 0,
+        };
+        let depth2 = PerftStats {
+            nodes: 2039,
+            captures: 351,
+            en_passants: 1,
+            castles: 91,
+            promotions: 0,
+            checks: 3,
+            checkmates: 0,
+        };
+        assert_eq!(perft_detailed(&pos, 1), depth1);
+        assert_eq!(perft_detailed(&pos, 2), depth2);
+
+        // Start position depth 4 has the first checkmates
+        let stats = perft_detailed(&Position::new(), 4);
+        assert_eq!(stats.nodes, 197281);
+        assert_eq!(stats.captures, 1576);
+        assert_eq!(stats.checks, 469);
+        assert_eq!(stats.checkmates, 8);
+    }
+
+    #[test]
+    fn test_perft_divide() {
+        setup();
+        let pos = Position::new();
+        let divide = perft_divide(&pos, 3);
+
+        assert_eq!(divide.len(), 20);
+        assert_eq!(divide.iter().map(|&(_, n)| n).sum::<u64>(), 8902);
+
+        let count = |uci: &str| {
+            divide
+                .iter()
+                .find(|(mv, _)| mv.to_uci() == uci)
+                .map(|&(_, n)| n)
+                .unwrap()
+        };
+        assert_eq!(count("e2e4"), 600);
+        assert_eq!(count("d2d4"), 560);
+        assert_eq!(count("g1f3"), 440);
+        assert_eq!(count("e2e3"), 599);
+        assert_eq!(count("a2a3"), 380);
+
+        let mut out = Vec::new();
+        assert_eq!(print_divide(&pos, 3, &mut out), 8902);
+        assert!(String::from_utf8(out).unwrap().contains("e2e4: 600"));
+    }
+
+    #[test]
+    fn test_perft_divide_totals_match_perft() {
+        setup();
+        let start = Position::new();
+        let position3 = Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+
+        for (pos, max_depth) in [(start, 4), (position3, 5)] {
+            for depth in 1..=max_depth {
+                let divide = perft_divide(&pos, depth);
+                let total: u64 = divide.iter().map(|&(_, n)| n).sum();
+                assert_eq!(total, perft(&pos, depth), "{} depth {}", pos.to_fen(), depth);
+                assert_eq!(divide.len(), pos.generate_legal_count());
+            }
+        }
+    }
+
     #[test]
     fn test_perft_position3() {
         setup();
@@ -157,6 +443,50 @@ mod tests {
         assert!(nodes > 0);
     }
 
+    #[test]
+    fn test_perft_en_passant_pins() {
+        setup();
+        // Capturing pawn and captured pawn both leave the king's rank to a queen or rook
+        for fen in [
+            "8/8/8/8/k2Pp2Q/8/8/3K4 b - d3 0 1",
+            "8/8/8/8/k3pP1Q/8/8/3K4 b - f3 0 1",
+            "8/8/8/8/1R1Pp2k/8/8/3K4 b - d3 0 1",
+        ] {
+            let pos = Position::from_fen(fen).unwrap();
+            assert_eq!(perft(&pos, 1), 6, "{}", fen);
+        }
+
+        // En passant cannot answer a knight check
+        let pos = Position::from_fen("8/8/8/8/k1pP4/8/1N6/4K3 b - d3 0 1").unwrap();
+        assert_eq!(perft(&pos, 1), 5);
+
+        // Reference counts for illegal and checking en passant captures
+        let cases = [
+            ("8/8/8/2k5/2pP4/8/B7/4K3 b - d3 0 3", 1, 8),
+            ("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", 6, 1134888),
+            ("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", 6, 1015133),
+            ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 6, 1440467),
+            ("8/5bk1/8/2Pp4/8/1K6/8/8 w - d6 0 1", 6, 824064),
+        ];
+        for (fen, depth, nodes) in cases {
+            assert_eq!(perft(&Position::from_fen(fen).unwrap(), depth), nodes, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_perft_chess960() {
+        setup();
+        let cases = [
+            ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", 326672),
+            ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", 667366),
+            ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", 273318),
+            ("qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9", 382958),
+        ];
+        for (fen, nodes) in cases {
+            assert_eq!(perft(&Position::from_fen(fen).unwrap(), 4), nodes, "{}", fen);
+        }
+    }
+
     #[test]
     fn test_perft_promotion() {
         setup();
@@ -0,0 +1,447 @@
+/// PGN import/export and SAN move notation
+use crate::moves::{Move, MoveList};
+use crate::position::Position;
+use crate::types::PieceType;
+
+/// Seven tag roster in the order required by the PGN standard
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// Maximum line length for PGN movetext
+const PGN_LINE_WIDTH: usize = 80;
+
+impl Position {
+    /// Convert a legal move to Standard Algebraic Notation
+    pub fn move_to_san(&self, mv: Move) -> String {
+        let from = mv.from_sq();
+        let to = mv.to_sq();
+        let piece_type = match self.piece_at(from) {
+            Some(piece) => piece.piece_type(),
+            None => return mv.to_uci(),
+        };
+
+        let mut san = String::new();
+
+        if mv.is_castle() {
+            san.push_str(if mv.is_kingside_castle() { "O-O" } else { "O-O-O" });
+        } else if piece_type == PieceType::Pawn {
+            if mv.is_capture() {
+                san.push((b'a' + from.file()) as char);
+                san.push('x');
+            }
+            san.push_str(&to.to_algebraic());
+            if mv.is_promotion() {
+                san.push('=');
+                san.push(mv.promotion_piece().to_char().to_ascii_uppercase());
+            }
+        } else {
+            san.push(piece_type.to_char().to_ascii_uppercase());
+
+            // Disambiguate between identical pieces that can reach the same square
+            let mut moves = MoveList::new();
+            self.generate_legal_moves(&mut moves);
+            let others: Vec<Move> = moves
+                .iter()
+                .filter(|&other| {
+                    other != mv
+                        && other.to_sq() == to
+                        && self.piece_at(other.from_sq()).map(|p| p.piece_type())
+                            == Some(piece_type)
+                })
+                .collect();
+
+            if !others.is_empty() {
+                let same_file = others.iter().any(|o| o.from_sq().file() == from.file());
+                let same_rank = others.iter().any(|o| o.from_sq().rank() == from.rank());
+                if !same_file {
+                    san.push((b'a' + from.file()) as char);
+                } else if !same_rank {
+                    san.push((b'1' + from.rank()) as char);
+                } else {
+                    san.push_str(&from.to_algebraic());
+                }
+            }
+
+            if mv.is_capture() {
+                san.push('x');
+            }
+            san.push_str(&to.to_algebraic());
+        }
+
+        // Check and checkmate suffix
+        if self.gives_check(mv) {
+            san.push(if self.make_move(mv).is_checkmate() { '#' } else { '+' });
+        }
+
+        san
+    }
+
+    /// Parse a SAN move (e.g. "Nbd7", "exd5", "e8=Q+", "O-O") into a legal move
+    pub fn parse_san(&self, san: &str) -> Option<Move> {
+        let san = san.trim_end_matches(['+', '#', '!', '?']);
+
+        let mut moves = MoveList::new();
+        self.generate_legal_moves(&mut moves);
+
+        // Castling (accept both letter O and digit 0)
+        match san {
+            "O-O" | "0-0" => return moves.iter().find(|mv| mv.is_kingside_castle()),
+            "O-O-O" | "0-0-0" => return moves.iter().find(|mv| mv.is_queenside_castle()),
+            _ => {}
+        }
+
+        // Promotion suffix, with or without '='
+        let (body, promotion) = match san.char_indices().last() {
+            Some((i, c)) if "QRBNqrbn".contains(c) && i >= 2 => {
+                let piece = PieceType::from_char(c.to_ascii_lowercase())?;
+                (san[..i].trim_end_matches('='), Some(piece))
+            }
+            _ => (san, None),
+        };
+
+        // Leading piece letter (pawn moves have none)
+        let (piece_type, body) = match body.chars().next()? {
+            c @ ('N' | 'B' | 'R' | 'Q' | 'K') => {
+                (PieceType::from_char(c.to_ascii_lowercase())?, &body[1..])
+            }
+            _ => (PieceType::Pawn, body),
+        };
+
+        if body.len() < 2 {
+            return None;
+        }
+        let to = crate::types::Square::from_algebraic(&body[body.len() - 2..])?;
+
+        // Whatever remains is disambiguation (file and/or rank) and the capture mark
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in body[..body.len() - 2].chars() {
+            match c {
+                'a'..='h' => from_file = Some(c as u8 - b'a'),
+                '1'..='8' => from_rank = Some(c as u8 - b'1'),
+                'x' | '-' => {}
+                _ => return None,
+            }
+        }
+
+        let mut found = None;
+        for mv in moves.iter() {
+            let from = mv.from_sq();
+            if mv.to_sq() != to
+                || self.piece_at(from).map(|p| p.piece_type()) != Some(piece_type)
+                || from_file.is_some_and(|f| from.file() != f)
+                || from_rank.is_some_and(|r| from.rank() != r)
+                || (mv.is_promotion() && Some(mv.promotion_piece()) != promotion)
+                || (!mv.is_promotion() && promotion.is_some())
+            {
+                continue;
+            }
+            if found.is_some() {
+                return None; // Ambiguous
+            }
+            found = Some(mv);
+        }
+
+        found
+    }
+
+    /// Game result string for a final position ("*" if the game is not over)
+    pub fn game_result(&self) -> &'static str {
+        let mut moves = MoveList::new();
+        self.generate_legal_moves(&mut moves);
+
+        if moves.is_empty() {
+            if self.is_in_check() {
+                match self.side_to_move {
+                    crate::types::Color::White => "0-1",
+                    crate::types::Color::Black => "1-0",
+                }
+            } else {
+                "1/2-1/2"
+            }
+        } else if self.halfmove_clock >= 100 {
+            "1/2-1/2"
+        } else {
+            "*"
+        }
+    }
+}
+
+/// Render a game as PGN with the seven tag roster, SAN movetext and result
+pub fn to_pgn(moves: &[Move], start: &Position, tags: &[(String, String)]) -> String {
+    // Play out the game to produce SAN and find the final position
+    let mut pos = start.clone();
+    let mut tokens = Vec::with_capacity(moves.len() * 3 / 2 + 1);
+
+    for (i, &mv) in moves.iter().enumerate() {
+        let white_to_move = pos.side_to_move == crate::types::Color::White;
+        if white_to_move {
+            tokens.push(format!("{}.", pos.fullmove_number));
+        } else if i == 0 {
+            tokens.push(format!("{}...", pos.fullmove_number));
+        }
+        tokens.push(pos.move_to_san(mv));
+        pos = pos.make_move(mv);
+    }
+
+    let tag_value = |name: &str| {
+        tags.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    };
+    let result = tag_value("Result").unwrap_or_else(|| pos.game_result().to_string());
+    tokens.push(result.clone());
+
+    let mut pgn = String::new();
+
+    // Seven tag roster first, then any extra tags
+    for (name, default) in SEVEN_TAG_ROSTER {
+        let value = if name == "Result" {
+            result.clone()
+        } else {
+            tag_value(name).unwrap_or_else(|| default.to_string())
+        };
+        pgn.push_str(&format!("[{} \"{}\"]\n", name, escape_tag(&value)));
+    }
+
+    let start_fen = start.to_fen();
+    if start_fen != Position::STARTPOS && tag_value("FEN").is_none() {
+        pgn.push_str("[SetUp \"1\"]\n");
+        pgn.push_str(&format!("[FEN \"{}\"]\n", start_fen));
+    }
+
+    for (name, value) in tags {
+        if !SEVEN_TAG_ROSTER.iter().any(|(roster, _)| roster == name) {
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, escape_tag(value)));
+        }
+    }
+
+    pgn.push('\n');
+
+    // Movetext wrapped to the standard line width
+    let mut line_len = 0;
+    for token in tokens {
+        if line_len > 0 && line_len + 1 + token.len() > PGN_LINE_WIDTH {
+            pgn.push('\n');
+            line_len = 0;
+        }
+        if line_len > 0 {
+            pgn.push(' ');
+            line_len += 1;
+        }
+        pgn.push_str(&token);
+        line_len += token.len();
+    }
+    pgn.push('\n');
+
+    pgn
+}
+
+/// Parse a PGN game and play through its main line
+///
+/// Returns the final position and the moves played. Comments, NAGs and
+/// variations are skipped. A `FEN` tag sets the starting position.
+pub fn from_pgn(pgn: &str) -> Result<(Position, Vec<Move>), &'static str> {
+    let mut pos = Position::new();
+    let mut movetext = String::new();
+
+    for line in pgn.lines() {
+        let line = line.trim();
+        if let Some(tag) = line.strip_prefix('[') {
+            let tag = tag.trim_end_matches(']');
+            if let Some((name, value)) = tag.split_once(' ') {
+                if name == "FEN" {
+                    pos = Position::from_fen(value.trim().trim_matches('"'))?;
+                }
+            }
+        } else {
+            // Rest-of-line comments
+            let line = line.split(';').next().unwrap_or("");
+            movetext.push_str(line);
+            movetext.push(' ');
+        }
+    }
+
+    // Drop brace comments and (possibly nested) variations
+    let mut cleaned = String::with_capacity(movetext.len());
+    let mut in_comment = false;
+    let mut variation_depth = 0usize;
+    for c in movetext.chars() {
+        match c {
+            '{' if !in_comment => in_comment = true,
+            '}' if in_comment => in_comment = false,
+            '(' if !in_comment => variation_depth += 1,
+            ')' if !in_comment => variation_depth = variation_depth.saturating_sub(1),
+            _ if in_comment || variation_depth > 0 => {}
+            _ => cleaned.push(c),
+        }
+    }
+
+    let mut moves = Vec::new();
+    for token in cleaned.split_whitespace() {
+        if matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*") {
+            break;
+        }
+
+        // Strip move numbers, including ones attached to the move ("1.e4", "3...Nf6")
+        let token = token.rsplit('.').next().unwrap_or("");
+        if token.is_empty() || token.starts_with('$') {
+            continue;
+        }
+
+        let mv = pos.parse_san(token).ok_or("Illegal or ambiguous SAN move in PGN")?;
+        pos = pos.make_move(mv);
+        moves.push(mv);
+    }
+
+    Ok((pos, moves))
+}
+
+/// Escape backslashes and quotes in a PGN tag value
+fn escape_tag(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::magic::init_magics;
+
+    fn setup() {
+        static INIT: std::sync::Once = std::sync::Once::new();
+        INIT.call_once(|| {
+            init_magics();
+        });
+    }
+
+    fn play(start: &Position, ucis: &[&str]) -> Vec<Move> {
+        let mut pos = start.clone();
+        let mut moves = Vec::new();
+        for uci in ucis {
+            let mv = pos.parse_uci_move(uci).unwrap();
+            moves.push(mv);
+            pos = pos.make_move(mv);
+        }
+        moves
+    }
+
+    #[test]
+    fn test_move_to_san() {
+        setup();
+        let pos = Position::new();
+        assert_eq!(pos.move_to_san(pos.parse_uci_move("e2e4").unwrap()), "e4");
+        assert_eq!(pos.move_to_san(pos.parse_uci_move("g1f3").unwrap()), "Nf3");
+
+        // Castling and promotion with capture
+        let pos = Position::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(pos.move_to_san(pos.parse_uci_move("e1g1").unwrap()), "O-O");
+        assert_eq!(pos.move_to_san(pos.parse_uci_move("e1c1").unwrap()), "O-O-O");
+        assert_eq!(pos.move_to_san(pos.parse_uci_move("b7a8q").unwrap()), "bxa8=Q+");
+
+        // File and rank disambiguation
+        let pos = Position::from_fen("4k3/8/8/8/R6R/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(pos.move_to_san(pos.parse_uci_move("a4d4").unwrap()), "Rad4");
+        let pos = Position::from_fen("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(pos.move_to_san(pos.parse_uci_move("a1a3").unwrap()), "R1a3");
+    }
+
+    #[test]
+    fn test_to_pgn_scholars_mate() {
+        setup();
+        let start = Position::new();
+        let moves = play(
+            &start,
+            &["e2e4", "e7e5", "f1c4", "b8c6", "d1h5", "g8f6", "h5f7"],
+        );
+        let tags = vec![
+            ("White".to_string(), "Kai".to_string()),
+            ("Black".to_string(), "Human".to_string()),
+        ];
+
+        let pgn = to_pgn(&moves, &start, &tags);
+
+        assert!(pgn.starts_with("[Event \"?\"]\n[Site \"?\"]\n"));
+        assert!(pgn.contains("[White \"Kai\"]\n[Black \"Human\"]\n[Result \"1-0\"]\n"));
+        assert!(pgn.contains("\n\n1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0\n"));
+    }
+
+    #[test]
+    fn test_to_pgn_from_fen_black_to_move() {
+        setup();
+        let start =
+            Position::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")
+                .unwrap();
+        let moves = play(&start, &["c7c5", "g1f3"]);
+
+        let pgn = to_pgn(&moves, &start, &[]);
+
+        assert!(pgn.contains("[SetUp \"1\"]\n"));
+        assert!(pgn.contains(&format!("[FEN \"{}\"]\n", start.to_fen())));
+        assert!(pgn.ends_with("1... c5 2. Nf3 *\n"));
+    }
+
+    #[test]
+    fn test_parse_san() {
+        setup();
+        let pos = Position::from_fen("r3k2r/1P6/8/8/R6R/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(pos.parse_san("O-O").unwrap().to_uci(), "e1g1");
+        assert_eq!(pos.parse_san("0-0-0").unwrap().to_uci(), "e1c1");
+        assert_eq!(pos.parse_san("bxa8=Q+").unwrap().to_uci(), "b7a8q");
+        assert_eq!(pos.parse_san("bxa8N").unwrap().to_uci(), "b7a8n");
+        assert_eq!(pos.parse_san("Rad4").unwrap().to_uci(), "a4d4");
+        assert_eq!(pos.parse_san("R1a2").unwrap().to_uci(), "a1a2");
+        // Ambiguous without disambiguation
+        assert!(pos.parse_san("Rd4").is_none());
+        assert!(pos.parse_san("Ra2").is_none());
+    }
+
+    #[test]
+    fn test_from_pgn() {
+        setup();
+        let pgn = r#"[Event "Test"]
+[White "A"]
+[Black "B"]
+[Result "*"]
+
+1. e4 {best by test} e5 2. Nf3 (2. f4 exf4) Nc6 $1 3.Bb5 a6 4. Ba4 ; Ruy Lopez
+4... Nf6 5. 0-0 Be7 *
+"#;
+
+        let (pos, moves) = from_pgn(pgn).unwrap();
+
+        assert_eq!(moves.len(), 10);
+        assert_eq!(
+            pos.to_fen(),
+            "r1bqk2r/1pppbppp/p1n2n2/4p3/B3P3/5N2/PPPP1PPP/RNBQ1RK1 w kq - 4 6"
+        );
+    }
+
+    #[test]
+    fn test_pgn_roundtrip() {
+        setup();
+        let start = Position::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let moves = play(&start, &["e1c1", "b4c3", "d2c3", "e8g8", "e5d7", "f6d7"]);
+
+        let (pos, parsed) = from_pgn(&to_pgn(&moves, &start, &[])).unwrap();
+
+        assert_eq!(parsed, moves);
+        assert_eq!(pos.to_fen(), play_to_fen(&start, &moves));
+    }
+
+    fn play_to_fen(start: &Position, moves: &[Move]) -> String {
+        moves
+            .iter()
+            .fold(start.clone(), |pos, &mv| pos.make_move(mv))
+            .to_fen()
+    }
+}
@@ -1,11 +1,13 @@
 /// Board representation and FEN parsing
-use crate::bitboard::{king_attacks, knight_attacks, pawn_attacks, Bitboard, KING_ATTACKS};
-use crate::magic::{bishop_attacks, queen_attacks, rook_attacks};
+use crate::bitboard::{king_attacks, knight_attacks, pawn_attacks, Bitboard};
+use crate::magic::{bishop_attacks, rook_attacks};
+use crate::moves::Move;
+use crate::see::see_piece_value;
 use crate::types::{CastlingRights, Color, Piece, PieceType, Square};
 use crate::zobrist::ZOBRIST;
 
 /// Represents a chess position
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct Position {
     /// Piece bitboards: [color][piece_type]
     pub pieces: [[Bitboard; 6]; 2],
@@ -25,12 +27,20 @@ pub struct Position {
     /// Castling rights
     pub castling: CastlingRights,
 
+    /// Home square of each castling rook, indexed by `CastlingRights::slot`. Only
+    /// Chess960 positions move them off the corners.
+    pub castling_rooks: [Square; 4],
+
     /// En passant target square (if any)
     pub en_passant: Option<Square>,
 
     /// Halfmove clock (for 50-move rule)
     pub halfmove_clock: u8,
 
+    /// Plies since the last null move, `u8::MAX` if there was none; no position before
+    /// a null move can repeat after it
+    pub plies_from_null: u8,
+
     /// Fullmove number
     pub fullmove_number: u16,
 
@@ -42,6 +52,89 @@ pub struct Position {
 
     /// Checkers bitboard (pieces giving check)
     pub checkers: Bitboard,
+
+    /// Pins and check squares, cached alongside `checkers`
+    pub check_info: CheckInfo,
+}
+
+/// Pin and check data computed once per position
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CheckInfo {
+    /// Pieces of either color that alone block a slider attack on each king
+    pub blockers: [Bitboard; 2],
+    /// Sliders pinning a piece of the king's color, indexed by king color
+    pub pinners: [Bitboard; 2],
+    /// Squares from which each piece type of the side to move checks the enemy king
+    pub check_squares: [Bitboard; 6],
+}
+
+/// Reasons a FEN string can be rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenError {
+    /// The FEN string has no fields
+    Empty,
+    /// Unknown character in the piece placement field
+    InvalidPiece(char),
+    /// Piece placement does not describe exactly 8 ranks of 8 squares
+    InvalidPlacement,
+    /// Side to move is not "w" or "b"
+    InvalidSideToMove,
+    /// En passant field is not a square
+    InvalidEnPassant,
+    /// A side does not have exactly one king
+    KingCount(Color),
+    /// A pawn stands on the first or eighth rank
+    PawnOnBackRank,
+    /// A side has more than 16 pieces
+    TooManyPieces(Color),
+    /// En passant square does not match the side to move and pawn placement
+    EnPassantMismatch,
+    /// A castling right has no king or rook on its home square
+    CastlingMismatch(CastlingRights),
+    /// The side that just moved is in check
+    OpponentInCheck,
+}
+
+impl FenError {
+    /// Short description of the failed check
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FenError::Empty => "Empty FEN string",
+            FenError::InvalidPiece(_) => "Invalid piece character in FEN",
+            FenError::InvalidPlacement => "Piece placement must have 8 ranks of 8 squares",
+            FenError::InvalidSideToMove => "Invalid side to move",
+            FenError::InvalidEnPassant => "Invalid en passant square",
+            FenError::KingCount(_) => "Each side must have exactly one king",
+            FenError::PawnOnBackRank => "Pawns cannot stand on the first or eighth rank",
+            FenError::TooManyPieces(_) => "A side cannot have more than 16 pieces",
+            FenError::EnPassantMismatch => "En passant square inconsistent with side to move",
+            FenError::CastlingMismatch(_) => {
+                "Castling rights inconsistent with king and rook placement"
+            }
+            FenError::OpponentInCheck => "The side not to move is in check",
+        }
+    }
+}
+
+impl std::fmt::Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FenError::InvalidPiece(c) => write!(f, "{} '{}'", self.as_str(), c),
+            FenError::KingCount(color) | FenError::TooManyPieces(color) => {
+                write!(f, "{} ({:?})", self.as_str(), color)
+            }
+            FenError::CastlingMismatch(rights) => write!(f, "{} ({})", self.as_str(), rights),
+            _ => write!(f, "{}", self.as_str()),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl From<FenError> for &'static str {
+    fn from(err: FenError) -> Self {
+        err.as_str()
+    }
 }
 
 impl Position {
@@ -49,6 +142,9 @@ impl Position {
     pub const STARTPOS: &'static str =
         "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
+    /// Castling rook squares of standard chess
+    const STANDARD_CASTLING_ROOKS: [Square; 4] = [Square::H1, Square::A1, Square::H8, Square::A8];
+
     /// Create an empty position
     pub fn empty() -> Self {
         Position {
@@ -58,12 +154,15 @@ impl Position {
             board: [None; 64],
             side_to_move: Color::White,
             castling: CastlingRights::NONE,
+            castling_rooks: Self::STANDARD_CASTLING_ROOKS,
             en_passant: None,
             halfmove_clock: 0,
+            plies_from_null: u8::MAX,
             fullmove_number: 1,
             hash: 0,
             king_sq: [Square::E1, Square::E8],
             checkers: Bitboard::EMPTY,
+            check_info: CheckInfo::default(),
         }
     }
 
@@ -73,33 +172,43 @@ impl Position {
     }
 
     /// Parse a position from FEN string
-    pub fn from_fen(fen: &str) -> Result<Self, &'static str> {
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
         let mut pos = Self::empty();
         let parts: Vec<&str> = fen.split_whitespace().collect();
 
         if parts.is_empty() {
-            return Err("Empty FEN string");
+            return Err(FenError::Empty);
         }
 
-        // Parse piece placement
-        let mut sq = 56u8; // Start at a8
-        for c in parts[0].chars() {
-            match c {
-                '/' => {
-                    sq = sq.wrapping_sub(16); // Move to next rank down
-                }
-                '1'..='8' => {
-                    sq += (c as u8) - b'0';
-                }
-                _ => {
-                    if let Some(piece) = Piece::from_char(c) {
-                        pos.put_piece(Square(sq), piece);
-                        sq += 1;
-                    } else {
-                        return Err("Invalid piece character in FEN");
+        // Parse piece placement, rank 8 first
+        let ranks: Vec<&str> = parts[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::InvalidPlacement);
+        }
+        for (i, rank_str) in ranks.iter().enumerate() {
+            let rank = 7 - i as u8;
+            let mut file = 0u8;
+            for c in rank_str.chars() {
+                match c {
+                    '1'..='8' => {
+                        file += (c as u8) - b'0';
                     }
+                    _ => {
+                        let piece = Piece::from_char(c).ok_or(FenError::InvalidPiece(c))?;
+                        if file >= 8 {
+                            return Err(FenError::InvalidPlacement);
+                        }
+                        pos.put_piece(Square::from_coords(file, rank), piece);
+                        file += 1;
+                    }
+                }
+                if file > 8 {
+                    return Err(FenError::InvalidPlacement);
                 }
             }
+            if file != 8 {
+                return Err(FenError::InvalidPlacement);
+            }
         }
 
         // Parse side to move
@@ -107,18 +216,19 @@ impl Position {
             pos.side_to_move = match parts[1] {
                 "w" => Color::White,
                 "b" => Color::Black,
-                _ => return Err("Invalid side to move"),
+                _ => return Err(FenError::InvalidSideToMove),
             };
         }
 
         // Parse castling rights
         if parts.len() > 2 {
-            pos.castling = CastlingRights::from_fen(parts[2]);
+            pos.parse_castling(parts[2])?;
         }
 
         // Parse en passant square
         if parts.len() > 3 && parts[3] != "-" {
-            pos.en_passant = Square::from_algebraic(parts[3]);
+            pos.en_passant =
+                Some(Square::from_algebraic(parts[3]).ok_or(FenError::InvalidEnPassant)?);
         }
 
         // Parse halfmove clock
@@ -131,15 +241,219 @@ impl Position {
             pos.fullmove_number = parts[5].parse().unwrap_or(1);
         }
 
+        pos.validate()?;
+
         // Compute hash
         pos.hash = pos.compute_hash();
 
         // Compute checkers
         pos.checkers = pos.compute_checkers();
+        pos.check_info = pos.compute_check_info();
 
         Ok(pos)
     }
 
+    /// Parse the castling field: standard/X-FEN KQkq, or Shredder-FEN rook files (HAha) for Chess960.
+    /// K and Q name the outermost rook on that side of the king, wherever the king stands.
+    fn parse_castling(&mut self, field: &str) -> Result<(), FenError> {
+        for c in field.chars() {
+            let color = if c.is_ascii_uppercase() {
+                Color::White
+            } else {
+                Color::Black
+            };
+            let rank = if color == Color::White { 0 } else { 7 };
+            let king = self.king_sq[color as usize];
+
+            let (right, rook_sq) = match c.to_ascii_lowercase() {
+                '-' => break,
+                'k' | 'q' => {
+                    let kingside = c.eq_ignore_ascii_case(&'k');
+                    let right = if kingside {
+                        CastlingRights::kingside(color)
+                    } else {
+                        CastlingRights::queenside(color)
+                    };
+                    // Without a rook on that side the corner is kept, and validation rejects it
+                    let rook = Some(Piece::new(color, PieceType::Rook));
+                    let is_rook = |&file: &u8| self.piece_at(Square::from_coords(file, rank)) == rook;
+                    let file = if kingside {
+                        (king.file() + 1..8).rev().find(is_rook).unwrap_or(7)
+                    } else {
+                        (0..king.file()).find(is_rook).unwrap_or(0)
+                    };
+                    let rook_sq = Square::from_coords(file, rank);
+                    (right, rook_sq)
+                }
+                file @ 'a'..='h' => {
+                    let file = file as u8 - b'a';
+                    let right = if file > king.file() {
+                        CastlingRights::kingside(color)
+                    } else {
+                        CastlingRights::queenside(color)
+                    };
+                    (right, Square::from_coords(file, rank))
+                }
+                _ => continue,
+            };
+
+            self.castling = self.castling.insert(right);
+            self.castling_rooks[right.slot()] = rook_sq;
+        }
+        Ok(())
+    }
+
+    /// Home square of the rook for a single castling right
+    #[inline(always)]
+    pub fn castling_rook(&self, right: CastlingRights) -> Square {
+        self.castling_rooks[right.slot()]
+    }
+
+    /// Whether a castling right is held with a king or rook off its standard square,
+    /// which only Chess960 positions allow
+    pub fn is_chess960(&self) -> bool {
+        CastlingRights::SINGLE.iter().any(|&right| {
+            let color = if right.0 & CastlingRights::WHITE_BOTH.0 != 0 {
+                Color::White
+            } else {
+                Color::Black
+            };
+            self.castling.contains(right)
+                && (self.king_sq[color as usize].file() != 4
+                    || self.castling_rook(right) != Self::STANDARD_CASTLING_ROOKS[right.slot()])
+        })
+    }
+
+    /// Castling field for `to_fen`: KQkq, or Shredder-FEN rook files for Chess960
+    fn castling_fen(&self) -> String {
+        if !self.is_chess960() {
+            return self.castling.to_fen();
+        }
+
+        let mut s = String::new();
+        for (i, &right) in CastlingRights::SINGLE.iter().enumerate() {
+            if self.castling.contains(right) {
+                let file = (b'A' + self.castling_rook(right).file()) as char;
+                s.push(if i < 2 { file } else { file.to_ascii_lowercase() });
+            }
+        }
+        s
+    }
+
+    /// Check that a freshly parsed position is legal enough to search
+    fn validate(&self) -> Result<(), FenError> {
+        for color in Color::ALL {
+            if self.piece_bb(color, PieceType::King).pop_count() != 1 {
+                return Err(FenError::KingCount(color));
+            }
+            if self.pieces_of(color).pop_count() > 16 {
+                return Err(FenError::TooManyPieces(color));
+            }
+        }
+
+        let pawns = self.piece_bb(Color::White, PieceType::Pawn)
+            | self.piece_bb(Color::Black, PieceType::Pawn);
+        if (pawns & (Bitboard::RANK_1 | Bitboard::RANK_8)).is_not_empty() {
+            return Err(FenError::PawnOnBackRank);
+        }
+
+        // The en passant square lies behind an enemy pawn that just double-pushed
+        if let Some(ep) = self.en_passant {
+            let (ep_rank, pawn_sq, them) = match self.side_to_move {
+                Color::White => (5, ep.0.wrapping_sub(8), Color::Black),
+                Color::Black => (2, ep.0 + 8, Color::White),
+            };
+            if ep.rank() != ep_rank
+                || self.piece_at(ep).is_some()
+                || self.piece_at(Square(pawn_sq)) != Some(Piece::new(them, PieceType::Pawn))
+            {
+                return Err(FenError::EnPassantMismatch);
+            }
+        }
+
+        // Each castling right needs the king on its back rank and the rook beside it
+        for (i, &right) in CastlingRights::SINGLE.iter().enumerate() {
+            if !self.castling.contains(right) {
+                continue;
+            }
+            let (color, rank) = if i < 2 { (Color::White, 0) } else { (Color::Black, 7) };
+            let king = self.king_sq[color as usize];
+            let rook = self.castling_rook(right);
+            let kingside = i % 2 == 0;
+            if king.rank() != rank
+                || self.piece_at(rook) != Some(Piece::new(color, PieceType::Rook))
+                || (rook.file() > king.file()) != kingside
+            {
+                return Err(FenError::CastlingMismatch(right));
+            }
+        }
+
+        // The side to move could otherwise capture the enemy king
+        let them = self.side_to_move.flip();
+        if self.is_attacked_by(self.king_sq[them as usize], self.side_to_move) {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        Ok(())
+    }
+
+    /// Flip the board vertically and swap colors, giving the same position
+    /// from the other side's point of view
+    pub fn mirror(&self) -> Position {
+        let mut pos = Self::empty();
+
+        for sq in Square::iter() {
+            if let Some(piece) = self.board[sq.0 as usize] {
+                let flipped = Piece::new(piece.color().flip(), piece.piece_type());
+                pos.put_piece(sq.flip_rank(), flipped);
+            }
+        }
+
+        pos.side_to_move = self.side_to_move.flip();
+        let c = self.castling.0;
+        pos.castling = CastlingRights(((c & 0b0011) << 2) | ((c & 0b1100) >> 2));
+        let rooks = self.castling_rooks.map(Square::flip_rank);
+        pos.castling_rooks = [rooks[2], rooks[3], rooks[0], rooks[1]];
+        pos.en_passant = self.en_passant.map(Square::flip_rank);
+        pos.halfmove_clock = self.halfmove_clock;
+        pos.plies_from_null = self.plies_from_null;
+        pos.fullmove_number = self.fullmove_number;
+        pos.hash = pos.compute_hash();
+        pos.checkers = pos.compute_checkers();
+        pos.check_info = pos.compute_check_info();
+        pos
+    }
+
+    /// The same position with `color` to move, for "what if" analysis
+    ///
+    /// Unlike the search's null move this keeps the halfmove clock. The en passant
+    /// square is dropped, as it only applies to the side that was to move. Returns
+    /// `None` if the side to move is in check, since the opponent could then take
+    /// the king.
+    pub fn with_side_to_move(&self, color: Color) -> Option<Position> {
+        if color == self.side_to_move {
+            return Some(self.clone());
+        }
+        if self.checkers.is_not_empty() {
+            return None;
+        }
+
+        let mut pos = self.clone();
+        if let Some(ep_sq) = pos.en_passant.take() {
+            pos.hash ^= ZOBRIST.en_passant_key(ep_sq.file());
+        }
+        pos.side_to_move = color;
+        pos.hash ^= ZOBRIST.side_key();
+        pos.checkers = pos.compute_checkers();
+        pos.check_info = pos.compute_check_info();
+        Some(pos)
+    }
+
+    /// The same position with the other side to move; see `with_side_to_move`
+    pub fn flip_side_to_move(&self) -> Option<Position> {
+        self.with_side_to_move(self.side_to_move.flip())
+    }
+
     /// Convert position to FEN string
     pub fn to_fen(&self) -> String {
         let mut fen = String::new();
@@ -179,7 +493,7 @@ impl Position {
 
         // Castling rights
         fen.push(' ');
-        fen.push_str(&self.castling.to_fen());
+        fen.push_str(&self.castling_fen());
 
         // En passant
         fen.push(' ');
@@ -231,6 +545,31 @@ impl Position {
         }
     }
 
+    /// XOR a piece on a square into or out of the hash. `put_piece` and `remove_piece`
+    /// leave the hash alone, so callers editing the board pair them with this.
+    #[inline(always)]
+    pub fn toggle_piece_hash(&mut self, color: Color, piece_type: PieceType, sq: Square) {
+        self.hash ^= ZOBRIST.piece_key(color, piece_type, sq);
+    }
+
+    /// XOR castling rights into or out of the hash
+    #[inline(always)]
+    pub fn toggle_castling_hash(&mut self, rights: CastlingRights) {
+        self.hash ^= ZOBRIST.castling_key(rights);
+    }
+
+    /// XOR an en passant square into or out of the hash
+    #[inline(always)]
+    pub fn toggle_en_passant_hash(&mut self, ep_sq: Square) {
+        self.hash ^= ZOBRIST.en_passant_key(ep_sq.file());
+    }
+
+    /// XOR the side to move into or out of the hash
+    #[inline(always)]
+    pub fn toggle_side_hash(&mut self) {
+        self.hash ^= ZOBRIST.side_key();
+    }
+
     /// Get piece bitboard for a color and piece type
     #[inline(always)]
     pub fn piece_bb(&self, color: Color, piece_type: PieceType) -> Bitboard {
@@ -243,6 +582,26 @@ impl Position {
         self.occupied[color as usize]
     }
 
+    /// Number of pieces of a color and type
+    #[inline(always)]
+    pub fn piece_count(&self, color: Color, piece_type: PieceType) -> u32 {
+        self.piece_bb(color, piece_type).pop_count()
+    }
+
+    /// Material of a color's knights, bishops, rooks and queens, in SEE piece values
+    pub fn non_pawn_material(&self, color: Color) -> i16 {
+        [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen]
+            .iter()
+            .map(|&pt| see_piece_value(pt) * self.piece_count(color, pt) as i16)
+            .sum()
+    }
+
+    /// Material of all of a color's pieces except the king, in SEE piece values
+    pub fn total_material(&self, color: Color) -> i16 {
+        self.non_pawn_material(color)
+            + see_piece_value(PieceType::Pawn) * self.piece_count(color, PieceType::Pawn) as i16
+    }
+
     /// Get the piece at a square
     #[inline(always)]
     pub fn piece_at(&self, sq: Square) -> Option<Piece> {
@@ -255,6 +614,12 @@ impl Position {
         self.checkers.is_not_empty()
     }
 
+    /// Number of pieces checking the side to move (2 in a double check)
+    #[inline(always)]
+    pub fn num_checkers(&self) -> u32 {
+        self.checkers.pop_count()
+    }
+
     /// Get diagonal sliders (bishops and queens)
     #[inline(always)]
     pub fn diagonal_sliders(&self, color: Color) -> Bitboard {
@@ -267,25 +632,49 @@ impl Position {
         self.piece_bb(color, PieceType::Rook) | self.piece_bb(color, PieceType::Queen)
     }
 
+    /// Whether neither side can ever deliver mate: kings with at most one minor piece,
+    /// or with bishops only, all on squares of one colour
+    pub fn is_insufficient_material(&self) -> bool {
+        let both = |pt: PieceType| self.piece_bb(Color::White, pt) | self.piece_bb(Color::Black, pt);
+
+        if (both(PieceType::Pawn) | both(PieceType::Rook) | both(PieceType::Queen)).is_not_empty() {
+            return false;
+        }
+
+        let knights = both(PieceType::Knight);
+        let bishops = both(PieceType::Bishop);
+        if (knights | bishops).pop_count() <= 1 {
+            return true;
+        }
+
+        knights.is_empty()
+            && ((bishops & Bitboard::LIGHT_SQUARES).is_empty()
+                || (bishops & Bitboard::DARK_SQUARES).is_empty())
+    }
+
     /// Get all attackers to a square
+    #[inline(always)]
     pub fn attackers_to(&self, sq: Square, occupied: Bitboard) -> Bitboard {
-        let knights =
-            self.piece_bb(Color::White, PieceType::Knight) | self.piece_bb(Color::Black, PieceType::Knight);
-        let kings =
-            self.piece_bb(Color::White, PieceType::King) | self.piece_bb(Color::Black, PieceType::King);
-        let diag_sliders = self.diagonal_sliders(Color::White) | self.diagonal_sliders(Color::Black);
-        let orth_sliders =
-            self.orthogonal_sliders(Color::White) | self.orthogonal_sliders(Color::Black);
-
-        let white_pawns = self.piece_bb(Color::White, PieceType::Pawn);
-        let black_pawns = self.piece_bb(Color::Black, PieceType::Pawn);
-
-        (pawn_attacks(Color::Black, sq) & white_pawns)
-            | (pawn_attacks(Color::White, sq) & black_pawns)
-            | (knight_attacks(sq) & knights)
-            | (king_attacks(sq) & kings)
-            | (bishop_attacks(sq, occupied) & diag_sliders)
-            | (rook_attacks(sq, occupied) & orth_sliders)
+        let (white, black) = self.attackers_to_both(sq, occupied);
+        white | black
+    }
+
+    /// Get (white, black) attackers to a square, computing each slider ray once
+    pub fn attackers_to_both(&self, sq: Square, occupied: Bitboard) -> (Bitboard, Bitboard) {
+        let knight = knight_attacks(sq);
+        let king = king_attacks(sq);
+        let bishop = bishop_attacks(sq, occupied);
+        let rook = rook_attacks(sq, occupied);
+
+        let side = |color: Color| {
+            (pawn_attacks(color.flip(), sq) & self.piece_bb(color, PieceType::Pawn))
+                | (knight & self.piece_bb(color, PieceType::Knight))
+                | (king & self.piece_bb(color, PieceType::King))
+                | (bishop & self.diagonal_sliders(color))
+                | (rook & self.orthogonal_sliders(color))
+        };
+
+        (side(Color::White), side(Color::Black))
     }
 
     /// Get attackers of a specific color to a square
@@ -313,9 +702,55 @@ impl Position {
 
     /// Compute checkers bitboard
     pub fn compute_checkers(&self) -> Bitboard {
+        self.checkers_of(self.side_to_move)
+    }
+
+    /// Enemy pieces giving check to `color`'s king, whichever side is to move
+    pub fn checkers_of(&self, color: Color) -> Bitboard {
+        let king_sq = self.king_sq[color as usize];
+        self.attackers_to_by(king_sq, color.flip(), self.all_occupied)
+    }
+
+    /// Compute pins and check squares for the current side to move
+    pub fn compute_check_info(&self) -> CheckInfo {
+        let mut info = CheckInfo::default();
+
+        for color in Color::ALL {
+            let king_sq = self.king_sq[color as usize];
+            let them = color.flip();
+
+            // Enemy sliders that would attack the king on an empty board
+            let snipers = (bishop_attacks(king_sq, Bitboard::EMPTY) & self.diagonal_sliders(them))
+                | (rook_attacks(king_sq, Bitboard::EMPTY) & self.orthogonal_sliders(them));
+
+            for sniper in snipers {
+                let between = crate::bitboard::between(king_sq, sniper) & self.all_occupied;
+                if between.exactly_one() {
+                    info.blockers[color as usize] |= between;
+                    if (between & self.occupied[color as usize]).is_not_empty() {
+                        info.pinners[color as usize] = info.pinners[color as usize].set(sniper);
+                    }
+                }
+            }
+        }
+
         let us = self.side_to_move;
-        let king_sq = self.king_sq[us as usize];
-        self.attackers_to_by(king_sq, us.flip(), self.all_occupied)
+        let king_sq = self.king_sq[us.flip() as usize];
+        let diag = bishop_attacks(king_sq, self.all_occupied);
+        let orth = rook_attacks(king_sq, self.all_occupied);
+        info.check_squares[PieceType::Pawn as usize] = pawn_attacks(us.flip(), king_sq);
+        info.check_squares[PieceType::Knight as usize] = knight_attacks(king_sq);
+        info.check_squares[PieceType::Bishop as usize] = diag;
+        info.check_squares[PieceType::Rook as usize] = orth;
+        info.check_squares[PieceType::Queen as usize] = diag | orth;
+
+        info
+    }
+
+    /// Pieces of `color` pinned to their own king (cached)
+    #[inline(always)]
+    pub fn pinned(&self, color: Color) -> Bitboard {
+        self.check_info.blockers[color as usize] & self.occupied[color as usize]
     }
 
     /// Compute the full Zobrist hash from scratch
@@ -323,15 +758,8 @@ impl Position {
         let mut hash = 0u64;
 
         // Piece keys
-        for color in [Color::White, Color::Black] {
-            for piece_type in [
-                PieceType::Pawn,
-                PieceType::Knight,
-                PieceType::Bishop,
-                PieceType::Rook,
-                PieceType::Queen,
-                PieceType::King,
-            ] {
+        for color in Color::ALL {
+            for piece_type in PieceType::ALL {
                 let mut bb = self.piece_bb(color, piece_type);
                 while bb.is_not_empty() {
                     let sq = bb.pop_lsb();
@@ -387,6 +815,78 @@ impl Position {
         pinned
     }
 
+    /// Check if a legal move gives check, without making it
+    pub fn gives_check(&self, mv: Move) -> bool {
+        let us = self.side_to_move;
+        let king_sq = self.king_sq[us.flip() as usize];
+        let from = mv.from_sq();
+        let to = mv.to_sq();
+
+        let piece_type = match self.piece_at(from) {
+            Some(piece) => piece.piece_type(),
+            None => return false,
+        };
+
+        // Ordinary moves: direct check from the cached check squares, or a discovered
+        // check when a blocker of the enemy king leaves the line to it
+        if !mv.is_promotion() && !mv.is_en_passant() && !mv.is_castle() {
+            let ci = &self.check_info;
+            return ci.check_squares[piece_type as usize].contains(to)
+                || ((ci.blockers[us.flip() as usize] & self.occupied[us as usize]).contains(from)
+                    && !crate::bitboard::aligned(from, to, king_sq));
+        }
+
+        let final_type = if mv.is_promotion() {
+            mv.promotion_piece()
+        } else {
+            piece_type
+        };
+
+        // Castling can only check with the rook, directly or by uncovering one of ours
+        if mv.is_castle() {
+            let (king_to, rook_to) = mv.castling_destinations();
+            let occupied = self.all_occupied.clear(from).clear(to).set(king_to).set(rook_to);
+            let orth = self.orthogonal_sliders(us).clear(to).set(rook_to);
+            return (bishop_attacks(king_sq, occupied) & self.diagonal_sliders(us)).is_not_empty()
+                || (rook_attacks(king_sq, occupied) & orth).is_not_empty();
+        }
+
+        // Occupancy and our sliders after the move
+        let mut occupied = self.all_occupied.clear(from).set(to);
+        let mut diag = self.diagonal_sliders(us).clear(from);
+        let mut orth = self.orthogonal_sliders(us).clear(from);
+
+        match final_type {
+            PieceType::Pawn => {
+                if pawn_attacks(us, to).contains(king_sq) {
+                    return true;
+                }
+            }
+            PieceType::Knight => {
+                if knight_attacks(to).contains(king_sq) {
+                    return true;
+                }
+            }
+            PieceType::Bishop => diag = diag.set(to),
+            PieceType::Rook => orth = orth.set(to),
+            PieceType::Queen => {
+                diag = diag.set(to);
+                orth = orth.set(to);
+            }
+            PieceType::King => {}
+        }
+
+        // En passant removes the captured pawn, which may uncover a ray
+        if mv.is_en_passant() {
+            let captured_sq = Square((to.0 as i8 + if us == Color::White { -8 } else { 8 }) as u8);
+            occupied = occupied.clear(captured_sq);
+        }
+
+        // Direct slider checks and discovered checks
+        (bishop_attacks(king_sq, occupied) & diag).is_not_empty()
+            || (rook_attacks(king_sq, occupied) & orth).is_not_empty()
+    }
+
     /// Print the board (for debugging)
     pub fn print(&self) {
         println!();
@@ -489,6 +989,140 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_mirror() {
+        setup();
+        let pos = Position::from_fen(
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b Kq e3 1 2",
+        )
+        .unwrap();
+        let mirrored = pos.mirror();
+
+        assert_eq!(
+            mirrored.to_fen(),
+            "rnbqkb1r/pppp1ppp/5n2/4p3/2P5/8/PP1PPPPP/RNBQKBNR w Qk e6 1 2"
+        );
+        assert_eq!(mirrored.hash, mirrored.compute_hash());
+        assert!(mirrored.mirror() == pos);
+    }
+
+    #[test]
+    fn test_fen_validation_errors() {
+        setup();
+        let cases = [
+            ("", FenError::Empty),
+            (
+                "rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::InvalidPiece('x'),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::InvalidPlacement,
+            ),
+            (
+                "rnbqkbnr/pppppppp/54/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::InvalidPlacement,
+            ),
+            (
+                "rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::InvalidPlacement,
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+                FenError::InvalidSideToMove,
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq z9 0 1",
+                FenError::InvalidEnPassant,
+            ),
+            ("4k3/8/8/8/8/8/8/8 w - - 0 1", FenError::KingCount(Color::White)),
+            ("4k3/8/8/8/8/8/8/3KK3 w - - 0 1", FenError::KingCount(Color::White)),
+            ("8/8/8/8/8/8/8/4K3 w - - 0 1", FenError::KingCount(Color::Black)),
+            ("4k3/8/8/8/8/8/8/P3K3 w - - 0 1", FenError::PawnOnBackRank),
+            ("p3k3/8/8/8/8/8/8/4K3 w - - 0 1", FenError::PawnOnBackRank),
+            (
+                "4k3/8/8/8/8/QQQQQQQQ/QQQQQQQQ/4K3 w - - 0 1",
+                FenError::TooManyPieces(Color::White),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e3 0 1",
+                FenError::EnPassantMismatch,
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq e3 0 1",
+                FenError::EnPassantMismatch,
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1KN1 w KQkq - 0 1",
+                FenError::CastlingMismatch(CastlingRights::WHITE_KINGSIDE),
+            ),
+            (
+                "1nbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::CastlingMismatch(CastlingRights::BLACK_QUEENSIDE),
+            ),
+            ("4k3/8/8/8/8/8/8/4RK2 w - - 0 1", FenError::OpponentInCheck),
+            ("4k3/8/8/8/8/8/3n4/5K2 b - - 0 1", FenError::OpponentInCheck),
+        ];
+
+        for (fen, expected) in cases {
+            assert_eq!(Position::from_fen(fen).err(), Some(expected), "FEN: {}", fen);
+        }
+
+        // Valid en passant and partial castling rights still parse
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "rnbqkbnr/pppp1ppp/8/4pP2/8/8/PPPPP1PP/RNBQKBNR w KQkq e6 0 1",
+        ] {
+            assert!(Position::from_fen(fen).is_ok());
+        }
+        assert!(
+            Position::from_fen("1nbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w Qk - 0 1").is_ok()
+        );
+
+        // Errors still convert to the plain message used elsewhere
+        let msg: &'static str = FenError::PawnOnBackRank.into();
+        assert_eq!(msg, "Pawns cannot stand on the first or eighth rank");
+    }
+
+    #[test]
+    fn test_chess960_castling_fen() {
+        setup();
+        // Shredder-FEN rook files round-trip for a Chess960 start
+        let fen = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+        let pos = Position::from_fen(fen).unwrap();
+        assert!(pos.is_chess960());
+        assert_eq!(pos.castling_rook(CastlingRights::WHITE_QUEENSIDE), Square::F1);
+        assert_eq!(pos.castling_rook(CastlingRights::BLACK_KINGSIDE), Square::H8);
+        assert_eq!(pos.to_fen(), fen);
+        assert_eq!(pos.mirror().mirror(), pos);
+
+        // Standard positions keep KQkq, however the rights were written
+        let pos = Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1")
+            .unwrap();
+        assert!(!pos.is_chess960());
+        assert_eq!(pos.to_fen(), Position::STARTPOS);
+
+        // X-FEN KQkq names the outermost rook on each side of a king off the e-file
+        let pos = Position::from_fen("bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w KQkq - 0 1")
+            .unwrap();
+        assert!(pos.is_chess960());
+        assert_eq!(pos.castling_rook(CastlingRights::WHITE_KINGSIDE), Square::G1);
+        assert_eq!(pos.castling_rook(CastlingRights::WHITE_QUEENSIDE), Square::E1);
+        assert_eq!(pos.castling_rook(CastlingRights::BLACK_KINGSIDE), Square::G8);
+        assert_eq!(pos.castling_rook(CastlingRights::BLACK_QUEENSIDE), Square::E8);
+        assert_eq!(pos.to_fen(), "bqnbrkrn/pppppppp/8/8/8/8/PPPPPPPP/BQNBRKRN w GEge - 0 1");
+
+        // With two rooks on one side, K picks the outer one
+        let pos = Position::from_fen("4k3/8/8/8/8/8/8/1K3RR1 w K - 0 1").unwrap();
+        assert_eq!(pos.castling_rook(CastlingRights::WHITE_KINGSIDE), Square::G1);
+
+        // A rook file with no rook on it is rejected
+        assert_eq!(
+            Position::from_fen("bqnb1rkr/8/8/8/8/8/8/BQ1BNRKR w C - 0 1").err(),
+            Some(FenError::CastlingMismatch(CastlingRights::WHITE_QUEENSIDE))
+        );
+    }
+
     #[test]
     fn test_piece_bitboards() {
         setup();
@@ -527,6 +1161,188 @@ mod tests {
         assert!(pos.is_attacked_by(Square::from_algebraic("f3").unwrap(), Color::White));
     }
 
+    fn gives_check_uci(fen: &str, uci: &str) -> bool {
+        let pos = Position::from_fen(fen).unwrap();
+        let mv = pos
+            .parse_uci_move(uci)
+            .unwrap_or_else(|| panic!("illegal move {} in {}", uci, fen));
+        let result = pos.gives_check(mv);
+        assert_eq!(result, pos.make_move(mv).is_in_check(), "{} in {}", uci, fen);
+        result
+    }
+
+    #[test]
+    fn test_gives_check_direct() {
+        setup();
+        assert!(gives_check_uci("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", "d1d8"));
+        assert!(!gives_check_uci("4k3/8/8/8/4N3/8/8/4K3 w - - 0 1", "e4g5"));
+        assert!(gives_check_uci("4k3/8/8/8/4N3/8/8/4K3 w - - 0 1", "e4f6"));
+        assert!(gives_check_uci("8/3P1k2/8/8/8/8/8/4K3 w - - 0 1", "d7d8n"));
+        assert!(gives_check_uci("4k3/8/3P4/8/8/8/8/4K3 w - - 0 1", "d6d7"));
+    }
+
+    #[test]
+    fn test_gives_check_discovered() {
+        setup();
+        // Bishop moves off the e-file, uncovering the rook
+        assert!(gives_check_uci("4k3/8/8/8/4B3/8/8/4RK2 w - - 0 1", "e4c6"));
+        // En passant removes both pawns from the rank between rook and king
+        assert!(gives_check_uci("8/8/8/R2pP2k/8/8/8/4K3 w - d6 0 1", "e5d6"));
+        // Pawn push that does not leave the ray
+        assert!(!gives_check_uci("4k3/8/8/8/4P3/8/8/4RK2 w - - 0 1", "e4e5"));
+    }
+
+    #[test]
+    fn test_gives_check_castling() {
+        setup();
+        // Rook lands on f1 facing the king on f8
+        assert!(gives_check_uci("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"));
+        // Queenside rook lands on d1 facing the king on d8
+        assert!(gives_check_uci("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", "e1c1"));
+        assert!(!gives_check_uci("6k1/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"));
+    }
+
+    #[test]
+    fn test_gives_check_matches_make_move() {
+        setup();
+        let fens = [
+            Position::STARTPOS,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ];
+
+        for fen in fens {
+            let pos = Position::from_fen(fen).unwrap();
+            let mut moves = crate::moves::MoveList::new();
+            pos.generate_legal_moves(&mut moves);
+            for mv in moves.iter() {
+                assert_eq!(
+                    pos.gives_check(mv),
+                    pos.make_move(mv).is_in_check(),
+                    "{} in {}",
+                    mv.to_uci(),
+                    fen
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cached_pins_match_pinned_pieces() {
+        setup();
+        let fens = [
+            Position::STARTPOS,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ];
+
+        for fen in fens {
+            let root = Position::from_fen(fen).unwrap();
+            let mut moves = crate::moves::MoveList::new();
+            root.generate_legal_moves(&mut moves);
+
+            let children = moves.iter().map(|mv| root.make_move(mv));
+            for pos in std::iter::once(root.clone()).chain(children) {
+                assert_eq!(pos.check_info, pos.compute_check_info(), "{}", pos.to_fen());
+                for color in Color::ALL {
+                    assert_eq!(pos.pinned(color), pos.pinned_pieces(color), "{}", pos.to_fen());
+                }
+            }
+        }
+
+        // Bishop b4 pins the d2 knight against e1
+        let pos = Position::from_fen("4k3/8/8/8/1b6/8/3N4/4K3 w - - 0 1").unwrap();
+        assert_eq!(pos.pinned(Color::White), Bitboard::from_square(Square::D2));
+        assert_eq!(
+            pos.check_info.pinners[Color::White as usize],
+            Bitboard::from_square(Square::B4)
+        );
+    }
+
+    #[test]
+    fn test_material_helpers() {
+        setup();
+        let pos = Position::new();
+        for color in Color::ALL {
+            assert_eq!(pos.piece_count(color, PieceType::Pawn), 8);
+            assert_eq!(pos.piece_count(color, PieceType::Knight), 2);
+            // 2 * 300 + 2 * 300 + 2 * 500 + 900
+            assert_eq!(pos.non_pawn_material(color), 3100);
+            assert_eq!(pos.total_material(color), 3900);
+        }
+
+        let pos = Position::from_fen("4k3/pp6/8/8/8/8/5PPP/3RK3 w - - 0 1").unwrap();
+        assert_eq!(pos.non_pawn_material(Color::White), 500);
+        assert_eq!(pos.non_pawn_material(Color::Black), 0);
+        assert_eq!(pos.total_material(Color::White), 800);
+        assert_eq!(pos.total_material(Color::Black), 200);
+    }
+
+    #[test]
+    fn test_attackers_to_both_matches_per_color() {
+        setup();
+        let fens = [
+            Position::STARTPOS,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ];
+
+        for fen in fens {
+            let pos = Position::from_fen(fen).unwrap();
+            for sq in Square::iter() {
+                let (white, black) = pos.attackers_to_both(sq, pos.all_occupied);
+                assert_eq!(white, pos.attackers_to_by(sq, Color::White, pos.all_occupied));
+                assert_eq!(black, pos.attackers_to_by(sq, Color::Black, pos.all_occupied));
+                assert_eq!(pos.attackers_to(sq, pos.all_occupied), white | black);
+            }
+        }
+    }
+
+    #[test]
+    fn test_flip_side_to_move() {
+        setup();
+        let pos = Position::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let flipped = pos.flip_side_to_move().unwrap();
+        assert_eq!(flipped.side_to_move, Color::Black);
+        assert_eq!(flipped.hash, flipped.compute_hash());
+        assert_eq!(flipped.flip_side_to_move().unwrap().hash, pos.hash);
+        assert_eq!(pos.with_side_to_move(Color::White).unwrap().hash, pos.hash);
+
+        // The en passant square belongs to the side that was to move
+        let ep = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        let flipped = ep.flip_side_to_move().unwrap();
+        assert_eq!(flipped.en_passant, None);
+        assert_eq!(flipped.hash, flipped.compute_hash());
+
+        // Black to move in check: with White to move the king could be taken
+        let check = Position::from_fen("4k3/8/8/8/8/8/8/4RK2 b - - 0 1").unwrap();
+        assert!(check.flip_side_to_move().is_none());
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        let insufficient = |fen: &str| Position::from_fen(fen).unwrap().is_insufficient_material();
+
+        assert!(insufficient("8/8/4k3/8/8/3K4/8/8 w - - 0 1"));
+        assert!(insufficient("8/8/4k3/8/8/3K4/8/6N1 w - - 0 1"));
+        assert!(insufficient("8/8/4k3/8/8/3K4/8/5B2 b - - 0 1"));
+        // Bishops on same-coloured squares, even on both sides
+        assert!(insufficient("8/8/4k3/8/2b5/3K4/8/5B2 w - - 0 1"));
+
+        assert!(!insufficient("8/8/4k3/8/3b4/3K4/8/5B2 w - - 0 1"));
+        assert!(!insufficient("8/8/4k3/8/8/3K4/8/5NN1 w - - 0 1"));
+        assert!(!insufficient("8/8/4k3/8/8/3K4/6P1/8 w - - 0 1"));
+        assert!(!insufficient("8/8/4k3/8/8/3K4/8/7R w - - 0 1"));
+    }
+
     #[test]
     fn test_hash_stability() {
         setup();
@@ -539,4 +1355,58 @@ mod tests {
             .unwrap();
         assert_ne!(pos1.hash, pos3.hash);
     }
+
+    #[test]
+    fn test_toggle_hash_helpers() {
+        setup();
+        let mut pos = Position::new();
+        let original = pos.hash;
+
+        pos.toggle_piece_hash(Color::White, PieceType::Knight, Square::E5);
+        assert_ne!(pos.hash, original);
+        pos.toggle_piece_hash(Color::White, PieceType::Knight, Square::E5);
+        assert_eq!(pos.hash, original);
+
+        // Placing a piece by hand and toggling its key agrees with a full recompute
+        pos.put_piece(Square::E5, Piece::new(Color::White, PieceType::Knight));
+        pos.toggle_piece_hash(Color::White, PieceType::Knight, Square::E5);
+        assert_eq!(pos.hash, pos.compute_hash());
+
+        pos.remove_piece(Square::E5);
+        pos.toggle_piece_hash(Color::White, PieceType::Knight, Square::E5);
+        assert_eq!(pos.hash, original);
+        assert_eq!(pos.hash, pos.compute_hash());
+
+        // Likewise for the non-piece keys
+        pos.castling = pos.castling.remove(CastlingRights::WHITE_KINGSIDE);
+        pos.toggle_castling_hash(CastlingRights::ALL);
+        pos.toggle_castling_hash(pos.castling);
+        pos.side_to_move = Color::Black;
+        pos.toggle_side_hash();
+        pos.en_passant = Some(Square::E3);
+        pos.toggle_en_passant_hash(Square::E3);
+        assert_eq!(pos.hash, pos.compute_hash());
+    }
+
+    #[test]
+    fn test_checkers_of_and_num_checkers() {
+        setup();
+        let cases = [
+            ("4k3/8/8/8/8/8/8/6K1 b - - 0 1", 0),
+            ("4k3/8/8/8/8/8/8/4R1K1 b - - 0 1", 1),
+            ("4k3/8/3N4/8/8/8/8/4R1K1 b - - 0 1", 2),
+        ];
+        for (fen, expected) in cases {
+            let pos = Position::from_fen(fen).unwrap();
+            assert_eq!(pos.num_checkers(), expected, "{}", fen);
+            assert_eq!(pos.checkers_of(Color::Black), pos.checkers, "{}", fen);
+            assert!(pos.checkers_of(Color::White).is_empty(), "{}", fen);
+        }
+
+        // Works for White's king too
+        let pos = Position::from_fen("4k3/8/8/8/8/8/8/r5K1 w - - 0 1").unwrap();
+        assert_eq!(pos.checkers_of(Color::White), Bitboard::from_square(Square::A1));
+        assert!(pos.checkers_of(Color::Black).is_empty());
+        assert_eq!(pos.num_checkers(), 1);
+    }
 }
@@ -1,83 +1,119 @@
 /// Quiescence search - search only captures to reach a quiet position
+use crate::bitboard::Bitboard;
+use crate::eval::TOTAL_PHASE;
 use crate::moves::MoveList;
 use crate::ordering::{pick_move, score_captures};
 use crate::position::Position;
-use crate::search::SearchInfo;
+use crate::search::{SearchInfo, MATE_SCORE};
+use crate::see::see_piece_value;
 use crate::tt::TranspositionTable;
+use crate::types::{Color, PieceType};
 
-/// Maximum quiescence depth
-const MAX_QSEARCH_DEPTH: i32 = 10;
+/// Default maximum quiescence depth (`SearchInfo::max_qsearch_depth`)
+pub const MAX_QSEARCH_DEPTH: i32 = 10;
 
-/// Delta pruning margin (queen value)
-const DELTA_MARGIN: i16 = 900;
+/// Delta pruning safety margin on top of the material gain, in the middlegame and endgame
+const DELTA_MARGIN_MG: i32 = 200;
+const DELTA_MARGIN_EG: i32 = 100;
 
 impl Position {
     /// Quiescence search
-    /// qs_ply tracks depth within quiescence search (starts at 0)
+    /// ply is the distance from the root; qs_ply tracks depth within quiescence search (starts at 0)
     pub fn qsearch(
         &self,
         mut alpha: i16,
         beta: i16,
+        ply: i32,
         qs_ply: i32,
         info: &mut SearchInfo,
         _tt: &mut TranspositionTable,
     ) -> i16 {
         info.nodes += 1;
+        info.qnodes += 1;
 
         // Check for timeout periodically
-        if info.nodes & 2047 == 0 && info.should_stop() {
+        if info.nodes & info.stop_check_mask == 0 && info.should_stop() {
             return 0;
         }
 
-        // Stand pat evaluation
-        let stand_pat = self.evaluate();
-
-        // Beta cutoff
-        if stand_pat >= beta {
-            return stand_pat;
+        // 50-move rule
+        if self.is_fifty_move_draw() {
+            return info.draw_score(ply);
         }
 
-        // Delta pruning - if we can't possibly raise alpha, return early
-        if stand_pat + DELTA_MARGIN < alpha {
-            return alpha;
-        }
-
-        // Update alpha
-        if stand_pat > alpha {
-            alpha = stand_pat;
-        }
+        // Stand pat evaluation
+        let stand_pat = self.evaluate_with(&info.eval_params);
 
         // Limit quiescence depth - qs_ply is the depth within qsearch (0 at entry)
-        if qs_ply >= MAX_QSEARCH_DEPTH {
+        if qs_ply >= info.max_qsearch_depth {
             return stand_pat;
         }
 
-        // Generate and score captures
+        // In check there is no standing pat: every evasion is searched instead
+        let in_check = self.is_in_check();
+        let delta_margin = self.delta_margin();
+
         let mut moves = MoveList::new();
-        self.generate_captures(&mut moves);
+        if in_check {
+            self.generate_legal_moves(&mut moves);
+            if moves.is_empty() {
+                return -(MATE_SCORE - ply as i16);
+            }
+        } else {
+            // Beta cutoff
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+
+            // If even the biggest possible gain can't raise alpha, return early. This also
+            // skips the quiet checks, which win no more than the best capture could.
+            if stand_pat.saturating_add(self.max_capture_gain() + delta_margin) < alpha {
+                return alpha;
+            }
+
+            // Update alpha
+            if stand_pat > alpha {
+                alpha = stand_pat;
+            }
+
+            // Captures, plus quiet checks right at the horizon to catch short check sequences
+            self.generate_captures(&mut moves);
+            if qs_ply == 0 {
+                self.generate_quiet_checks(&mut moves);
+            }
+        }
         score_captures(&mut moves, self);
 
         // Search captures
         for i in 0..moves.len() {
             let mv = pick_move(&mut moves, i);
 
-            // SEE pruning - skip clearly losing captures
-            if !self.see_ge(mv, 0) {
+            // SEE pruning - skip clearly losing captures (evasions are all searched)
+            if !in_check && !self.see_ge(mv, 0) {
                 continue;
             }
 
-            // Delta pruning for individual captures
-            if !mv.is_promotion() {
+            // Delta pruning for individual captures, counting what a promotion adds. Quiet
+            // checks win material by force if at all, so they are not pruned per move.
+            let quiet = !mv.is_capture() && !mv.is_promotion();
+            if !in_check && !quiet {
                 let captured_value = if mv.is_en_passant() {
-                    100 // Pawn value
-                } else {
+                    see_piece_value(PieceType::Pawn)
+                } else if mv.is_capture() {
                     match self.piece_at(mv.to_sq()) {
-                        Some(p) => crate::see::see_piece_value(p.piece_type()),
+                        Some(p) => see_piece_value(p.piece_type()),
                         None => continue, // Invalid capture, skip
                     }
+                } else {
+                    0
+                };
+                let promotion_gain = if mv.is_promotion() {
+                    see_piece_value(mv.promotion_piece()) - see_piece_value(PieceType::Pawn)
+                } else {
+                    0
                 };
 
-                if stand_pat + captured_value + 200 < alpha {
+                if stand_pat.saturating_add(captured_value + promotion_gain + delta_margin) < alpha {
                     continue;
                 }
             }
@@ -89,7 +125,7 @@ impl Position {
 
             // Make move and recurse
             let new_pos = self.make_move(mv);
-            let score = -new_pos.qsearch(-beta, -alpha, qs_ply + 1, info, _tt);
+            let score = -new_pos.qsearch(-beta, -alpha, ply + 1, qs_ply + 1, info, _tt);
 
             // Check for timeout
             if info.stopped {
@@ -109,6 +145,27 @@ impl Position {
 
         alpha
     }
+
+    /// Safety margin for delta pruning, more generous while pieces remain for tactics
+    fn delta_margin(&self) -> i16 {
+        let phase = self.game_phase();
+        ((DELTA_MARGIN_MG * phase + DELTA_MARGIN_EG * (TOTAL_PHASE - phase)) / TOTAL_PHASE) as i16
+    }
+
+    /// Most material one capture can win: a queen, plus a promotion if a pawn is about to queen
+    fn max_capture_gain(&self) -> i16 {
+        let queen = see_piece_value(PieceType::Queen);
+        let seventh = match self.side_to_move {
+            Color::White => Bitboard::RANK_7,
+            Color::Black => Bitboard::RANK_2,
+        };
+
+        if (self.piece_bb(self.side_to_move, PieceType::Pawn) & seventh).is_not_empty() {
+            queen + queen - see_piece_value(PieceType::Pawn)
+        } else {
+            queen
+        }
+    }
 }
 
 #[cfg(test)]
@@ -126,6 +183,26 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_qsearch_depth_limit() {
+        setup();
+        // A long exchange on d5: cutting it off after three captures misjudges it
+        let pos =
+            Position::from_fen("3r1k2/3r4/2n1nb2/3p4/4P3/1BN1N3/3R4/3R1K2 w - - 0 1").unwrap();
+        let qsearch = |max_depth| {
+            let mut info = SearchInfo::new(Instant::now());
+            info.max_qsearch_depth = max_depth;
+            let mut tt = TranspositionTable::new(1);
+            let score = pos.qsearch(-30000, 30000, 0, 0, &mut info, &mut tt);
+            assert_eq!(info.qnodes, info.nodes);
+            score
+        };
+
+        let resolved = qsearch(40);
+        assert_eq!(qsearch(MAX_QSEARCH_DEPTH), resolved);
+        assert!(qsearch(3) > resolved + 50, "{} vs {}", qsearch(3), resolved);
+    }
+
     #[test]
     fn test_qsearch_quiet_position() {
         setup();
@@ -133,7 +210,7 @@ mod tests {
         let mut info = SearchInfo::new(Instant::now());
         let mut tt = TranspositionTable::new(1);
 
-        let score = pos.qsearch(-30000, 30000, 0, &mut info, &mut tt);
+        let score = pos.qsearch(-30000, 30000, 0, 0, &mut info, &mut tt);
 
         // Starting position has no captures, should return stand pat
         assert!(score.abs() < 50);
@@ -144,16 +221,48 @@ mod tests {
         setup();
         // White can capture a free queen
         let pos =
-            Position::from_fen("4k3/8/4q3/8/8/4R3/8/4K3 w - - 0 1").unwrap();
+            Position::from_fen("4k3/8/4q3/8/8/4R3/8/3QK3 w - - 0 1").unwrap();
         let mut info = SearchInfo::new(Instant::now());
         let mut tt = TranspositionTable::new(1);
 
-        let score = pos.qsearch(-30000, 30000, 0, &mut info, &mut tt);
+        let score = pos.qsearch(-30000, 30000, 0, 0, &mut info, &mut tt);
 
         // Should find the queen capture
         assert!(score > 800, "Should find winning capture: {}", score);
     }
 
+    #[test]
+    fn test_qsearch_promotion_capture_not_pruned() {
+        setup();
+        // Far behind in an endgame, but bxa8=Q wins a rook and makes a queen
+        let pos = Position::from_fen("r7/1P2r1k1/8/8/8/8/3q4/7K w - - 0 1").unwrap();
+        let mut info = SearchInfo::new(Instant::now());
+        let mut tt = TranspositionTable::new(1);
+
+        // A window well over a queen above the static eval
+        let alpha = pos.evaluate() + 1100;
+        let score = pos.qsearch(alpha, alpha + 1, 0, 0, &mut info, &mut tt);
+
+        assert!(score > alpha, "promotion capture was pruned: {} <= {}", score, alpha);
+    }
+
+    #[test]
+    fn test_qsearch_quiet_check_wins_material() {
+        setup();
+        // Nc7+ forks king and queen; no capture wins anything
+        let pos = Position::from_fen("q3k3/7p/8/3N4/8/8/7P/4K3 w - - 0 1").unwrap();
+        let mut tt = TranspositionTable::new(1);
+
+        // Quiet checks are only tried at the first qsearch ply
+        let mut info = SearchInfo::new(Instant::now());
+        let captures_only = pos.qsearch(-30000, 30000, 0, 1, &mut info, &mut tt);
+        let mut info = SearchInfo::new(Instant::now());
+        let with_checks = pos.qsearch(-30000, 30000, 0, 0, &mut info, &mut tt);
+
+        assert!(captures_only < -300, "{}", captures_only);
+        assert!(with_checks > 0, "{}", with_checks);
+    }
+
     #[test]
     fn test_qsearch_nodes() {
         setup();
@@ -164,7 +273,7 @@ mod tests {
         let mut info = SearchInfo::new(Instant::now());
         let mut tt = TranspositionTable::new(1);
 
-        let _ = pos.qsearch(-30000, 30000, 0, &mut info, &mut tt);
+        let _ = pos.qsearch(-30000, 30000, 0, 0, &mut info, &mut tt);
 
         // Should search some nodes
         assert!(info.nodes > 0);
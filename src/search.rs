@@ -1,9 +1,17 @@
 /// Main search implementation with alpha-beta pruning
+use crate::bitboard::between;
+use crate::cuckoo::CUCKOO;
+use crate::eval::EvalParams;
 use crate::moves::{Move, MoveList};
 use crate::ordering::{pick_move, score_moves, SearchHeuristics, MAX_PLY};
 use crate::position::Position;
-use crate::tt::{Bound, TranspositionTable, TTEntry};
+use crate::qsearch::MAX_QSEARCH_DEPTH;
+use crate::timeman::{RootStability, TimeBudget};
+use crate::tt::{Bound, TranspositionTable};
+use crate::types::PieceType;
+use std::io::{self, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 /// Score constants
@@ -11,6 +19,16 @@ pub const INFINITY: i16 = 32000;
 pub const MATE_SCORE: i16 = 30000;
 pub const MATE_BOUND: i16 = MATE_SCORE - MAX_PLY as i16;
 
+/// Queue a debug line on a `SearchInfo` if its debug output is on; the driver writes
+/// the queue to the search's writer
+macro_rules! search_debug {
+    ($info:expr, $($arg:tt)*) => {
+        if $info.debug {
+            $info.debug_log.push(format!($($arg)*));
+        }
+    };
+}
+
 /// Search result
 #[derive(Debug, Clone)]
 pub struct SearchResult {
@@ -21,31 +39,108 @@ pub struct SearchResult {
     pub pv: Vec<Move>,
 }
 
+/// Where the search stands at the root, for progress reports (XBoard "stat01")
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RootProgress {
+    /// Iteration depth being searched
+    pub depth: u8,
+    /// 1-based index of the root move being searched
+    pub move_number: usize,
+    /// Legal moves at the root
+    pub total_moves: usize,
+    /// Root move being searched
+    pub current: Move,
+}
+
+/// Snapshot of a search for progress reports from another thread (XBoard ".")
+#[derive(Debug, Clone, Copy)]
+pub struct SearchProgress {
+    pub start_time: Instant,
+    pub nodes: u64,
+    pub root: RootProgress,
+}
+
+/// Latest progress of a running search, shared with the thread that reports it
+pub type SharedProgress = Arc<Mutex<Option<SearchProgress>>>;
+
 /// Search information and control
 pub struct SearchInfo {
     pub nodes: u64,
+    /// Nodes visited in quiescence search (included in `nodes`)
+    pub qnodes: u64,
     pub start_time: Instant,
     pub time_limit: Option<Duration>,
     pub deadline: Option<Instant>,
+    /// Don't start another iteration once this much time has passed
+    pub soft_limit: Option<Duration>,
     pub depth_limit: Option<u8>,
+    /// The clock and stop flag are polled when `nodes & stop_check_mask == 0`
+    pub stop_check_mask: u64,
+    /// Captures deep a quiescence search may go before standing pat
+    pub max_qsearch_depth: i32,
+    /// Nodes at least this deep without a TT move are searched one ply shallower
+    pub iir_min_depth: i32,
     pub stopped: bool,
     pub stop_flag: Option<&'static AtomicBool>,
     pub heuristics: SearchHeuristics,
     pub sel_depth: u8,
+    /// Ignore the clock and stop flag so fixed-depth node counts are reproducible
+    pub deterministic: bool,
+    /// Score of a draw for the root side, in centipawns (positive avoids draws)
+    pub contempt: i16,
+    /// Hashes of earlier game positions followed by the current search path
+    pub history: Vec<u64>,
+    /// Static evaluation at each ply of the current path (-INFINITY when in check)
+    pub static_evals: [i16; MAX_PLY],
+    /// Evaluation weights used by the search
+    pub eval_params: EvalParams,
+    /// Null move is disabled before this ply while a verification search runs
+    pub nmp_min_ply: i32,
+    /// Print castling as king-takes-rook in the PV (UCI_Chess960)
+    pub chess960: bool,
+    /// Append win/draw/loss estimates to info lines (UCI_ShowWDL)
+    pub show_wdl: bool,
+    /// Current iteration and root move
+    pub root: RootProgress,
+    /// Print thinking as XBoard "ply score time nodes pv" lines instead of UCI info
+    pub xboard_output: bool,
+    /// Published at every clock check and root move, if set
+    pub progress: Option<SharedProgress>,
+    /// Collect debug lines ("debug on"), which the driver flushes to its writer
+    pub debug: bool,
+    pub debug_log: Vec<String>,
 }
 
 impl SearchInfo {
     pub fn new(start_time: Instant) -> Self {
         SearchInfo {
             nodes: 0,
+            qnodes: 0,
             start_time,
             time_limit: None,
             deadline: None,
+            soft_limit: None,
             depth_limit: None,
+            stop_check_mask: STOP_CHECK_MASK,
+            max_qsearch_depth: MAX_QSEARCH_DEPTH,
+            iir_min_depth: IIR_MIN_DEPTH,
             stopped: false,
             stop_flag: None,
             heuristics: SearchHeuristics::new(),
             sel_depth: 0,
+            deterministic: false,
+            contempt: 0,
+            history: Vec::new(),
+            static_evals: [-INFINITY; MAX_PLY],
+            eval_params: EvalParams::default(),
+            nmp_min_ply: 0,
+            chess960: false,
+            show_wdl: false,
+            root: RootProgress::default(),
+            xboard_output: false,
+            progress: None,
+            debug: crate::debug::is_debug(),
+            debug_log: Vec::new(),
         }
     }
 
@@ -53,6 +148,50 @@ impl SearchInfo {
     pub fn set_time_limit(&mut self, limit: Duration) {
         self.time_limit = Some(limit);
         self.deadline = Some(self.start_time + limit);
+        self.stop_check_mask = stop_check_mask(limit);
+    }
+
+    /// Set the soft limit (checked between iterations) and the hard limit (mid-search)
+    pub fn set_time_budget(&mut self, budget: TimeBudget) {
+        self.soft_limit = budget.soft;
+        self.set_time_limit(budget.hard);
+    }
+
+    /// Whether the soft limit, scaled by `percent`, has passed, so no new iteration should start
+    pub fn soft_limit_reached(&self, percent: u64) -> bool {
+        !self.deterministic
+            && self
+                .soft_limit
+                .is_some_and(|limit| self.start_time.elapsed() >= limit * percent as u32 / 100)
+    }
+
+    /// Draw score from the side to move's perspective at `ply`
+    #[inline(always)]
+    pub fn draw_score(&self, ply: i32) -> i16 {
+        // Even plies are the root side, which values a draw at -contempt
+        if ply % 2 == 0 {
+            -self.contempt
+        } else {
+            self.contempt
+        }
+    }
+
+    /// Record the static eval at `ply` and report whether it improved on two plies ago
+    #[inline(always)]
+    pub fn update_improving(&mut self, ply: usize, static_eval: i16, in_check: bool) -> bool {
+        self.static_evals[ply] = static_eval;
+        !in_check && ply >= 2 && static_eval > self.static_evals[ply - 2]
+    }
+
+    /// Share the node count and root progress with a reporting thread, if there is one
+    pub fn publish_progress(&self) {
+        if let Some(progress) = &self.progress {
+            *progress.lock().unwrap() = Some(SearchProgress {
+                start_time: self.start_time,
+                nodes: self.nodes,
+                root: self.root,
+            });
+        }
     }
 
     /// Check if search should stop
@@ -62,6 +201,12 @@ impl SearchInfo {
             return true;
         }
 
+        self.publish_progress();
+
+        if self.deterministic {
+            return false;
+        }
+
         // Check external stop flag
         if let Some(flag) = self.stop_flag {
             if flag.load(Ordering::Relaxed) {
@@ -82,6 +227,40 @@ impl SearchInfo {
     }
 }
 
+/// Nodes between clock checks (minus one) for long or untimed searches
+const STOP_CHECK_MASK: u64 = 4095;
+
+/// Poll the clock more often under short limits, which a few thousand nodes at
+/// full speed would overshoot
+fn stop_check_mask(limit: Duration) -> u64 {
+    if limit < Duration::from_millis(20) {
+        255
+    } else if limit < Duration::from_millis(200) {
+        1023
+    } else {
+        STOP_CHECK_MASK
+    }
+}
+
+/// Largest thread count the search can use; it is single-threaded until SMP lands
+pub const MAX_SEARCH_THREADS: usize = 1;
+
+/// Clamp a requested thread count (UCI "Threads", XBoard "cores") to what the search can use
+pub fn search_threads(requested: usize) -> usize {
+    requested.clamp(1, MAX_SEARCH_THREADS)
+}
+
+/// Default minimum depth for internal iterative reduction (`SearchInfo::iir_min_depth`)
+const IIR_MIN_DEPTH: i32 = 6;
+
+/// Deepest remaining depth at which reverse futility pruning applies
+const RFP_MAX_DEPTH: i32 = 8;
+
+/// Minimum depth at which a null-move cutoff in a low-material position is verified
+const NMP_VERIFY_DEPTH: i32 = 8;
+/// At most this many non-pawn pieces counts as low material (zugzwang-prone)
+const NMP_VERIFY_PIECES: u32 = 2;
+
 /// LMR reduction table
 static LMR_TABLE: [[i32; 64]; 64] = init_lmr_table();
 
@@ -102,12 +281,56 @@ const fn init_lmr_table() -> [[i32; 64]; 64] {
     table
 }
 
+/// Late move reduction for a quiet move from the LMR table, adjusted for node type and
+/// move ordering heuristics. Killers and the countermove are quiet moves that recently
+/// caused cutoffs, so they are reduced less.
+fn lmr_reduction(
+    depth: i32,
+    moves_searched: usize,
+    is_pv: bool,
+    heuristics: &SearchHeuristics,
+    mv: Move,
+    ply: usize,
+) -> i32 {
+    let mut r = LMR_TABLE[depth.min(63) as usize][moves_searched.min(63)];
+    if !is_pv {
+        r += 1;
+    }
+    if heuristics.is_killer(mv, ply).is_some() {
+        r -= 1;
+    }
+    if heuristics.is_countermove(heuristics.prev_move, mv) {
+        r -= 1;
+    }
+    r
+}
+
 /// Approximate natural log for const evaluation
 const fn ln_approx(x: f64) -> f64 {
-    // Taylor series approximation for ln(x) around x=1
+    // Range-reduce to m in [1, 2) so that x = m * 2^k, then
+    // ln(m) = 2 * atanh(y) with y = (m - 1) / (m + 1), which converges quickly
     // Only works for x > 0
-    let x = x - 1.0;
-    x - (x * x) / 2.0 + (x * x * x) / 3.0 - (x * x * x * x) / 4.0
+    let mut m = x;
+    let mut k = 0.0;
+    while m >= 2.0 {
+        m /= 2.0;
+        k += 1.0;
+    }
+    while m < 1.0 {
+        m *= 2.0;
+        k -= 1.0;
+    }
+    let y = (m - 1.0) / (m + 1.0);
+    let y2 = y * y;
+    let mut term = y;
+    let mut sum = 0.0;
+    let mut n = 1.0;
+    while n < 40.0 {
+        sum += term / n;
+        term *= y2;
+        n += 2.0;
+    }
+    k * std::f64::consts::LN_2 + 2.0 * sum
 }
 
 impl Position {
@@ -119,30 +342,68 @@ impl Position {
         depth_limit: Option<u8>,
         stop_flag: Option<&'static AtomicBool>,
     ) -> SearchResult {
-        // Log search start for debugging
-        eprintln!(
-            "SEARCH START: side={:?} fen={}",
-            self.side_to_move,
-            self.to_fen()
-        );
-
-        let start_time = Instant::now();
-        let mut info = SearchInfo::new(start_time);
+        let mut info = SearchInfo::new(Instant::now());
         if let Some(limit) = time_limit {
             info.set_time_limit(limit);
         }
         info.depth_limit = depth_limit;
         info.stop_flag = stop_flag;
 
+        self.search_with_info(tt, &mut info)
+    }
+
+    /// Fixed-depth search from a cleared TT with no time checks, for reproducible node counts
+    pub fn search_deterministic(&self, tt: &mut TranspositionTable, depth: u8) -> SearchResult {
+        self.search_deterministic_with_output(tt, depth, &mut io::stdout())
+    }
+
+    /// `search_deterministic`, writing its UCI "info" lines to `out`
+    pub fn search_deterministic_with_output(
+        &self,
+        tt: &mut TranspositionTable,
+        depth: u8,
+        out: &mut dyn Write,
+    ) -> SearchResult {
+        let mut info = SearchInfo::new(Instant::now());
+        info.depth_limit = Some(depth);
+        info.deterministic = true;
+
+        tt.clear();
+        self.search_with_output(tt, &mut info, out)
+    }
+
+    /// Iterative deepening driver using a caller-prepared `SearchInfo`
+    pub fn search_with_info(&self, tt: &mut TranspositionTable, info: &mut SearchInfo) -> SearchResult {
+        self.search_with_output(tt, info, &mut io::stdout())
+    }
+
+    /// Iterative deepening driver that writes its UCI "info" lines to `out`
+    pub fn search_with_output(
+        &self,
+        tt: &mut TranspositionTable,
+        info: &mut SearchInfo,
+        out: &mut dyn Write,
+    ) -> SearchResult {
+        // Log search start for debugging
+        search_debug!(
+            info,
+            "SEARCH START: side={:?} fen={}",
+            self.side_to_move,
+            self.to_fen()
+        );
+
         tt.new_search();
+        info.heuristics.age_history();
 
-        let max_depth = depth_limit.unwrap_or(MAX_PLY as u8);
+        let max_depth = info.depth_limit.unwrap_or(MAX_PLY as u8);
         let mut best_move = Move::NULL;
         let mut best_score = -INFINITY;
         let mut pv = Vec::new();
+        let mut stability = RootStability::new();
 
         // Iterative deepening
         for depth in 1..=max_depth {
+            info.root.depth = depth;
             let mut alpha = -INFINITY;
             let mut beta = INFINITY;
             let mut delta = 25i16;
@@ -155,6 +416,10 @@ impl Position {
 
             let mut current_pv = Vec::new();
 
+            // Always finish depth 1 so there is a move to play, even when the budget is
+            // used up (a movetime below the move overhead); the stop flag still applies
+            let deadline = if depth == 1 { info.deadline.take() } else { None };
+
             loop {
                 current_pv.clear();
                 let score = self.negamax(
@@ -162,9 +427,9 @@ impl Position {
                     0,
                     alpha,
                     beta,
-                    &mut info,
+                    info,
                     tt,
-                    &mut current_pv,
+                    Some(&mut current_pv),
                     true,
                 );
 
@@ -172,12 +437,15 @@ impl Position {
                     break;
                 }
 
-                // Research if outside aspiration window
+                // Research if outside aspiration window, reporting the failed bound so
+                // GUIs don't mistake it for an exact score
                 if score <= alpha {
+                    write_info(out, depth, score, Bound::Upper, info, &[]);
                     beta = (alpha + beta) / 2;
                     alpha = (score.saturating_sub(delta)).max(-INFINITY);
                     delta = delta.saturating_mul(2);
                 } else if score >= beta {
+                    write_info(out, depth, score, Bound::Lower, info, &current_pv);
                     beta = (score.saturating_add(delta)).min(INFINITY);
                     delta = delta.saturating_mul(2);
                 } else {
@@ -195,13 +463,14 @@ impl Position {
                             best_move = pv_move;
                             pv = current_pv;
                         } else {
-                            eprintln!(
+                            search_debug!(
+                                info,
                                 "WARN: Rejecting invalid PV at depth {}: {} (side: {:?})",
                                 depth,
                                 pv_move.to_uci(),
                                 self.side_to_move
                             );
-                            eprintln!("Position: {}", self.to_fen());
+                            search_debug!(info, "Position: {}", self.to_fen());
                             // Don't update pv, keep the previous valid one
                         }
                     }
@@ -209,6 +478,9 @@ impl Position {
                 }
             }
 
+            if depth == 1 {
+                info.deadline = deadline;
+            }
             if info.stopped {
                 break;
             }
@@ -220,7 +492,8 @@ impl Position {
                 for (i, pv_mv) in pv.iter().enumerate() {
                     if let Some(piece) = check_pos.piece_at(pv_mv.from_sq()) {
                         if piece.color() != check_pos.side_to_move {
-                            eprintln!(
+                            search_debug!(
+                                info,
                                 "BUG: PV[{}] {} wrong color at depth {}. Expected {:?}, got {:?}",
                                 i,
                                 pv_mv.to_uci(),
@@ -228,7 +501,7 @@ impl Position {
                                 check_pos.side_to_move,
                                 piece.color()
                             );
-                            eprintln!("Position: {}", check_pos.to_fen());
+                            search_debug!(info, "Position: {}", check_pos.to_fen());
                             // Truncate PV at the corrupted point
                             pv.truncate(i);
                             if i == 0 {
@@ -244,7 +517,8 @@ impl Position {
                             break;
                         }
                     } else {
-                        eprintln!(
+                        search_debug!(
+                            info,
                             "BUG: PV[{}] {} has no piece at source at depth {}",
                             i,
                             pv_mv.to_uci(),
@@ -258,31 +532,20 @@ impl Position {
             }
 
             // Print UCI info
-            let elapsed = start_time.elapsed();
-            let nps = if elapsed.as_millis() > 0 {
-                (info.nodes as u128 * 1000) / elapsed.as_millis()
-            } else {
-                0
-            };
-
-            print!(
-                "info depth {} seldepth {} score {} nodes {} nps {} time {} pv",
-                depth,
-                info.sel_depth,
-                format_score(best_score),
-                info.nodes,
-                nps,
-                elapsed.as_millis()
-            );
-            for mv in &pv {
-                print!(" {}", mv.to_uci());
-            }
-            println!();
+            flush_debug(out, info);
+            write_info(out, depth, best_score, Bound::Exact, info, &pv);
 
             // If mate found, no need to search deeper
             if best_score.abs() >= MATE_BOUND {
                 break;
             }
+
+            // Past the soft limit the next iteration would likely be cut off. The limit
+            // shrinks for a stable best move and grows when it changes or the score drops.
+            let scale = stability.update(best_move, best_score);
+            if info.soft_limit_reached(scale) {
+                break;
+            }
         }
 
         // CRITICAL: Validate that best_move and PV belong to the correct side
@@ -293,22 +556,24 @@ impl Position {
             if let Some(piece) = self.piece_at(best_move.from_sq()) {
                 if piece.color() != self.side_to_move {
                     // This should NEVER happen - if it does, we have a serious bug
-                    eprintln!(
+                    search_debug!(
+                        info,
                         "CRITICAL: best_move {} is for {:?} but position has {:?} to move!",
                         best_move.to_uci(),
                         piece.color(),
                         self.side_to_move
                     );
-                    eprintln!("Position: {}", self.to_fen());
+                    search_debug!(info, "Position: {}", self.to_fen());
                     needs_fallback = true;
                 }
             } else {
                 // No piece at source - also a bug
-                eprintln!(
+                search_debug!(
+                    info,
                     "CRITICAL: best_move {} has no piece at source square!",
                     best_move.to_uci()
                 );
-                eprintln!("Position: {}", self.to_fen());
+                search_debug!(info, "Position: {}", self.to_fen());
                 needs_fallback = true;
             }
         }
@@ -318,27 +583,29 @@ impl Position {
             let pv_move = pv[0];
             if let Some(piece) = self.piece_at(pv_move.from_sq()) {
                 if piece.color() != self.side_to_move {
-                    eprintln!(
+                    search_debug!(
+                        info,
                         "CRITICAL: PV[0] {} is for {:?} but position has {:?} to move!",
                         pv_move.to_uci(),
                         piece.color(),
                         self.side_to_move
                     );
-                    eprintln!("Position: {}", self.to_fen());
+                    search_debug!(info, "Position: {}", self.to_fen());
                     needs_fallback = true;
                 }
             } else {
-                eprintln!(
+                search_debug!(
+                    info,
                     "CRITICAL: PV[0] {} has no piece at source square!",
                     pv_move.to_uci()
                 );
-                eprintln!("Position: {}", self.to_fen());
+                search_debug!(info, "Position: {}", self.to_fen());
                 needs_fallback = true;
             }
         }
 
-        if needs_fallback {
-            // Fall back to generating a legal move
+        if needs_fallback || best_move.is_null() {
+            // Fall back to generating a legal move (also covers a stop during depth 1)
             let mut moves = crate::moves::MoveList::new();
             self.generate_legal_moves(&mut moves);
             if !moves.is_empty() {
@@ -348,6 +615,15 @@ impl Position {
             }
         }
 
+        search_debug!(
+            info,
+            "SEARCH END: nodes={} qnodes={}",
+            info.nodes,
+            info.qnodes
+        );
+        flush_debug(out, info);
+        info.publish_progress();
+
         SearchResult {
             best_move,
             score: best_score,
@@ -358,15 +634,19 @@ impl Position {
     }
 
     /// Negamax search with alpha-beta pruning
+    ///
+    /// `pv` is `None` for null-move and null-window searches, whose lines are
+    /// never reported, so those subtrees skip PV collection entirely.
+    #[allow(clippy::too_many_arguments)]
     fn negamax(
         &self,
-        depth: i32,
+        mut depth: i32,
         ply: i32,
         mut alpha: i16,
         beta: i16,
         info: &mut SearchInfo,
         tt: &mut TranspositionTable,
-        pv: &mut Vec<Move>,
+        mut pv: Option<&mut Vec<Move>>,
         is_pv: bool,
     ) -> i16 {
         // Update selective depth
@@ -374,8 +654,8 @@ impl Position {
             info.sel_depth = ply as u8;
         }
 
-        // Check for timeout (every 4096 nodes to reduce syscall overhead)
-        if info.nodes & 4095 == 0 && info.should_stop() {
+        // Check for timeout periodically (not every node, to reduce syscall overhead)
+        if info.nodes & info.stop_check_mask == 0 && info.should_stop() {
             return 0;
         }
 
@@ -383,22 +663,27 @@ impl Position {
 
         // Mate distance pruning
         let mating_score = MATE_SCORE - ply as i16;
-        if mating_score < beta {
-            let mut new_beta = beta;
-            if mating_score <= alpha {
-                return alpha;
-            }
-            new_beta = mating_score;
-            let _ = new_beta; // Use to avoid warning
+        if mating_score <= alpha {
+            return alpha;
         }
 
+        let is_root = ply == 0;
+        let in_check = self.is_in_check();
+
         // Check for draw
-        if self.halfmove_clock >= 100 {
-            return 0;
+        if !is_root && (self.is_fifty_move_draw() || self.is_repetition(&info.history, 2)) {
+            return info.draw_score(ply);
         }
 
-        let is_root = ply == 0;
-        let in_check = self.is_in_check();
+        // Upcoming repetition: one reversible move reaches an earlier position, so the
+        // side to move can always settle for the draw score
+        let draw_score = info.draw_score(ply);
+        if !is_root && alpha < draw_score && self.has_upcoming_repetition(&info.history, ply) {
+            alpha = draw_score;
+            if alpha >= beta {
+                return alpha;
+            }
+        }
 
         // Probe transposition table
         let tt_entry = tt.probe(self.hash);
@@ -434,44 +719,71 @@ impl Position {
 
         // Hard ply limit to prevent stack overflow
         if ply >= MAX_PLY as i32 {
-            return self.evaluate();
+            return self.evaluate_with(&info.eval_params);
         }
 
         // Drop into quiescence search at depth 0
         if depth <= 0 {
-            return self.qsearch(alpha, beta, 0, info, tt);
+            return self.qsearch(alpha, beta, ply, 0, info, tt);
         }
 
-        // Check extension (limited to prevent excessive depth growth)
-        let depth = if in_check && ply < (MAX_PLY as i32 / 2) {
-            depth + 1
+        // Internal iterative reduction: without a TT move the ordering is poor, so search
+        // this node one ply shallower; the next visit then finds a TT move here
+        if depth >= info.iir_min_depth && tt_move.is_null() {
+            depth -= 1;
+        }
+
+        // Static evaluation for pruning
+        let static_eval = if in_check {
+            -INFINITY
         } else {
-            depth
+            self.evaluate_with(&info.eval_params)
         };
 
-        // Static evaluation for pruning
-        let static_eval = if in_check { -INFINITY } else { self.evaluate() };
+        // Improving: our static eval is higher than on our previous move
+        let improving = info.update_improving(ply as usize, static_eval, in_check);
+
+        // A static eval above beta overstates the position while one of our pieces is
+        // about to be lost, so neither static nor real null-move pruning may trust it
+        let hanging = !is_pv
+            && !in_check
+            && static_eval >= beta
+            && self.has_hanging_piece(self.side_to_move);
 
         // Reverse futility pruning (static null move pruning)
-        if !is_pv && !in_check && depth <= 7 {
-            let margin = 80 * depth as i16;
-            if static_eval - margin >= beta {
+        // The margin shrinks when improving, so non-improving nodes are pruned less.
+        // Like null move, it is off while a null-move cutoff is being verified.
+        if !is_pv && !in_check && depth <= RFP_MAX_DEPTH && ply >= info.nmp_min_ply {
+            let margin = 80 * (depth - improving as i32) as i16;
+
+            // Trust the static eval only if the TT doesn't already bound the score below beta
+            let tt_disagrees = tt_entry
+                .is_some_and(|e| e.bound == Bound::Upper && e.adjusted_score(ply) < beta);
+            if static_eval - margin >= beta && !tt_disagrees && !hanging {
                 return static_eval - margin;
             }
         }
 
         // Null move pruning
-        if !is_pv && !in_check && depth >= 3 && static_eval >= beta {
+        if !is_pv
+            && !in_check
+            && depth >= 3
+            && static_eval >= beta
+            && !hanging
+            && ply >= info.nmp_min_ply
+        {
             // Don't do null move if we only have pawns
-            let non_pawn_material = (self.piece_bb(self.side_to_move, crate::types::PieceType::Knight)
-                | self.piece_bb(self.side_to_move, crate::types::PieceType::Bishop)
-                | self.piece_bb(self.side_to_move, crate::types::PieceType::Rook)
-                | self.piece_bb(self.side_to_move, crate::types::PieceType::Queen))
-            .is_not_empty();
-
-            if non_pawn_material {
-                let r = 3 + depth / 4;
+            let us = self.side_to_move;
+            if self.non_pawn_material(us) > 0 {
+                let non_pawn_pieces =
+                    self.pieces_of(us).pop_count() - self.piece_count(us, PieceType::Pawn) - 1;
+
+                // Reduce more the further the static eval is above beta
+                let eval_margin = ((static_eval as i32 - beta as i32) / 200).min(3);
+                let r = 3 + depth / 4 + improving as i32 + eval_margin;
                 let null_pos = self.make_null_move();
+                info.history.push(self.hash);
+                let prev_move = std::mem::replace(&mut info.heuristics.prev_move, Move::NULL);
                 let score = -null_pos.negamax(
                     depth - 1 - r,
                     ply + 1,
@@ -479,9 +791,11 @@ impl Position {
                     -beta + 1,
                     info,
                     tt,
-                    &mut Vec::new(),
+                    None,
                     false,
                 );
+                info.history.pop();
+                info.heuristics.prev_move = prev_move;
 
                 if info.stopped {
                     return 0;
@@ -489,10 +803,37 @@ impl Position {
 
                 if score >= beta {
                     // Don't return unproven mate scores
-                    if score >= MATE_BOUND {
-                        return beta;
+                    let score = if score >= MATE_BOUND { beta } else { score };
+
+                    // Zugzwang guard: with few pieces left, passing may be the only good
+                    // "move", so confirm the cutoff with a reduced search that can't pass
+                    if depth < NMP_VERIFY_DEPTH
+                        || non_pawn_pieces > NMP_VERIFY_PIECES
+                        || info.nmp_min_ply > 0
+                    {
+                        return score;
+                    }
+
+                    info.nmp_min_ply = ply + 3 * (depth - r) / 4;
+                    let verified = self.negamax(
+                        depth - 1 - r,
+                        ply,
+                        beta - 1,
+                        beta,
+                        info,
+                        tt,
+                        None,
+                        false,
+                    );
+                    info.nmp_min_ply = 0;
+
+                    if info.stopped {
+                        return 0;
+                    }
+
+                    if verified >= beta {
+                        return score;
                     }
-                    return score;
                 }
             }
         }
@@ -503,7 +844,11 @@ impl Position {
 
         // Check for checkmate or stalemate
         if moves.is_empty() {
-            return if in_check { -mating_score } else { 0 };
+            return if in_check {
+                -mating_score
+            } else {
+                info.draw_score(ply)
+            };
         }
 
         // Score moves for ordering
@@ -515,6 +860,10 @@ impl Position {
         let mut local_pv = Vec::new();
 
         let old_alpha = alpha;
+        // The move that led here, for countermove lookups; children overwrite it
+        let prev_move = info.heuristics.prev_move;
+
+        info.history.push(self.hash);
 
         for i in 0..moves.len() {
             let mv = pick_move(&mut moves, i);
@@ -523,26 +872,56 @@ impl Position {
             // This should never fire if move generation is correct, but serves as a safety net
             if let Some(piece) = self.piece_at(mv.from_sq()) {
                 if piece.color() != self.side_to_move {
-                    eprintln!(
+                    search_debug!(
+                        info,
                         "BUG: negamax move {} has wrong color! Expected {:?}, got {:?}",
                         mv.to_uci(),
                         self.side_to_move,
                         piece.color()
                     );
-                    eprintln!("Position: {}", self.to_fen());
+                    search_debug!(info, "Position: {}", self.to_fen());
                     continue; // Skip this invalid move
                 }
             } else {
-                eprintln!("BUG: negamax move {} has no piece at source!", mv.to_uci());
-                eprintln!("Position: {}", self.to_fen());
+                search_debug!(info, "BUG: negamax move {} has no piece at source!", mv.to_uci());
+                search_debug!(info, "Position: {}", self.to_fen());
                 continue; // Skip this invalid move
             }
 
+            if is_root {
+                info.root.move_number = i + 1;
+                info.root.total_moves = moves.len();
+                info.root.current = mv;
+                info.publish_progress();
+            }
+
             // SEE pruning for bad captures (skip losing captures after first few moves)
             if !is_pv && moves_searched >= 2 && mv.is_capture() && !self.see_ge(mv, 0) {
                 continue;
             }
 
+            let gives_check = self.gives_check(mv);
+
+            // Late move pruning: skip late quiet moves at shallow depth
+            // Fewer moves are tried when not improving
+            if !is_pv
+                && !in_check
+                && depth <= 3
+                && !mv.is_tactical()
+                && !gives_check
+                && best_score > -MATE_BOUND
+                && moves_searched >= ((3 + depth * depth) / (2 - improving as i32)) as usize
+            {
+                continue;
+            }
+
+            // Check extension (limited to prevent excessive depth growth)
+            let new_depth = if gives_check && ply < (MAX_PLY as i32 / 2) {
+                depth
+            } else {
+                depth - 1
+            };
+
             let new_pos = self.make_move(mv);
 
             // Prefetch TT entry for child position
@@ -550,82 +929,93 @@ impl Position {
 
             let mut score: i16;
 
-            // Late move reductions
-            let reduction = if moves_searched >= 4
-                && depth >= 3
-                && !mv.is_tactical()
-                && !in_check
-                && !new_pos.is_in_check()
+            // Late move reductions (never applied to the first move)
+            let reduction = if moves_searched >= 4 && depth >= 3 && !mv.is_tactical() && !in_check
             {
-                let mut r = LMR_TABLE[depth.min(63) as usize][moves_searched.min(63)];
-                if !is_pv {
-                    r += 1;
+                let mut r = lmr_reduction(
+                    depth,
+                    moves_searched,
+                    is_pv,
+                    &info.heuristics,
+                    mv,
+                    ply as usize,
+                );
+                // Reduce less for moves likely to matter
+                if mv == tt_move {
+                    r -= 1;
                 }
-                r.min(depth - 1)
+                if gives_check {
+                    r -= 1;
+                }
+                r.clamp(0, new_depth - 1)
             } else {
                 0
             };
 
+            info.heuristics.prev_move = mv;
+
             // Principal Variation Search
             if moves_searched == 0 {
-                // Full window search for first move
+                // Full window search for first move, never reduced
                 local_pv.clear();
                 score = -new_pos.negamax(
-                    depth - 1,
+                    new_depth,
                     ply + 1,
                     -beta,
                     -alpha,
                     info,
                     tt,
-                    &mut local_pv,
+                    pv.is_some().then_some(&mut local_pv),
                     is_pv,
                 );
             } else {
                 // Null window search with LMR
                 score = -new_pos.negamax(
-                    depth - 1 - reduction,
+                    new_depth - reduction,
                     ply + 1,
                     -alpha - 1,
                     -alpha,
                     info,
                     tt,
-                    &mut Vec::new(),
+                    None,
                     false,
                 );
 
-                // Re-search without reduction if LMR failed high
+                // Re-search at the un-reduced depth if LMR failed high
                 if score > alpha && reduction > 0 {
                     score = -new_pos.negamax(
-                        depth - 1,
+                        new_depth,
                         ply + 1,
                         -alpha - 1,
                         -alpha,
                         info,
                         tt,
-                        &mut Vec::new(),
+                        None,
                         false,
                     );
                 }
 
-                // Full re-search if null window search failed high
-                if score > alpha && score < beta {
+                // Full window re-search at PV nodes if null window search failed high
+                if is_pv && score > alpha && score < beta {
                     local_pv.clear();
                     score = -new_pos.negamax(
-                        depth - 1,
+                        new_depth,
                         ply + 1,
                         -beta,
                         -alpha,
                         info,
                         tt,
-                        &mut local_pv,
+                        pv.is_some().then_some(&mut local_pv),
                         true,
                     );
                 }
             }
 
+            info.heuristics.prev_move = prev_move;
             moves_searched += 1;
 
             if info.stopped {
+                info.history.pop();
                 return 0;
             }
 
@@ -639,20 +1029,22 @@ impl Position {
                     // CRITICAL: Validate move color before adding to PV
                     let mv_valid = if let Some(piece) = self.piece_at(mv.from_sq()) {
                         if piece.color() != self.side_to_move {
-                            eprintln!(
+                            search_debug!(
+                                info,
                                 "BUG: negamax ply {} trying to add move {} for {:?} but side is {:?}",
                                 ply,
                                 mv.to_uci(),
                                 piece.color(),
                                 self.side_to_move
                             );
-                            eprintln!("Position: {}", self.to_fen());
+                            search_debug!(info, "Position: {}", self.to_fen());
                             false
                         } else {
                             true
                         }
                     } else {
-                        eprintln!(
+                        search_debug!(
+                            info,
                             "BUG: negamax ply {} move {} has no piece at source",
                             ply,
                             mv.to_uci()
@@ -661,9 +1053,11 @@ impl Position {
                     };
 
                     if mv_valid {
-                        pv.clear();
-                        pv.push(mv);
-                        pv.extend_from_slice(&local_pv);
+                        if let Some(pv) = pv.as_deref_mut() {
+                            pv.clear();
+                            pv.push(mv);
+                            pv.extend_from_slice(&local_pv);
+                        }
                     } else {
                         // Don't update PV with invalid move - this should never happen
                         // but if it does, leave PV empty rather than corrupt it
@@ -676,7 +1070,7 @@ impl Position {
                             info.heuristics
                                 .update_history(self.side_to_move, mv, depth, true);
                             info.heuristics
-                                .update_countermove(info.heuristics.prev_move, mv);
+                                .update_countermove(prev_move, mv);
                         }
 
                         // Update history for quiet moves that didn't cause cutoff
@@ -698,6 +1092,8 @@ impl Position {
             }
         }
 
+        info.history.pop();
+
         // Store in TT
         let bound = if best_score >= beta {
             Bound::Lower
@@ -713,20 +1109,192 @@ impl Position {
     }
 }
 
+impl Position {
+    /// Number of earlier positions that can repeat: none before the last irreversible
+    /// move or null move
+    fn repetition_window(&self) -> usize {
+        self.halfmove_clock.min(self.plies_from_null) as usize
+    }
+
+    /// Check if this position has now occurred `count` times, counting itself, with the same
+    /// side to move. `history` holds the hashes of the earlier positions, oldest first; only
+    /// the last `repetition_window` of them can repeat.
+    pub fn is_repetition(&self, history: &[u64], count: usize) -> bool {
+        let earlier = history
+            .iter()
+            .rev()
+            .take(self.repetition_window())
+            .skip(1)
+            .step_by(2)
+            .filter(|&&hash| hash == self.hash)
+            .count();
+        earlier + 1 >= count
+    }
+
+    /// Check if the side to move has a reversible move back to an earlier position within
+    /// the repetition window, found by looking up hash differences in the cuckoo table.
+    /// Before the root the moving piece must be ours, so the move is not the opponent's.
+    fn has_upcoming_repetition(&self, history: &[u64], ply: i32) -> bool {
+        let end = self.repetition_window().min(history.len());
+
+        for back in (3..=end).step_by(2) {
+            let earlier = history[history.len() - back];
+            let Some((s1, s2)) = CUCKOO.lookup(self.hash ^ earlier) else {
+                continue;
+            };
+            if (between(s1, s2) & self.all_occupied).is_not_empty() {
+                continue;
+            }
+            if ply as usize > back {
+                return true;
+            }
+            let from = if self.piece_at(s1).is_some() { s1 } else { s2 };
+            if self.piece_at(from).is_some_and(|p| p.color() == self.side_to_move) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Check if the game is drawn by rule: 50 moves, threefold repetition or
+    /// insufficient material
+    pub fn is_draw(&self, history: &[u64]) -> bool {
+        self.is_fifty_move_draw() || self.is_repetition(history, 3) || self.is_insufficient_material()
+    }
+
+    /// Check if the 50-move rule applies; checkmate takes precedence over the draw
+    pub fn is_fifty_move_draw(&self) -> bool {
+        if self.halfmove_clock < 100 {
+            return false;
+        }
+        !self.is_checkmate()
+    }
+}
+
+/// Write a UCI "info" line for a finished (or failed) iteration
+fn write_info(
+    out: &mut dyn Write,
+    depth: u8,
+    score: i16,
+    bound: Bound,
+    info: &SearchInfo,
+    pv: &[Move],
+) {
+    let elapsed = info.start_time.elapsed();
+
+    // XBoard thinking output has no bounds, so only completed iterations are shown
+    if info.xboard_output {
+        if bound == Bound::Exact {
+            let score = xboard_score(score);
+            write!(out, "{} {} {} {}", depth, score, elapsed.as_millis() / 10, info.nodes).unwrap();
+            for mv in pv {
+                write!(out, " {}", mv.to_uci()).unwrap();
+            }
+            writeln!(out).unwrap();
+            out.flush().unwrap();
+        }
+        return;
+    }
+
+    let nps = if elapsed.as_millis() > 0 {
+        (info.nodes as u128 * 1000) / elapsed.as_millis()
+    } else {
+        0
+    };
+    let bound = match bound {
+        Bound::Lower => " lowerbound",
+        Bound::Upper => " upperbound",
+        Bound::Exact | Bound::None => "",
+    };
+
+    write!(
+        out,
+        "info depth {} seldepth {} score {}{} nodes {} nps {} time {}",
+        depth,
+        info.sel_depth,
+        format_score(score),
+        bound,
+        info.nodes,
+        nps,
+        elapsed.as_millis()
+    )
+    .unwrap();
+    if info.show_wdl {
+        let (win, draw, loss) = wdl(score);
+        write!(out, " wdl {} {} {}", win, draw, loss).unwrap();
+    }
+    if !pv.is_empty() {
+        write!(out, " pv").unwrap();
+    }
+    for mv in pv {
+        write!(out, " {}", mv.to_uci_with(info.chess960)).unwrap();
+    }
+    writeln!(out).unwrap();
+    out.flush().unwrap();
+}
+
+/// Centipawns the score must clear before a win (or loss) outweighs the draw
+const WDL_DRAW_MARGIN: f64 = 200.0;
+
+/// Win, draw and loss chances in per mille for the side to move
+///
+/// Each decisive result follows the Elo curve 1 / (1 + 10^(-x / 400)) with the
+/// score shifted by `WDL_DRAW_MARGIN`; whatever is left over is the draw chance.
+pub fn wdl(score: i16) -> (u16, u16, u16) {
+    if score.abs() >= MATE_BOUND {
+        return if score > 0 { (1000, 0, 0) } else { (0, 0, 1000) };
+    }
+
+    let chance = |cp: f64| 1000.0 / (1.0 + 10f64.powf(-(cp - WDL_DRAW_MARGIN) / 400.0));
+    let win = chance(score as f64).round() as u16;
+    let loss = chance(-(score as f64)).round() as u16;
+    (win, 1000u16.saturating_sub(win + loss), loss)
+}
+
+/// Moves to mate for a mate score, negative when the side to move gets mated
+fn mate_moves(score: i16) -> i16 {
+    if score > 0 {
+        (MATE_SCORE - score + 1) / 2
+    } else {
+        -(MATE_SCORE + score + 1) / 2
+    }
+}
+
+/// Write queued debug lines as UCI "info string" lines, or XBoard "#" comments
+fn flush_debug(out: &mut dyn Write, info: &mut SearchInfo) {
+    for line in info.debug_log.drain(..) {
+        if info.xboard_output {
+            writeln!(out, "# {}", line).unwrap();
+        } else {
+            crate::debug::write_line(out, format_args!("{}", line));
+        }
+    }
+}
+
 /// Format score for UCI output
 fn format_score(score: i16) -> String {
     if score.abs() >= MATE_BOUND {
-        let moves_to_mate = if score > 0 {
-            (MATE_SCORE - score + 1) / 2
-        } else {
-            -(MATE_SCORE + score + 1) / 2
-        };
-        format!("mate {}", moves_to_mate)
+        format!("mate {}", mate_moves(score))
     } else {
         format!("cp {}", score)
     }
 }
 
+/// Score for XBoard thinking output: centipawns, or 100000 + N for mate in N moves
+/// and -100000 - N for getting mated in N
+pub fn xboard_score(score: i16) -> i32 {
+    if score.abs() >= MATE_BOUND {
+        let moves = mate_moves(score) as i32;
+        if moves > 0 {
+            100_000 + moves
+        } else {
+            -100_000 + moves
+        }
+    } else {
+        score as i32
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -739,6 +1307,27 @@ mod tests {
         });
     }
 
+    /// Node rate at a fixed depth from the start position, for comparing builds:
+    /// `cargo test --release bench_startpos -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_startpos_fixed_depth() {
+        setup();
+        let pos = Position::new();
+        let mut tt = TranspositionTable::new(16);
+
+        let start = Instant::now();
+        let result = pos.search(&mut tt, None, Some(18), None);
+        let elapsed = start.elapsed();
+
+        println!(
+            "nodes {} nps {} bestmove {}",
+            result.nodes,
+            (result.nodes as f64 / elapsed.as_secs_f64()) as u64,
+            result.best_move.to_uci()
+        );
+    }
+
     #[test]
     fn test_search_startpos() {
         setup();
@@ -754,8 +1343,8 @@ mod tests {
     #[test]
     fn test_search_finds_mate_in_1() {
         setup();
-        // White to move, Qh7#
-        let pos = Position::from_fen("k7/8/1K6/8/8/8/8/7Q w - - 0 1").unwrap();
+        // White to move, Qg8#
+        let pos = Position::from_fen("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1").unwrap();
         let mut tt = TranspositionTable::new(16);
 
         let result = pos.search(&mut tt, None, Some(3), None);
@@ -788,6 +1377,128 @@ mod tests {
         assert!(!result.best_move.is_null());
     }
 
+    #[test]
+    fn test_stop_check_mask_follows_limit() {
+        assert_eq!(stop_check_mask(Duration::from_millis(5)), 255);
+        assert_eq!(stop_check_mask(Duration::from_millis(100)), 1023);
+        assert_eq!(stop_check_mask(Duration::from_secs(1)), STOP_CHECK_MASK);
+
+        let mut info = SearchInfo::new(Instant::now());
+        assert_eq!(info.stop_check_mask, STOP_CHECK_MASK);
+        info.set_time_limit(Duration::from_millis(5));
+        assert_eq!(info.stop_check_mask, 255);
+    }
+
+    #[test]
+    fn test_spent_budget_completes_depth_one() {
+        setup();
+        let pos = Position::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let mut tt = TranspositionTable::new(16);
+
+        // A budget already used up still completes depth 1 rather than returning no move,
+        // then stops at the first clock check of depth 2
+        let mut info = SearchInfo::new(Instant::now());
+        info.set_time_limit(Duration::ZERO);
+        let mut out = Vec::new();
+        let result = pos.search_with_output(&mut tt, &mut info, &mut out);
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.starts_with("info depth 1 "), "{}", out);
+        assert!(!out.contains("info depth 2 "), "{}", out);
+        assert!(!result.best_move.is_null());
+        assert_eq!(out.lines().last().unwrap().split(" pv ").nth(1).unwrap(), result.best_move.to_uci());
+    }
+
+    /// Wall-clock bound, so too noisy for a loaded machine:
+    /// `cargo test --release short_movetime -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_short_movetime_stops_promptly() {
+        setup();
+        let pos = Position::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let mut tt = TranspositionTable::new(16);
+
+        let limit = Duration::from_millis(5);
+        let start = Instant::now();
+        let result = pos.search(&mut tt, Some(limit), None, None);
+        let elapsed = start.elapsed();
+
+        assert!(!result.best_move.is_null());
+        assert!(elapsed < limit * 10, "ran for {:?}", elapsed);
+    }
+
+    #[test]
+    fn test_soft_and_hard_limits() {
+        // A search that started a second ago, with limits well on either side of that
+        let mut info = SearchInfo::new(Instant::now() - Duration::from_secs(1));
+        info.set_time_budget(TimeBudget {
+            soft: Some(Duration::from_millis(500)),
+            hard: Duration::from_secs(60),
+        });
+
+        // The soft limit is scaled by root stability: 60% and 100% have passed, 1000% has not
+        assert!(info.soft_limit_reached(60));
+        assert!(info.soft_limit_reached(100));
+        assert!(!info.soft_limit_reached(1000));
+        assert!(!info.should_stop());
+
+        // The hard limit stops the search mid-iteration
+        info.set_time_limit(Duration::from_millis(300));
+        assert!(info.should_stop());
+        assert!(info.stopped);
+    }
+
+    /// Wall-clock bounds, so too noisy for a loaded machine:
+    /// `cargo test --release soft_and_hard_limits -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_search_respects_soft_and_hard_limits() {
+        setup();
+        let pos = Position::new();
+        let mut tt = TranspositionTable::new(16);
+
+        let budget = TimeBudget {
+            soft: Some(Duration::from_millis(50)),
+            hard: Duration::from_millis(300),
+        };
+        let mut info = SearchInfo::new(Instant::now());
+        info.set_time_budget(budget);
+        let result = pos.search_with_info(&mut tt, &mut info);
+        let elapsed = info.start_time.elapsed();
+
+        assert!(!result.best_move.is_null());
+        // Stops after the first iteration that ends past the soft limit (which shrinks to
+        // 60% for an easy move)...
+        assert!(elapsed >= budget.soft.unwrap() * 3 / 5, "stopped after {:?}", elapsed);
+        // ...and never runs far past the hard limit
+        assert!(elapsed < budget.hard * 2, "ran for {:?}", elapsed);
+    }
+
+    #[test]
+    fn test_fixed_movetime_is_used_in_full() {
+        setup();
+        let pos = Position::new();
+        let mut tt = TranspositionTable::new(16);
+
+        // The best move settles quickly from the start position, which would cut a clock
+        // budget short. A fixed move time ignores stability and runs to the hard limit.
+        let budget = TimeBudget::fixed(200, 0);
+        let mut info = SearchInfo::new(Instant::now());
+        info.set_time_budget(budget);
+        assert!(!info.soft_limit_reached(0));
+        let result = pos.search_with_info(&mut tt, &mut info);
+        let elapsed = info.start_time.elapsed();
+
+        assert!(!result.best_move.is_null());
+        assert!(elapsed >= budget.hard, "stopped after {:?}", elapsed);
+    }
+
     #[test]
     fn test_search_returns_correct_color_move() {
         use crate::types::Color;
@@ -849,4 +1560,496 @@ mod tests {
             current_pos = current_pos.make_move(*mv);
         }
     }
+
+    #[test]
+    fn test_pv_is_legal_line_at_fixed_depth() {
+        setup();
+        let pos = Position::new();
+        let mut tt = TranspositionTable::new(16);
+
+        let result = pos.search(&mut tt, None, Some(6), None);
+
+        // PV collection only runs along PV nodes, but must still yield a full line
+        assert!(result.pv.len() >= 2);
+        assert_eq!(result.pv[0], result.best_move);
+
+        let mut current_pos = pos.clone();
+        for mv in &result.pv {
+            let mut legal = MoveList::new();
+            current_pos.generate_legal_moves(&mut legal);
+            assert!(
+                (0..legal.len()).any(|i| legal.get(i) == *mv),
+                "PV move {} is not legal",
+                mv.to_uci()
+            );
+            current_pos = current_pos.make_move(*mv);
+        }
+    }
+
+    #[test]
+    fn test_deterministic_search_is_repeatable() {
+        setup();
+        let pos = Position::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let mut tt = TranspositionTable::new(16);
+
+        let first = pos.search_deterministic(&mut tt, 6);
+        let second = pos.search_deterministic(&mut tt, 6);
+
+        assert_eq!(first.nodes, second.nodes);
+        assert_eq!(first.best_move, second.best_move);
+        assert_eq!(first.score, second.score);
+    }
+
+    #[test]
+    fn test_contempt_avoids_repetition() {
+        setup();
+        // Kings shuffle back, so Kh1 now repeats the position after the first move
+        let mut pos = Position::from_fen("6k1/5pp1/7p/8/8/6PP/1r3P2/R5K1 w - - 0 1").unwrap();
+        let mut history = Vec::new();
+        for uci in ["g1h1", "g8h7", "h1g1", "h7g8"] {
+            history.push(pos.hash);
+            pos = pos.make_uci_move(uci).unwrap();
+        }
+
+        let search = |contempt: i16| {
+            let mut tt = TranspositionTable::new(16);
+            let mut info = SearchInfo::new(Instant::now());
+            info.depth_limit = Some(6);
+            info.deterministic = true;
+            info.contempt = contempt;
+            info.history = history.clone();
+            pos.search_with_info(&mut tt, &mut info)
+        };
+
+        // White is slightly worse, so without contempt it takes a draw: Kh1 repeats at once,
+        // and Ra8+ Kh7 Ra1 repeats too
+        let neutral = search(0);
+        assert!(["g1h1", "a1a8"].contains(&neutral.best_move.to_uci().as_str()));
+        assert_eq!(neutral.score, 0);
+
+        // With contempt the draw scores below the position, so it plays on
+        let avoiding = search(100);
+        assert_ne!(avoiding.best_move.to_uci(), "g1h1");
+        assert!(avoiding.score > -100);
+    }
+
+    #[test]
+    fn test_improving_flag() {
+        let mut info = SearchInfo::new(Instant::now());
+
+        // No history at the first two plies
+        assert!(!info.update_improving(0, 50, false));
+        assert!(!info.update_improving(1, -40, false));
+
+        // Compared against two plies ago (same side to move)
+        assert!(info.update_improving(2, 80, false));
+        assert!(!info.update_improving(3, -60, false));
+        assert!(!info.update_improving(4, 80, false));
+
+        // Never improving while in check
+        assert!(!info.update_improving(5, -INFINITY, true));
+    }
+
+    #[test]
+    fn test_improving_pruning_keeps_tactics() {
+        setup();
+        // Knight fork winning the queen: Nc7+ followed by Nxa8
+        let pos = Position::from_fen("q3k3/8/8/1N6/8/8/6P1/6K1 w - - 0 1").unwrap();
+        let mut tt = TranspositionTable::new(16);
+
+        let result = pos.search_deterministic(&mut tt, 6);
+
+        assert_eq!(result.best_move.to_uci(), "b5c7");
+        assert!(result.score > 200);
+    }
+
+    #[test]
+    fn test_lmr_finds_quiet_winning_move() {
+        setup();
+        // WAC.003: the quiet move Rg3 wins; late quiet moves must not be reduced out of sight
+        let pos =
+            Position::from_fen("5rk1/1ppb3p/p1pb4/6q1/3P1p1r/2P1R2P/PP1BQ1P1/5RKN w - - 0 1")
+                .unwrap();
+        let mut tt = TranspositionTable::new(16);
+
+        let result = pos.search_deterministic(&mut tt, 6);
+
+        assert_eq!(result.best_move.to_uci(), "e3g3");
+    }
+
+    #[test]
+    fn test_internal_iterative_reduction() {
+        setup();
+        // Nxh4 wins the queen at any depth, so reduced and full searches agree on the move
+        let pos =
+            Position::from_fen("rnb1kbnr/pppp1ppp/8/4p3/4P2q/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+                .unwrap();
+        let depth = IIR_MIN_DEPTH + 2;
+        let search = |tt: &mut TranspositionTable, iir_min_depth: i32| {
+            let mut info = SearchInfo::new(Instant::now());
+            info.deterministic = true;
+            info.iir_min_depth = iir_min_depth;
+            let mut pv = Vec::new();
+            pos.negamax(depth, 0, -INFINITY, INFINITY, &mut info, tt, Some(&mut pv), true);
+            (pv[0], tt.probe(pos.hash).unwrap().depth as i32, info.nodes)
+        };
+
+        // Root TT miss: searched one ply shallower, with fewer nodes than without IIR
+        let mut tt = TranspositionTable::new(16);
+        let (reduced_move, stored_depth, reduced_nodes) = search(&mut tt, IIR_MIN_DEPTH);
+        assert_eq!(stored_depth, depth - 1);
+
+        let mut tt = TranspositionTable::new(16);
+        let (_, stored_depth, full_nodes) = search(&mut tt, i32::MAX);
+        assert_eq!(stored_depth, depth);
+        assert!(reduced_nodes < full_nodes, "{} vs {}", reduced_nodes, full_nodes);
+
+        // With a TT move at the root, the full depth is searched
+        let mut tt = TranspositionTable::new(16);
+        tt.store(pos.hash, 1, 0, Bound::Lower, reduced_move, 0);
+        let (full_move, stored_depth, _) = search(&mut tt, IIR_MIN_DEPTH);
+        assert_eq!(stored_depth, depth);
+        assert_eq!(reduced_move, full_move);
+    }
+
+    #[test]
+    fn test_reverse_futility_pruning() {
+        setup();
+        let nodes_searched = |pos: &Position, tt: &mut TranspositionTable| {
+            let mut info = SearchInfo::new(Instant::now());
+            info.deterministic = true;
+            pos.negamax(4, 1, -1, 0, &mut info, tt, None, false);
+            info.nodes
+        };
+
+        // A queen up in a quiet position: cut off at once from the static eval
+        let quiet = Position::from_fen("6k1/5ppp/8/8/8/8/5PPP/3Q2K1 w - - 0 1").unwrap();
+        let mut tt = TranspositionTable::new(16);
+        assert_eq!(nodes_searched(&quiet, &mut tt), 1);
+
+        // A shallower search already proved the score is below beta
+        let mut tt = TranspositionTable::new(16);
+        tt.store(quiet.hash, 2, -50, Bound::Upper, Move::NULL, 1);
+        assert!(nodes_searched(&quiet, &mut tt) > 1);
+
+        // Same material, but the queen is attacked by a pawn: search it
+        let hanging = Position::from_fen("6k1/5ppp/8/8/6p1/5Q2/5P1P/6K1 w - - 0 1").unwrap();
+        let mut tt = TranspositionTable::new(16);
+        assert!(nodes_searched(&hanging, &mut tt) > 1);
+    }
+
+    #[test]
+    fn test_low_eval_keeps_winning_capture() {
+        setup();
+        // Down a queen on the board, but Rxd5 wins it back: shallow nodes must not give
+        // up on the static eval while a winning capture is available
+        let pos = Position::from_fen("r3k3/8/8/3q4/8/6P1/5P1P/3R2K1 w - - 0 1").unwrap();
+        let static_eval = pos.evaluate();
+        let alpha = static_eval + 500;
+
+        for depth in 1..=3 {
+            let mut info = SearchInfo::new(Instant::now());
+            info.deterministic = true;
+            let mut tt = TranspositionTable::new(16);
+            let score = pos.negamax(depth, 1, alpha, alpha + 1, &mut info, &mut tt, None, false);
+            assert!(score > alpha, "depth {}: {} <= {}", depth, score, alpha);
+        }
+    }
+
+    #[test]
+    fn test_null_move_verification_zugzwang() {
+        setup();
+        // Zugzwang test position (bm Nxd5). Unverified null-move cutoffs hide the
+        // winning line, and the search used to settle on Rd8 with a losing score.
+        let pos =
+            Position::from_fen("8/8/1p1r1k2/p1pPN1p1/P3KnP1/1P6/8/3R4 b - - 0 1").unwrap();
+        let mut tt = TranspositionTable::new(16);
+
+        let result = pos.search_deterministic(&mut tt, 14);
+
+        assert_eq!(result.best_move.to_uci(), "f4d5");
+        assert!(result.score > 0);
+    }
+
+    #[test]
+    fn test_null_move_verification_queen_zugzwang() {
+        setup();
+        // Zugzwang test position (bm Kh6): black must then give up material. Unverified
+        // null-move cutoffs hide this, and the search used to settle on Kg4 at about +0.2.
+        let pos = Position::from_fen("1q1k4/2Rr4/8/2Q3K1/8/8/8/8 w - - 0 1").unwrap();
+        let mut tt = TranspositionTable::new(16);
+
+        let result = pos.search_deterministic(&mut tt, 12);
+
+        assert_eq!(result.best_move.to_uci(), "g5h6");
+        assert!(result.score > 300);
+    }
+
+    #[test]
+    fn test_debug_lines_go_to_the_search_writer() {
+        setup();
+        let pos = Position::new();
+        let mut tt = TranspositionTable::new(16);
+        let search = |tt: &mut TranspositionTable, debug: bool| {
+            let mut info = SearchInfo::new(Instant::now());
+            info.depth_limit = Some(2);
+            info.debug = debug;
+            let mut out = Vec::new();
+            pos.search_with_output(tt, &mut info, &mut out);
+            String::from_utf8(out).unwrap()
+        };
+
+        let output = search(&mut tt, true);
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("info string SEARCH START: side=White"), "{}", output);
+        assert!(lines[1].starts_with("info depth 1 "), "{}", output);
+        assert!(lines.last().unwrap().starts_with("info string SEARCH END: nodes="), "{}", output);
+
+        assert!(!search(&mut tt, false).contains("info string"));
+    }
+
+    #[test]
+    fn test_info_output_is_captured() {
+        setup();
+        let pos = Position::new();
+        let mut tt = TranspositionTable::new(16);
+        let mut info = SearchInfo::new(Instant::now());
+        info.depth_limit = Some(1);
+
+        let mut out = Vec::new();
+        let result = pos.search_with_output(&mut tt, &mut info, &mut out);
+        let output = String::from_utf8(out).unwrap();
+        let tokens: Vec<&str> = output.lines().next().unwrap().split_whitespace().collect();
+
+        assert_eq!(&tokens[..3], ["info", "depth", "1"]);
+        let field = |name: &str| tokens[tokens.iter().position(|&t| t == name).unwrap() + 1];
+        assert!(field("seldepth").parse::<u8>().is_ok());
+        assert_eq!(field("score"), "cp");
+        assert_eq!(field("cp").parse::<i16>().unwrap(), result.score);
+        assert_eq!(field("nodes").parse::<u64>().unwrap(), result.nodes);
+        assert!(field("time").parse::<u64>().is_ok());
+        assert_eq!(field("pv"), result.best_move.to_uci());
+    }
+
+    #[test]
+    fn test_aspiration_failures_report_bounds() {
+        setup();
+        // WAC.003 again: the score climbs as Rg3 is found, failing the aspiration window
+        let pos =
+            Position::from_fen("5rk1/1ppb3p/p1pb4/6q1/3P1p1r/2P1R2P/PP1BQ1P1/5RKN w - - 0 1")
+                .unwrap();
+        let mut tt = TranspositionTable::new(16);
+        let mut info = SearchInfo::new(Instant::now());
+        info.depth_limit = Some(8);
+        info.deterministic = true;
+
+        let mut out = Vec::new();
+        pos.search_with_output(&mut tt, &mut info, &mut out);
+        let output = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert!(lines.iter().any(|l| l.contains("lowerbound")));
+        assert!(lines.iter().any(|l| l.contains("upperbound")));
+
+        // Each depth still ends with an exact score
+        for depth in 1..=8 {
+            let prefix = format!("info depth {} ", depth);
+            let last = lines.iter().rev().find(|l| l.starts_with(&prefix)).unwrap();
+            assert!(!last.contains("bound"), "{}", last);
+        }
+    }
+
+    #[test]
+    fn test_lmr_table_is_sane() {
+        assert!((ln_approx(10.0) - 10f64.ln()).abs() < 1e-9);
+        assert!((ln_approx(0.5) - 0.5f64.ln()).abs() < 1e-9);
+
+        // Reductions stay small and grow monotonically with depth and move count
+        for depth in 1..63 {
+            for moves in 1..63 {
+                let r = LMR_TABLE[depth][moves];
+                assert!((0..=depth as i32).contains(&r));
+                assert!(LMR_TABLE[depth + 1][moves] >= r);
+                assert!(LMR_TABLE[depth][moves + 1] >= r);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lmr_reduces_killers_and_countermoves_less() {
+        setup();
+        let mut pos = Position::new();
+        let e4 = pos.parse_uci_move("e2e4").unwrap();
+        pos = pos.make_move(e4);
+        let quiet = pos.parse_uci_move("a7a6").unwrap();
+        let killer = pos.parse_uci_move("g8f6").unwrap();
+        let counter = pos.parse_uci_move("e7e5").unwrap();
+
+        let mut heuristics = SearchHeuristics::new();
+        heuristics.update_killer(killer, 1);
+        heuristics.update_countermove(e4, counter);
+        heuristics.prev_move = e4;
+
+        let reduction = |h: &SearchHeuristics, mv| lmr_reduction(12, 20, false, h, mv, 1);
+        assert_eq!(reduction(&heuristics, killer), reduction(&heuristics, quiet) - 1);
+        assert_eq!(reduction(&heuristics, counter), reduction(&heuristics, quiet) - 1);
+
+        // The countermove only applies after the move it answers
+        heuristics.prev_move = Move::NULL;
+        assert_eq!(reduction(&heuristics, counter), reduction(&heuristics, quiet));
+
+        // Searching records countermoves against the move actually played before them
+        let mut tt = TranspositionTable::new(16);
+        let mut info = SearchInfo::new(Instant::now());
+        info.deterministic = true;
+        info.depth_limit = Some(6);
+        Position::new().search_with_output(&mut tt, &mut info, &mut io::sink());
+        assert!(info.heuristics.prev_move.is_null());
+        assert!(info.heuristics.countermoves.iter().flatten().any(|mv| !mv.is_null()));
+    }
+
+    #[test]
+    fn test_fifty_move_draw_yields_to_checkmate() {
+        setup();
+        // Checkmated with the halfmove clock at 100: not a draw
+        let mated = Position::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 100 80").unwrap();
+        assert!(!mated.is_fifty_move_draw());
+
+        let drawn = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 b - - 100 80").unwrap();
+        assert!(drawn.is_fifty_move_draw());
+
+        // Ra8# is the 100th halfmove: it must score as mate, not as a draw
+        let pos = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80").unwrap();
+        let mut tt = TranspositionTable::new(16);
+        let result = pos.search_deterministic(&mut tt, 3);
+
+        assert_eq!(result.best_move.to_uci(), "a1a8");
+        assert!(result.score > MATE_BOUND);
+    }
+
+    #[test]
+    fn test_avoids_stalemate_in_won_ending() {
+        setup();
+        // Qb6 takes every square from the cornered king without giving check
+        let pos = Position::from_fen("k7/8/8/8/8/8/8/1Q2K3 w - - 0 1").unwrap();
+        let mut tt = TranspositionTable::new(16);
+        let result = pos.search_deterministic(&mut tt, 6);
+
+        assert_ne!(result.best_move.to_uci(), "b1b6");
+        let after = pos.make_move(result.best_move);
+        let mut replies = MoveList::new();
+        after.generate_legal_moves(&mut replies);
+        assert!(!replies.is_empty());
+        assert!(result.score > 500);
+    }
+
+    /// Play `moves` from `pos`, returning the final position and the hashes before each move
+    fn play(pos: &Position, moves: &[&str]) -> (Position, Vec<u64>) {
+        let mut pos = pos.clone();
+        let mut history = Vec::new();
+        for uci in moves {
+            let mv = pos.parse_uci_move(uci).unwrap();
+            history.push(pos.hash);
+            pos = pos.make_move(mv);
+        }
+        (pos, history)
+    }
+
+    #[test]
+    fn test_repetition_counts() {
+        setup();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let start = Position::new();
+
+        let (once, history) = play(&start, &shuffle);
+        assert_eq!(once.hash, start.hash);
+        assert!(once.is_repetition(&history, 2));
+        assert!(!once.is_repetition(&history, 3));
+        assert!(!once.is_draw(&history));
+
+        let (twice, history) = play(&start, &[shuffle, shuffle].concat());
+        assert!(twice.is_repetition(&history, 3));
+        assert!(twice.is_draw(&history));
+
+        // Null moves cut the scan, not the 50-move count: passing twice around Nf3 and
+        // Ng1 gets back to the start position, which must not count as a repetition
+        let mut pos = start.clone();
+        let mut history = Vec::new();
+        for uci in ["g1f3", "null", "f3g1", "null"] {
+            history.push(pos.hash);
+            pos = match pos.parse_uci_move(uci) {
+                Some(mv) => pos.make_move(mv),
+                None => pos.make_null_move(),
+            };
+        }
+        assert_eq!(pos.hash, start.hash);
+        assert_eq!(pos.halfmove_clock, 2);
+        assert!(!pos.is_repetition(&history, 2));
+        assert!(!pos.has_upcoming_repetition(&history, 0));
+
+    }
+
+    #[test]
+    fn test_draw_rules() {
+        setup();
+        let draw = |fen: &str| Position::from_fen(fen).unwrap().is_draw(&[]);
+
+        // 50-move boundary
+        assert!(!draw("6k1/5ppp/8/8/8/8/8/R5K1 b - - 99 80"));
+        assert!(draw("6k1/5ppp/8/8/8/8/8/R5K1 b - - 100 80"));
+        assert!(!draw("R5k1/5ppp/8/8/8/8/8/6K1 b - - 100 80"));
+
+        // Insufficient material
+        assert!(draw("8/8/4k3/8/8/3K4/8/5B2 w - - 0 1"));
+    }
+
+    #[test]
+    fn test_upcoming_repetition() {
+        setup();
+        let start = Position::new();
+
+        // Black to move can play Nf6-g8 back to the start position
+        let (pos, history) = play(&start, &["g1f3", "g8f6", "f3g1"]);
+        assert!(pos.has_upcoming_repetition(&history, 0));
+
+        // A pawn move in between makes the start position unreachable
+        let (pawn_moved, history) = play(&start, &["g1f3", "g8f6", "e2e4"]);
+        assert!(!pawn_moved.has_upcoming_repetition(&history, 0));
+
+        // The search takes the draw floor at once instead of searching for the repetition
+        let (pos, history) = play(&start, &["g1f3", "g8f6", "f3g1"]);
+        let mut info = SearchInfo::new(Instant::now());
+        info.history = history;
+        let mut tt = TranspositionTable::new(16);
+        let score = pos.negamax(6, 1, -1, 0, &mut info, &mut tt, None, false);
+        assert_eq!(score, 0);
+        assert_eq!(info.nodes, 1);
+    }
+
+    #[test]
+    fn test_xboard_score() {
+        assert_eq!(xboard_score(35), 35);
+        assert_eq!(xboard_score(-120), -120);
+        // Mate in 1 (one ply) and in 2 (three plies); mated in 1 (two plies)
+        assert_eq!(xboard_score(MATE_SCORE - 1), 100_001);
+        assert_eq!(xboard_score(MATE_SCORE - 3), 100_002);
+        assert_eq!(xboard_score(-MATE_SCORE + 2), -100_001);
+    }
+
+    #[test]
+    fn test_wdl_model() {
+        let (win, draw, loss) = wdl(0);
+        assert_eq!(win, loss);
+        assert!(draw > 500, "level positions should mostly be drawn: {}", draw);
+
+        let (win, draw, loss) = wdl(1000);
+        assert!(win >= 990 && loss <= 1, "{} {} {}", win, draw, loss);
+        assert_eq!(wdl(-1000), (loss, draw, win));
+
+        assert_eq!(wdl(MATE_SCORE - 3), (1000, 0, 0));
+        assert_eq!(wdl(-MATE_SCORE + 4), (0, 0, 1000));
+    }
 }
@@ -1,5 +1,5 @@
 /// Static Exchange Evaluation (SEE)
-use crate::bitboard::{king_attacks, knight_attacks, pawn_attacks, Bitboard};
+use crate::bitboard::{line, Bitboard};
 use crate::magic::{bishop_attacks, rook_attacks};
 use crate::moves::Move;
 use crate::position::Position;
@@ -15,10 +15,31 @@ const SEE_VALUES: [i16; 6] = [
     10000, // King (should never be captured)
 ];
 
+/// Tactical category of a move, from SEE and whether it gives check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveClass {
+    /// Wins material even after the best recaptures
+    GoodCapture,
+    /// Trades evenly
+    EqualCapture,
+    /// Loses material to the recaptures
+    BadCapture,
+    /// Any promotion, capturing or not
+    Promotion,
+    /// Non-capture that gives check
+    QuietCheck,
+    Quiet,
+}
+
 impl Position {
     /// Check if SEE of a move is >= threshold
     /// Returns true if the move is winning or equal according to SEE
     pub fn see_ge(&self, mv: Move, threshold: i16) -> bool {
+        // Castling is legal only onto unattacked squares and wins nothing
+        if mv.is_castle() {
+            return threshold <= 0;
+        }
+
         let from = mv.from_sq();
         let to = mv.to_sq();
 
@@ -67,8 +88,9 @@ impl Position {
             occupied = occupied.clear(captured_sq);
         }
 
-        // Get all attackers to the target square
-        let mut attackers = self.attackers_to(to, occupied) & occupied;
+        // Get all attackers to the target square, split by color
+        let (white, black) = self.attackers_to_both(to, occupied);
+        let mut attackers = [white & occupied, black & occupied];
 
         // Start the exchange
         let mut side_to_move = self.side_to_move.flip();
@@ -83,34 +105,47 @@ impl Position {
             gain[depth] = SEE_VALUES[piece_on_sq as usize] - gain[depth - 1];
 
             // Pruning: if the current side can't improve even with a max gain, exit
-            if (-gain[depth - 1]).max(gain[depth]) < threshold {
+            if (-gain[depth - 1]).max(gain[depth]) < 0 {
                 break;
             }
 
             // Find least valuable attacker for the side to move
-            let stm_attackers = attackers & self.occupied[side_to_move as usize];
+            let stm = side_to_move as usize;
+            let mut stm_attackers = attackers[stm];
+
+            // Pinned pieces may only recapture along the pin ray while their pinner stands
+            if (self.check_info.pinners[stm] & occupied).is_not_empty() {
+                stm_attackers &= !self.pinned(side_to_move) | line(self.king_sq[stm], to);
+            }
             if stm_attackers.is_empty() {
                 break;
             }
 
             // Find LVA (Least Valuable Attacker)
-            let (attacker_sq, attacker_type) = self.find_lva(stm_attackers);
+            let (attacker_sq, attacker_type) = self.find_lva(side_to_move, stm_attackers);
+
+            // The king can only recapture once the square is no longer defended
+            if attacker_type == PieceType::King && attackers[stm ^ 1].is_not_empty() {
+                break;
+            }
 
             // Remove the attacker from occupied
             occupied = occupied.clear(attacker_sq);
-            attackers = attackers.clear(attacker_sq);
+            attackers[stm] = attackers[stm].clear(attacker_sq);
 
-            // Update x-ray attackers (sliders behind the attacker)
+            // Update x-ray attackers (sliders behind the attacker), one ray for both colors
             if attacker_type == PieceType::Pawn
                 || attacker_type == PieceType::Bishop
                 || attacker_type == PieceType::Queen
             {
-                attackers |=
-                    bishop_attacks(to, occupied) & self.diagonal_sliders_all() & occupied;
+                let ray = bishop_attacks(to, occupied) & occupied;
+                attackers[0] |= ray & self.diagonal_sliders(Color::White);
+                attackers[1] |= ray & self.diagonal_sliders(Color::Black);
             }
             if attacker_type == PieceType::Rook || attacker_type == PieceType::Queen {
-                attackers |=
-                    rook_attacks(to, occupied) & self.orthogonal_sliders_all() & occupied;
+                let ray = rook_attacks(to, occupied) & occupied;
+                attackers[0] |= ray & self.orthogonal_sliders(Color::White);
+                attackers[1] |= ray & self.orthogonal_sliders(Color::Black);
             }
 
             piece_on_sq = attacker_type;
@@ -126,8 +161,17 @@ impl Position {
         gain[0] >= threshold
     }
 
-    /// Get the SEE value of a capture move
+    /// Get the SEE value of a move: the exchange result for captures, the promotion gain
+    /// for quiet promotions and 0 for other quiet moves
     pub fn see_value(&self, mv: Move) -> i16 {
+        if !mv.is_capture() {
+            return if mv.is_promotion() {
+                SEE_VALUES[mv.promotion_piece() as usize] - SEE_VALUES[PieceType::Pawn as usize]
+            } else {
+                0
+            };
+        }
+
         // Find the actual SEE value through binary search
         let mut lo = -1500i16;
         let mut hi = 1500i16;
@@ -144,34 +188,33 @@ impl Position {
         lo
     }
 
-    /// Find the least valuable attacker in a set of attackers
-    fn find_lva(&self, attackers: Bitboard) -> (crate::types::Square, PieceType) {
-        for pt in [
-            PieceType::Pawn,
-            PieceType::Knight,
-            PieceType::Bishop,
-            PieceType::Rook,
-            PieceType::Queen,
-            PieceType::King,
-        ] {
-            let piece_bb = (self.piece_bb(Color::White, pt) | self.piece_bb(Color::Black, pt))
-                & attackers;
+    /// Classify a move for capture analysis
+    pub fn classify_move(&self, mv: Move) -> MoveClass {
+        if mv.is_promotion() {
+            MoveClass::Promotion
+        } else if mv.is_capture() {
+            match self.see_value(mv) {
+                v if v > 0 => MoveClass::GoodCapture,
+                0 => MoveClass::EqualCapture,
+                _ => MoveClass::BadCapture,
+            }
+        } else if self.gives_check(mv) {
+            MoveClass::QuietCheck
+        } else {
+            MoveClass::Quiet
+        }
+    }
+
+    /// Find the least valuable attacker of `color` in a set of attackers
+    fn find_lva(&self, color: Color, attackers: Bitboard) -> (crate::types::Square, PieceType) {
+        for pt in PieceType::ALL {
+            let piece_bb = self.piece_bb(color, pt) & attackers;
             if piece_bb.is_not_empty() {
                 return (piece_bb.lsb(), pt);
             }
         }
         unreachable!("No attacker found");
     }
-
-    /// Get all diagonal sliders (both colors)
-    fn diagonal_sliders_all(&self) -> Bitboard {
-        self.diagonal_sliders(Color::White) | self.diagonal_sliders(Color::Black)
-    }
-
-    /// Get all orthogonal sliders (both colors)
-    fn orthogonal_sliders_all(&self) -> Bitboard {
-        self.orthogonal_sliders(Color::White) | self.orthogonal_sliders(Color::Black)
-    }
 }
 
 /// Get piece value for SEE
@@ -216,10 +259,10 @@ mod tests {
     #[test]
     fn test_see_equal_exchange() {
         setup();
-        // Knight takes knight
+        // Knight takes knight, pawn recaptures
         let pos =
-            Position::from_fen("4k3/8/4n3/8/8/4N3/8/4K3 w - - 0 1").unwrap();
-        let mv = Move::capture(Square::from_algebraic("e3").unwrap(), Square::from_algebraic("e6").unwrap());
+            Position::from_fen("4k3/5p2/4n3/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        let mv = Move::capture(Square::from_algebraic("d4").unwrap(), Square::from_algebraic("e6").unwrap());
         assert!(pos.see_ge(mv, 0), "Knight takes knight should be equal");
         assert!(!pos.see_ge(mv, 100), "Should not win material");
     }
@@ -227,10 +270,10 @@ mod tests {
     #[test]
     fn test_see_complex_exchange() {
         setup();
-        // Pawn takes knight, knight retakes, pawn retakes
-        let pos = Position::from_fen("4k3/8/3n4/4n3/3P4/4P3/8/4K3 w - - 0 1").unwrap();
-        let mv = Move::capture(Square::from_algebraic("e3").unwrap(), Square::from_algebraic("d4").unwrap());
-        // This is a bad capture - pawn takes nothing, knight takes pawn
+        // Knight takes pawn, pawn retakes, rook takes pawn, rook retakes
+        let pos = Position::from_fen("3rk3/8/4p3/3p4/8/4N3/8/3RK3 w - - 0 1").unwrap();
+        let mv = Move::capture(Square::from_algebraic("e3").unwrap(), Square::from_algebraic("d5").unwrap());
+        // This is a bad capture - knight for pawn, and the rook cannot profitably continue
         assert!(!pos.see_ge(mv, 0));
     }
 
@@ -243,4 +286,59 @@ mod tests {
         let mv = Move::capture(Square::from_algebraic("d1").unwrap(), Square::from_algebraic("d8").unwrap());
         assert!(pos.see_ge(mv, 0), "RxR with x-ray should be winning");
     }
+
+    #[test]
+    fn test_see_pinned_defender() {
+        setup();
+        // The f6 knight is pinned to h8 by the c3 bishop and cannot retake on d5
+        let pos = Position::from_fen("7k/8/5n2/3p4/8/2B5/8/3R2K1 w - - 0 1").unwrap();
+        let mv = Move::capture(Square::D1, Square::D5);
+        assert!(pos.see_ge(mv, 100), "RxP against a pinned defender should win the pawn");
+
+        // Unpinned, the same defender makes the capture lose the exchange
+        let pos = Position::from_fen("7k/8/5n2/3p4/8/8/8/3R2K1 w - - 0 1").unwrap();
+        assert!(!pos.see_ge(mv, 0));
+    }
+
+    #[test]
+    fn test_see_king_cannot_take_defended() {
+        setup();
+        // The d5 pawn is covered by the f6 knight, so Kxd5 loses the king
+        let pos = Position::from_fen("8/8/5n2/3p4/4K3/8/8/7k w - - 0 1").unwrap();
+        let mv = Move::capture(Square::E4, Square::D5);
+        assert!(!pos.see_ge(mv, 0));
+
+        // NxP NxN, and the king cannot retake while the a8 bishop still covers d5
+        let pos = Position::from_fen("b7/8/4Kn2/3p4/8/2N5/8/7k w - - 0 1").unwrap();
+        let mv = Move::capture(Square::C3, Square::D5);
+        assert_eq!(pos.see_value(mv), -200);
+    }
+
+    #[test]
+    fn test_classify_move() {
+        setup();
+        let classify = |fen: &str, from: Square, to: Square| {
+            let pos = Position::from_fen(fen).unwrap();
+            let mv = pos.parse_uci_move(&format!("{}{}", from.to_algebraic(), to.to_algebraic()));
+            pos.classify_move(mv.unwrap())
+        };
+
+        // The positions of the SEE tests above
+        assert_eq!(classify("4k3/8/4p3/8/8/4Q3/8/4K3 w - - 0 1", Square::E3, Square::E6), MoveClass::GoodCapture);
+        assert_eq!(classify("4k3/4r3/4p3/8/8/4Q3/8/4K3 w - - 0 1", Square::E3, Square::E6), MoveClass::BadCapture);
+        assert_eq!(classify("4k3/5p2/4n3/8/3N4/8/8/4K3 w - - 0 1", Square::D4, Square::E6), MoveClass::EqualCapture);
+        assert_eq!(classify("3rk3/8/4p3/3p4/8/4N3/8/3RK3 w - - 0 1", Square::E3, Square::D5), MoveClass::BadCapture);
+
+        // Quiet moves
+        let fen = "4k3/8/8/8/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(classify(fen, Square::A1, Square::A8), MoveClass::QuietCheck);
+        assert_eq!(classify(fen, Square::A1, Square::A2), MoveClass::Quiet);
+
+        // Promotions, and SEE of non-captures
+        let pos = Position::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let promotion = pos.parse_uci_move("a7a8q").unwrap();
+        assert_eq!(pos.classify_move(promotion), MoveClass::Promotion);
+        assert_eq!(pos.see_value(promotion), 800);
+        assert_eq!(pos.see_value(pos.parse_uci_move("e1d2").unwrap()), 0);
+    }
 }
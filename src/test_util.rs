@@ -0,0 +1,42 @@
+//! Helpers shared by the unit tests
+
+use crate::moves::Move;
+use crate::position::Position;
+
+/// Seeded xorshift64 generator for reproducible random choices in tests
+pub struct TestRng(u64);
+
+impl TestRng {
+    /// Seed used unless a test needs a sequence of its own
+    pub const DEFAULT_SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+    /// Create a generator; the seed must be nonzero
+    pub fn new(seed: u64) -> Self {
+        debug_assert!(seed != 0, "xorshift seed must be nonzero");
+        TestRng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniform-enough index in `0..n`
+    pub fn below(&mut self, n: usize) -> usize {
+        self.next_u64() as usize % n
+    }
+}
+
+impl Default for TestRng {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SEED)
+    }
+}
+
+/// A random legal move, or `None` at mate or stalemate
+pub fn random_move(pos: &Position, rng: &mut TestRng) -> Option<Move> {
+    let moves = pos.legal_moves();
+    (!moves.is_empty()).then(|| moves[rng.below(moves.len())])
+}
@@ -0,0 +1,192 @@
+/// Time management: splitting the clock into per-move limits
+use crate::moves::Move;
+use std::time::Duration;
+
+/// Default safety margin for GUI and network lag, in milliseconds
+pub const DEFAULT_MOVE_OVERHEAD: u64 = 30;
+
+/// Moves left in the game assumed for sudden-death time controls
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+/// How far past the soft limit an unstable iteration may run
+const HARD_LIMIT_FACTOR: u64 = 3;
+
+/// Never spend more than this share of the clock (in quarters) on one move
+const MAX_CLOCK_QUARTERS: u64 = 3;
+
+/// Time allowed for one move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeBudget {
+    /// Don't start a new iteration after this much time, scaled by root stability.
+    /// `None` for a fixed move time, which should be used in full.
+    pub soft: Option<Duration>,
+    /// Abort the search mid-iteration at this point
+    pub hard: Duration,
+}
+
+impl TimeBudget {
+    /// Fixed time per move: no soft limit, and a hard limit of the move time minus the overhead
+    pub fn fixed(movetime_ms: u64, overhead_ms: u64) -> Self {
+        TimeBudget {
+            soft: None,
+            hard: Duration::from_millis(movetime_ms.saturating_sub(overhead_ms)),
+        }
+    }
+
+    /// Budget from the remaining clock, increment and moves to the next time control
+    pub fn from_clock(time_ms: u64, inc_ms: u64, movestogo: Option<u32>, overhead_ms: u64) -> Self {
+        let moves = movestogo.map_or(DEFAULT_MOVES_TO_GO, |m| m as u64).max(1);
+
+        // Never plan to use the time the interface needs
+        let max_time = time_ms.saturating_sub(overhead_ms) * MAX_CLOCK_QUARTERS / 4;
+
+        // Soft target: an even share of the clock plus most of the increment. Every move
+        // loses the overhead to interface lag, so it comes off each share.
+        let share = time_ms / moves + inc_ms * 3 / 4;
+        let soft = share.saturating_sub(overhead_ms).min(max_time);
+
+        // Hard limit: room to finish an unstable iteration
+        let hard = (soft * HARD_LIMIT_FACTOR).min(max_time);
+
+        TimeBudget {
+            soft: Some(Duration::from_millis(soft)),
+            hard: Duration::from_millis(hard),
+        }
+    }
+}
+
+/// Soft limit scale (percent) once the best move has held for several iterations
+const EASY_MOVE_SCALE: u64 = 60;
+/// Soft limit scale (percent) right after the best move changed
+const CHANGED_MOVE_SCALE: u64 = 150;
+/// Iterations the best move must survive to count as an easy move
+const EASY_MOVE_ITERATIONS: u32 = 3;
+
+/// Root best-move and score stability across iterative deepening iterations
+#[derive(Debug, Clone, Copy)]
+pub struct RootStability {
+    best_move: Move,
+    score: i16,
+    stable_iterations: u32,
+}
+
+impl RootStability {
+    pub fn new() -> Self {
+        RootStability {
+            best_move: Move::NULL,
+            score: 0,
+            stable_iterations: 0,
+        }
+    }
+
+    /// Record a finished iteration and return how much of the soft limit to use, in percent.
+    /// A long-standing best move with a steady score saves time; a new best move or a falling
+    /// score (fail-low at the root) extends it.
+    pub fn update(&mut self, best_move: Move, score: i16) -> u64 {
+        let first = self.best_move.is_null();
+        let drop = self.score as i32 - score as i32;
+
+        if best_move == self.best_move {
+            self.stable_iterations += 1;
+        } else {
+            self.stable_iterations = 0;
+        }
+        self.best_move = best_move;
+        self.score = score;
+
+        if first {
+            return 100;
+        }
+
+        let mut scale = match self.stable_iterations {
+            0 => CHANGED_MOVE_SCALE,
+            n if n >= EASY_MOVE_ITERATIONS && drop.abs() < 20 => EASY_MOVE_SCALE,
+            _ => 100,
+        };
+
+        if drop > 50 {
+            scale += 60;
+        } else if drop > 20 {
+            scale += 30;
+        }
+
+        scale
+    }
+}
+
+impl Default for RootStability {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Square;
+
+    #[test]
+    fn test_budget_from_clock() {
+        // 60s sudden death: a 30th of the clock, with up to three times that as the hard limit
+        let budget = TimeBudget::from_clock(60_000, 0, None, 0);
+        assert_eq!(budget.soft, Some(Duration::from_millis(2_000)));
+        assert_eq!(budget.hard, Duration::from_millis(6_000));
+
+        // Last move before the time control: still keep a quarter of the clock
+        let budget = TimeBudget::from_clock(4_000, 0, Some(1), 0);
+        assert_eq!(budget.soft, Some(Duration::from_millis(3_000)));
+        assert_eq!(Some(budget.hard), budget.soft);
+
+        // Low on time with a big increment
+        let budget = TimeBudget::from_clock(1_000, 2_000, None, 0);
+        assert_eq!(budget.soft, Some(Duration::from_millis(750)));
+        assert_eq!(Some(budget.hard), budget.soft);
+    }
+
+    #[test]
+    fn test_root_stability_scales_soft_limit() {
+        let e4 = Move::double_push(Square::E2, Square::E4);
+        let d4 = Move::double_push(Square::D2, Square::D4);
+
+        // Same move and score every iteration: an easy move, stop early
+        let mut stable = RootStability::new();
+        let stable_scales: Vec<u64> = (0..6).map(|_| stable.update(e4, 30)).collect();
+        assert_eq!(stable_scales.last(), Some(&EASY_MOVE_SCALE));
+
+        // The best move keeps flipping: use more of the budget every time
+        let mut flipping = RootStability::new();
+        let flipping_scales: Vec<u64> = (0..6)
+            .map(|i| flipping.update(if i % 2 == 0 { e4 } else { d4 }, 30))
+            .collect();
+        assert!(flipping_scales[1..].iter().all(|&scale| scale > 100));
+        let total = |scales: &[u64]| scales.iter().sum::<u64>();
+        assert!(total(&flipping_scales) > total(&stable_scales));
+
+        // A sharp score drop on the same move (fail-low) also extends
+        let mut failing = RootStability::new();
+        for _ in 0..4 {
+            failing.update(e4, 30);
+        }
+        assert!(failing.update(e4, -40) > 100);
+    }
+
+    #[test]
+    fn test_move_overhead_reduces_budget() {
+        let base = TimeBudget::from_clock(60_000, 1_000, None, 0);
+        let lagged = TimeBudget::from_clock(60_000, 1_000, None, 100);
+        assert_eq!(base.soft.unwrap() - lagged.soft.unwrap(), Duration::from_millis(100));
+        assert_eq!(base.hard - lagged.hard, Duration::from_millis(300));
+
+        // Nearly flagging: the overhead still comes off the clock
+        let budget = TimeBudget::from_clock(200, 0, None, 100);
+        assert!(budget.hard <= Duration::from_millis(75));
+    }
+
+    #[test]
+    fn test_fixed_budget() {
+        let budget = TimeBudget::fixed(1_000, 30);
+        assert_eq!(budget.soft, None);
+        assert_eq!(budget.hard, Duration::from_millis(970));
+        assert_eq!(TimeBudget::fixed(10, 30).hard, Duration::ZERO);
+    }
+}
@@ -18,7 +18,8 @@ pub enum Bound {
 pub struct TTEntry {
     /// Upper 32 bits of hash key for verification
     pub key: u32,
-    /// Bits 16-31 of hash key for additional collision resistance
+    /// Bits 16-31 of hash key. These overlap the bits `index` uses, so entries in one
+    /// slot mostly agree on them; only small tables gain any collision resistance.
     pub key_low: u16,
     /// Best move found
     pub best_move: Move,
@@ -62,7 +63,8 @@ impl TTEntry {
     }
 
     /// Check if this entry is valid for the given hash
-    /// Uses 48 bits of the hash for collision resistance (upper 32 + bits 16-31)
+    /// Compares the upper 32 bits, which are independent of the slot, and bits 16-31,
+    /// which the slot already largely fixes
     #[inline(always)]
     pub fn is_valid(&self, hash: u64) -> bool {
         self.key == (hash >> 32) as u32 && self.key_low == ((hash >> 16) & 0xFFFF) as u16
@@ -81,28 +83,32 @@ impl TTEntry {
     }
 }
 
+/// Entries inspected by `hashfull`
+const HASHFULL_SAMPLES: usize = 1000;
+
 /// Transposition table
 pub struct TranspositionTable {
     /// Table entries
     table: Vec<TTEntry>,
-    /// Mask for indexing (size - 1)
-    mask: usize,
     /// Current age
     age: u8,
     /// Number of entries
     num_entries: usize,
 }
 
+/// Entries in a table of `size_mb` MB. Any count works with multiply-shift indexing, so
+/// the table uses exactly the requested memory; the cap keeps `index` within 64 bits.
+fn entries_for(size_mb: usize) -> usize {
+    (size_mb * 1024 * 1024 / std::mem::size_of::<TTEntry>()).clamp(1, u32::MAX as usize)
+}
+
 impl TranspositionTable {
     /// Create a new transposition table with the given size in MB
     pub fn new(size_mb: usize) -> Self {
-        let size_bytes = size_mb * 1024 * 1024;
-        let entry_size = std::mem::size_of::<TTEntry>();
-        let num_entries = (size_bytes / entry_size).next_power_of_two();
+        let num_entries = entries_for(size_mb);
 
         TranspositionTable {
             table: vec![TTEntry::default(); num_entries],
-            mask: num_entries - 1,
             age: 0,
             num_entries,
         }
@@ -110,22 +116,21 @@ impl TranspositionTable {
 
     /// Resize the table to the given size in MB
     pub fn resize(&mut self, size_mb: usize) {
-        let size_bytes = size_mb * 1024 * 1024;
-        let entry_size = std::mem::size_of::<TTEntry>();
-        let num_entries = (size_bytes / entry_size).next_power_of_two();
+        let num_entries = entries_for(size_mb);
 
         if num_entries != self.num_entries {
             self.table = vec![TTEntry::default(); num_entries];
-            self.mask = num_entries - 1;
             self.num_entries = num_entries;
             self.age = 0;
         }
     }
 
-    /// Get the index for a hash
+    /// Get the index for a hash: the low 32 bits scaled to `[0, num_entries)` by a
+    /// multiply-shift, so the slot depends mostly on their top bits. The 32-bit
+    /// verification key holds the upper half and stays independent of the slot.
     #[inline(always)]
     fn index(&self, hash: u64) -> usize {
-        (hash as usize) & self.mask
+        ((hash as u32 as u64 * self.num_entries as u64) >> 32) as usize
     }
 
     /// Probe the table for an entry
@@ -206,14 +211,25 @@ impl TranspositionTable {
         self.age = self.age.wrapping_add(1);
     }
 
-    /// Get occupancy percentage (for UCI info)
+    /// Per mille of the table in use, whatever the entries' age (UCI "hashfull")
     pub fn hashfull(&self) -> usize {
-        let sample_size = 1000.min(self.num_entries);
-        let used = self.table[..sample_size]
-            .iter()
-            .filter(|e| e.bound != Bound::None && e.age == self.age)
+        self.sample_usage(|_| true)
+    }
+
+    /// Per mille of the table written since the last `new_search`
+    pub fn hashfull_current(&self) -> usize {
+        self.sample_usage(|entry| entry.age == self.age)
+    }
+
+    /// Count used entries among HASHFULL_SAMPLES spread evenly over the whole table
+    fn sample_usage(&self, counts: impl Fn(&TTEntry) -> bool) -> usize {
+        let samples = HASHFULL_SAMPLES.min(self.num_entries);
+        let stride = self.num_entries / samples;
+        let used = (0..samples)
+            .map(|i| &self.table[i * stride])
+            .filter(|&entry| entry.bound != Bound::None && counts(entry))
             .count();
-        (used * 1000) / sample_size
+        (used * 1000) / samples
     }
 
     /// Get the size in MB
@@ -231,6 +247,7 @@ impl Default for TranspositionTable {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::TestRng;
     use crate::types::Square;
 
     #[test]
@@ -239,6 +256,47 @@ mod tests {
         assert_eq!(std::mem::size_of::<TTEntry>(), 16);
     }
 
+    /// Smallest hash whose low half indexes slot `idx`
+    fn hash_for_slot(tt: &TranspositionTable, idx: usize) -> u64 {
+        ((idx as u64) << 32).div_ceil(tt.num_entries as u64)
+    }
+
+    #[test]
+    fn test_size_matches_request() {
+        for size_mb in [1, 3, 64, 96, 100] {
+            assert_eq!(TranspositionTable::new(size_mb).size_mb(), size_mb);
+        }
+
+        let mut tt = TranspositionTable::new(64);
+        tt.resize(200);
+        assert_eq!(tt.size_mb(), 200);
+    }
+
+    #[test]
+    fn test_index_in_range_and_even() {
+        let tt = TranspositionTable::new(3);
+        let n = tt.num_entries;
+        assert!(!n.is_power_of_two());
+
+        // Every slot is reachable, and the edges of the hash range stay in bounds
+        for idx in [0, 1, n / 2, n - 1] {
+            assert_eq!(tt.index(hash_for_slot(&tt, idx)), idx);
+        }
+        assert_eq!(tt.index(u64::MAX), n - 1);
+
+        // Random hashes land evenly in ten bands of slots
+        let mut rng = TestRng::default();
+        let mut bands = [0u32; 10];
+        for _ in 0..100_000 {
+            let idx = tt.index(rng.next_u64());
+            assert!(idx < n);
+            bands[idx * 10 / n] += 1;
+        }
+        for count in bands {
+            assert!((9_000..=11_000).contains(&count), "{:?}", bands);
+        }
+    }
+
     #[test]
     fn test_tt_store_and_probe() {
         let mut tt = TranspositionTable::new(1);
@@ -307,7 +365,29 @@ mod tests {
         let entry = tt.probe(hash).unwrap();
         assert_eq!(entry.depth, 5);
 
-        // But hashfull should be 0 (old entries don't count)
-        assert_eq!(tt.hashfull(), 0);
+        // But it no longer counts as written in this search
+        assert_eq!(tt.hashfull_current(), 0);
+    }
+
+    #[test]
+    fn test_hashfull_full_table() {
+        let mut tt = TranspositionTable::new(1);
+        for i in 0..tt.num_entries {
+            tt.store(hash_for_slot(&tt, i) | ((i as u64) << 32), 1, 0, Bound::Lower, Move::NULL, 0);
+        }
+        assert_eq!(tt.hashfull(), 1000);
+        assert_eq!(tt.hashfull_current(), 1000);
+
+        // Entries from earlier searches still take up the table
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 1000);
+        assert_eq!(tt.hashfull_current(), 0);
+
+        // Half the slots, interleaved, reads as half full across the whole table
+        tt.clear();
+        for i in (0..tt.num_entries).step_by(2) {
+            tt.store(hash_for_slot(&tt, i), 1, 0, Bound::Lower, Move::NULL, 0);
+        }
+        assert!((400..=600).contains(&tt.hashfull()), "{}", tt.hashfull());
     }
 }
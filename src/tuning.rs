@@ -0,0 +1,453 @@
+/// Texel tuning of evaluation weights against labeled positions
+use crate::eval::{EvalParams, Score};
+use crate::magic::init_magics;
+use crate::position::Position;
+use crate::types::Color;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::str::FromStr;
+
+/// A position labeled with the result of the game it came from
+pub struct TuningEntry {
+    pub position: Position,
+    /// Game result from white's perspective: 1.0 win, 0.5 draw, 0.0 loss
+    pub result: f64,
+}
+
+/// Groups of eval weights that can be tuned independently
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamGroup {
+    PieceValues,
+    Psqt,
+    Pawns,
+    Rooks,
+    BishopPair,
+    Threats,
+    KingSafety,
+}
+
+impl ParamGroup {
+    pub const ALL: [ParamGroup; 7] = [
+        ParamGroup::PieceValues,
+        ParamGroup::Psqt,
+        ParamGroup::Pawns,
+        ParamGroup::Rooks,
+        ParamGroup::BishopPair,
+        ParamGroup::Threats,
+        ParamGroup::KingSafety,
+    ];
+}
+
+impl FromStr for ParamGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "material" => Ok(ParamGroup::PieceValues),
+            "psqt" => Ok(ParamGroup::Psqt),
+            "pawns" => Ok(ParamGroup::Pawns),
+            "rooks" => Ok(ParamGroup::Rooks),
+            "bishoppair" => Ok(ParamGroup::BishopPair),
+            "threats" => Ok(ParamGroup::Threats),
+            "kingsafety" => Ok(ParamGroup::KingSafety),
+            _ => Err(format!("unknown parameter group: {}", s)),
+        }
+    }
+}
+
+/// Parse a game result token ("1-0", "1/2-1/2", "0.5", ...)
+fn parse_result(token: &str) -> Option<f64> {
+    match token.trim().trim_matches('"') {
+        "1-0" | "1" | "1.0" => Some(1.0),
+        "0-1" | "0" | "0.0" => Some(0.0),
+        "1/2-1/2" | "1/2" | "0.5" => Some(0.5),
+        _ => None,
+    }
+}
+
+/// Parse one dataset line: `<fen> c9 "<result>";`, `<fen> [<result>]` or `<fen> <result>`
+pub fn parse_entry(line: &str) -> Option<TuningEntry> {
+    let line = line.trim().trim_end_matches(';');
+
+    let (fen, result) = if let Some(idx) = line.find(" c9 ") {
+        (&line[..idx], &line[idx + 4..])
+    } else if let Some(idx) = line.find('[') {
+        (&line[..idx], line[idx + 1..].trim_end_matches(']'))
+    } else {
+        line.rsplit_once(char::is_whitespace)?
+    };
+
+    Some(TuningEntry {
+        position: Position::from_fen(fen.trim()).ok()?,
+        result: parse_result(result)?,
+    })
+}
+
+/// Load a labeled dataset, skipping lines that can't be parsed
+pub fn load_dataset(path: &str) -> io::Result<Vec<TuningEntry>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+
+    for line in reader.lines() {
+        if let Some(entry) = parse_entry(&line?) {
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Expected score for a white-perspective eval, scaled by K
+fn sigmoid(eval: f64, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval / 400.0))
+}
+
+/// Mean squared error between the game results and the sigmoid of the static eval
+pub fn mean_error(entries: &[TuningEntry], params: &EvalParams, k: f64) -> f64 {
+    if entries.is_empty() {
+        return 0.0;
+    }
+
+    // Attack tables must exist before any worker touches them
+    init_magics();
+
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = entries.len().div_ceil(threads);
+
+    let total: f64 = std::thread::scope(|scope| {
+        let workers: Vec<_> = entries
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|entry| {
+                            let eval = entry.position.evaluate_with(params) as f64;
+                            let eval = match entry.position.side_to_move {
+                                Color::White => eval,
+                                Color::Black => -eval,
+                            };
+                            (entry.result - sigmoid(eval, k)).powi(2)
+                        })
+                        .sum::<f64>()
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("tuning worker panicked"))
+            .sum()
+    });
+
+    total / entries.len() as f64
+}
+
+/// Find the scaling constant K that minimizes the error for the given weights
+pub fn optimize_k(entries: &[TuningEntry], params: &EvalParams) -> f64 {
+    let mut best_k = 1.0;
+    let mut best_error = mean_error(entries, params, best_k);
+    let mut step = 0.1;
+
+    for _ in 0..4 {
+        let center = best_k;
+        for i in -10..=10 {
+            let k = center + i as f64 * step;
+            if k <= 0.0 {
+                continue;
+            }
+            let error = mean_error(entries, params, k);
+            if error < best_error {
+                best_error = error;
+                best_k = k;
+            }
+        }
+        step /= 10.0;
+    }
+
+    best_k
+}
+
+/// Mutable references to the weights in the selected groups
+fn tunable_mut<'a>(params: &'a mut EvalParams, groups: &[ParamGroup]) -> Vec<&'a mut i16> {
+    let EvalParams {
+        piece_values,
+        psqt_mg,
+        psqt_eg,
+        bishop_pair,
+        doubled_pawn,
+        isolated_pawn,
+        passed_pawn,
+        rook_open_file,
+        rook_semi_open_file,
+        queen_open_file,
+        queen_semi_open_file,
+        trapped_rook,
+        hanging_piece,
+        threat_by_pawn,
+        threat_by_minor,
+        king_shelter,
+        pawn_storm,
+    } = params;
+
+    fn push<'a>(out: &mut Vec<&'a mut i16>, score: &'a mut Score) {
+        out.push(&mut score.mg);
+        out.push(&mut score.eg);
+    }
+
+    let mut out = Vec::new();
+    if groups.contains(&ParamGroup::PieceValues) {
+        // The king has no material value
+        for score in piece_values.iter_mut().take(5) {
+            push(&mut out, score);
+        }
+    }
+    if groups.contains(&ParamGroup::Psqt) {
+        out.extend(psqt_mg.iter_mut().flatten());
+        out.extend(psqt_eg.iter_mut().flatten());
+    }
+    if groups.contains(&ParamGroup::Pawns) {
+        push(&mut out, doubled_pawn);
+        push(&mut out, isolated_pawn);
+        // Pawns never stand on the first or last rank
+        for score in passed_pawn.iter_mut().take(7).skip(1) {
+            push(&mut out, score);
+        }
+    }
+    if groups.contains(&ParamGroup::Rooks) {
+        push(&mut out, rook_open_file);
+        push(&mut out, rook_semi_open_file);
+        push(&mut out, queen_open_file);
+        push(&mut out, queen_semi_open_file);
+        push(&mut out, trapped_rook);
+    }
+    if groups.contains(&ParamGroup::BishopPair) {
+        push(&mut out, bishop_pair);
+    }
+    if groups.contains(&ParamGroup::Threats) {
+        // Kings are never hanging, and pawns are never threatened by pawns
+        for score in hanging_piece.iter_mut().take(5) {
+            push(&mut out, score);
+        }
+        for score in threat_by_pawn.iter_mut().take(5).skip(1) {
+            push(&mut out, score);
+        }
+        for score in threat_by_minor.iter_mut().take(5).skip(3) {
+            push(&mut out, score);
+        }
+    }
+    if groups.contains(&ParamGroup::KingSafety) {
+        // Pawns are never more than six ranks in front of the king
+        out.extend(king_shelter.iter_mut().take(7));
+        out.extend(pawn_storm.iter_mut().take(7).skip(1));
+    }
+    out
+}
+
+/// Coordinate-descent tuner over a subset of the eval weights
+pub struct Tuner {
+    entries: Vec<TuningEntry>,
+    groups: Vec<ParamGroup>,
+    pub params: EvalParams,
+    pub k: f64,
+    error: f64,
+}
+
+impl Tuner {
+    /// Create a tuner starting from `params`, fitting K to the dataset first
+    pub fn new(entries: Vec<TuningEntry>, params: EvalParams, groups: &[ParamGroup]) -> Self {
+        let k = optimize_k(&entries, &params);
+        let error = mean_error(&entries, &params, k);
+        Tuner {
+            entries,
+            groups: groups.to_vec(),
+            params,
+            k,
+            error,
+        }
+    }
+
+    /// Current error over the dataset
+    pub fn error(&self) -> f64 {
+        self.error
+    }
+
+    /// Number of weights being tuned
+    pub fn param_count(&self) -> usize {
+        tunable_mut(&mut self.params.clone(), &self.groups).len()
+    }
+
+    /// Try moving every weight by +-1, keeping changes that lower the error.
+    /// Returns the new error, which never exceeds the previous one.
+    pub fn iterate(&mut self) -> f64 {
+        for i in 0..self.param_count() {
+            for delta in [1, -1] {
+                let mut candidate = self.params.clone();
+                *tunable_mut(&mut candidate, &self.groups)[i] += delta;
+
+                let error = mean_error(&self.entries, &candidate, self.k);
+                if error < self.error {
+                    self.params = candidate;
+                    self.error = error;
+                    break;
+                }
+            }
+        }
+
+        self.error
+    }
+}
+
+/// Print the weights as Rust source that can be pasted back into eval.rs
+pub fn print_params(params: &EvalParams) {
+    let score = |s: &Score| format!("Score::new({}, {})", s.mg, s.eg);
+
+    println!("pub const PIECE_VALUES: [Score; 6] = [");
+    for value in &params.piece_values {
+        println!("    {},", score(value));
+    }
+    println!("];");
+
+    for (name, table) in [("PSQT_MG", &params.psqt_mg), ("PSQT_EG", &params.psqt_eg)] {
+        println!("pub static {}: [[i16; 64]; 6] = [", name);
+        for piece in table {
+            println!("    [");
+            for row in piece.chunks(8) {
+                let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+                println!("        {},", row.join(", "));
+            }
+            println!("    ],");
+        }
+        println!("];");
+    }
+
+    println!("const BISHOP_PAIR: Score = {};", score(&params.bishop_pair));
+    println!("const DOUBLED_PAWN: Score = {};", score(&params.doubled_pawn));
+    println!("const ISOLATED_PAWN: Score = {};", score(&params.isolated_pawn));
+    println!("const PASSED_PAWN_BONUS: [Score; 8] = [");
+    for value in &params.passed_pawn {
+        println!("    {},", score(value));
+    }
+    println!("];");
+    println!("const ROOK_OPEN_FILE: Score = {};", score(&params.rook_open_file));
+    println!("const ROOK_SEMI_OPEN_FILE: Score = {};", score(&params.rook_semi_open_file));
+    println!("const QUEEN_OPEN_FILE: Score = {};", score(&params.queen_open_file));
+    println!("const QUEEN_SEMI_OPEN_FILE: Score = {};", score(&params.queen_semi_open_file));
+    println!("const TRAPPED_ROOK: Score = {};", score(&params.trapped_rook));
+    for (name, table) in [
+        ("HANGING_PIECE", &params.hanging_piece),
+        ("THREAT_BY_PAWN", &params.threat_by_pawn),
+        ("THREAT_BY_MINOR", &params.threat_by_minor),
+    ] {
+        println!("const {}: [Score; 6] = [", name);
+        for value in table {
+            println!("    {},", score(value));
+        }
+        println!("];");
+    }
+    println!("const KING_SHELTER: [i16; 8] = {:?};", params.king_shelter);
+    println!("const PAWN_STORM: [i16; 8] = {:?};", params.pawn_storm);
+}
+
+/// Entry point for `kai tune <dataset> [iterations] [group,group,...]`
+pub fn run_cli(args: &[String]) {
+    let Some(path) = args.first() else {
+        eprintln!(
+            "usage: kai tune <dataset> [iterations] \
+             [material,psqt,pawns,rooks,bishoppair,threats,kingsafety]"
+        );
+        return;
+    };
+    let iterations: usize = args.get(1).and_then(|s| s.parse().ok()).unwrap_or(100);
+    let groups = match args.get(2) {
+        Some(list) => match list.split(',').map(str::parse).collect::<Result<Vec<_>, _>>() {
+            Ok(groups) => groups,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        },
+        None => ParamGroup::ALL.to_vec(),
+    };
+
+    let entries = match load_dataset(path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("Failed to read {}: {}", path, err);
+            return;
+        }
+    };
+    println!("Loaded {} positions", entries.len());
+
+    let mut tuner = Tuner::new(entries, EvalParams::default(), &groups);
+    println!("Tuning {} parameters, K = {:.4}", tuner.param_count(), tuner.k);
+    println!("Initial error: {:.8}", tuner.error());
+
+    for iteration in 1..=iterations {
+        let before = tuner.error();
+        let error = tuner.iterate();
+        println!("Iteration {}: error {:.8}", iteration, error);
+        if error >= before {
+            break;
+        }
+    }
+
+    print_params(&tuner.params);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() {
+        static INIT: std::sync::Once = std::sync::Once::new();
+        INIT.call_once(|| {
+            init_magics();
+        });
+    }
+
+    #[test]
+    fn test_parse_entry_formats() {
+        setup();
+
+        let entry = parse_entry(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - c9 \"1/2-1/2\";",
+        )
+        .unwrap();
+        assert_eq!(entry.result, 0.5);
+        assert_eq!(entry.position.side_to_move, Color::Black);
+
+        let entry = parse_entry("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 [1.0]").unwrap();
+        assert_eq!(entry.result, 1.0);
+
+        let entry = parse_entry("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 0-1").unwrap();
+        assert_eq!(entry.result, 0.0);
+
+        assert!(parse_entry("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 [2.0]").is_none());
+        assert!(parse_entry("not a fen 1-0").is_none());
+    }
+
+    #[test]
+    fn test_tuning_iteration_does_not_increase_error() {
+        setup();
+        let dataset = [
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 [1.0]",
+            "4k3/4p3/8/8/8/8/8/4K3 b - - 0 1 [0.0]",
+            "r3k3/8/8/8/8/8/8/4K2R w - - 0 1 [0.5]",
+            "4k3/8/8/3b4/8/8/8/2B1K3 w - - 0 1 [0.5]",
+            "4k3/pp6/8/8/8/8/PP6/RN2K3 w - - 0 1 [1.0]",
+            "2b1k3/pp6/8/8/8/8/PP6/R3K3 b - - 0 1 [0.5]",
+        ];
+        let entries: Vec<_> = dataset.iter().filter_map(|line| parse_entry(line)).collect();
+        assert_eq!(entries.len(), dataset.len());
+
+        let groups = [ParamGroup::PieceValues, ParamGroup::BishopPair];
+        let mut tuner = Tuner::new(entries, EvalParams::default(), &groups);
+        assert_eq!(tuner.param_count(), 12);
+
+        let before = tuner.error();
+        let after = tuner.iterate();
+        assert!(after <= before, "error went from {} to {}", before, after);
+        assert_eq!(after, mean_error(&tuner.entries, &tuner.params, tuner.k));
+    }
+}
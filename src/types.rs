@@ -1,4 +1,4 @@
-/// Core types for the chess engine
+//! Core types for the chess engine
 
 /// Represents a square on the chess board (0-63)
 /// Layout: a1=0, b1=1, ..., h1=7, a2=8, ..., h8=63
@@ -15,10 +15,54 @@ impl Square {
     pub const F1: Square = Square(5);
     pub const G1: Square = Square(6);
     pub const H1: Square = Square(7);
+    pub const A2: Square = Square(8);
+    pub const B2: Square = Square(9);
+    pub const C2: Square = Square(10);
+    pub const D2: Square = Square(11);
     pub const E2: Square = Square(12);
+    pub const F2: Square = Square(13);
+    pub const G2: Square = Square(14);
+    pub const H2: Square = Square(15);
+    pub const A3: Square = Square(16);
+    pub const B3: Square = Square(17);
+    pub const C3: Square = Square(18);
+    pub const D3: Square = Square(19);
+    pub const E3: Square = Square(20);
+    pub const F3: Square = Square(21);
+    pub const G3: Square = Square(22);
+    pub const H3: Square = Square(23);
+    pub const A4: Square = Square(24);
+    pub const B4: Square = Square(25);
+    pub const C4: Square = Square(26);
+    pub const D4: Square = Square(27);
     pub const E4: Square = Square(28);
+    pub const F4: Square = Square(29);
+    pub const G4: Square = Square(30);
+    pub const H4: Square = Square(31);
+    pub const A5: Square = Square(32);
+    pub const B5: Square = Square(33);
+    pub const C5: Square = Square(34);
     pub const D5: Square = Square(35);
+    pub const E5: Square = Square(36);
+    pub const F5: Square = Square(37);
+    pub const G5: Square = Square(38);
+    pub const H5: Square = Square(39);
+    pub const A6: Square = Square(40);
+    pub const B6: Square = Square(41);
+    pub const C6: Square = Square(42);
+    pub const D6: Square = Square(43);
+    pub const E6: Square = Square(44);
+    pub const F6: Square = Square(45);
+    pub const G6: Square = Square(46);
+    pub const H6: Square = Square(47);
+    pub const A7: Square = Square(48);
+    pub const B7: Square = Square(49);
+    pub const C7: Square = Square(50);
+    pub const D7: Square = Square(51);
     pub const E7: Square = Square(52);
+    pub const F7: Square = Square(53);
+    pub const G7: Square = Square(54);
+    pub const H7: Square = Square(55);
     pub const A8: Square = Square(56);
     pub const B8: Square = Square(57);
     pub const C8: Square = Square(58);
@@ -67,6 +111,56 @@ impl Square {
         self.0 < 64
     }
 
+    /// King-move distance between two squares
+    #[inline(always)]
+    pub const fn chebyshev_distance(self, other: Square) -> u8 {
+        let df = self.file().abs_diff(other.file());
+        let dr = self.rank().abs_diff(other.rank());
+        if df > dr {
+            df
+        } else {
+            dr
+        }
+    }
+
+    /// Rook-move (file plus rank) distance between two squares
+    #[inline(always)]
+    pub const fn manhattan_distance(self, other: Square) -> u8 {
+        self.file().abs_diff(other.file()) + self.rank().abs_diff(other.rank())
+    }
+
+    /// Chebyshev distance to the nearest centre square (0 on d4-e5, 3 in a corner)
+    #[inline(always)]
+    pub const fn center_distance(self) -> u8 {
+        let df = if self.file() < 4 { 3 - self.file() } else { self.file() - 4 };
+        let dr = if self.rank() < 4 { 3 - self.rank() } else { self.rank() - 4 };
+        if df > dr {
+            df
+        } else {
+            dr
+        }
+    }
+
+    /// All 64 squares, a1 to h8
+    #[inline(always)]
+    pub fn iter() -> impl DoubleEndedIterator<Item = Square> + ExactSizeIterator {
+        (0..64).map(Square)
+    }
+
+    /// The squares of a rank (0 = first rank), a-file to h-file
+    #[inline(always)]
+    pub fn rank_squares(rank: u8) -> impl DoubleEndedIterator<Item = Square> + ExactSizeIterator {
+        debug_assert!(rank < 8);
+        (rank * 8..rank * 8 + 8).map(Square)
+    }
+
+    /// The squares of a file (0 = a-file), first rank to eighth
+    #[inline(always)]
+    pub fn file_squares(file: u8) -> impl DoubleEndedIterator<Item = Square> + ExactSizeIterator {
+        debug_assert!(file < 8);
+        (0..8).map(move |rank| Square::from_coords(file, rank))
+    }
+
     /// Parse square from algebraic notation (e.g., "e4")
     pub fn from_algebraic(s: &str) -> Option<Self> {
         let bytes = s.as_bytes();
@@ -110,6 +204,9 @@ pub enum Color {
 }
 
 impl Color {
+    /// Both colors in index order
+    pub const ALL: [Color; 2] = [Color::White, Color::Black];
+
     #[inline(always)]
     pub const fn flip(self) -> Self {
         match self {
@@ -185,11 +282,36 @@ pub enum PieceType {
 impl PieceType {
     pub const COUNT: usize = 6;
 
+    /// All piece types in index order
+    pub const ALL: [PieceType; 6] = [
+        PieceType::Pawn,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Rook,
+        PieceType::Queen,
+        PieceType::King,
+    ];
+
     #[inline(always)]
     pub const fn index(self) -> usize {
         self as usize
     }
 
+    /// Fast conversion from an index known to be in range (checked in debug builds).
+    /// Out-of-range values saturate to King instead of being UB.
+    #[inline(always)]
+    pub const fn from_index(index: u8) -> Self {
+        debug_assert!(index < 6, "invalid piece type");
+        match index {
+            0 => PieceType::Pawn,
+            1 => PieceType::Knight,
+            2 => PieceType::Bishop,
+            3 => PieceType::Rook,
+            4 => PieceType::Queen,
+            _ => PieceType::King,
+        }
+    }
+
     pub fn from_char(c: char) -> Option<Self> {
         match c.to_ascii_lowercase() {
             'p' => Some(PieceType::Pawn),
@@ -214,11 +336,13 @@ impl PieceType {
     }
 }
 
-impl From<u8> for PieceType {
+/// Checked conversion, returning the value back if it is out of range
+impl TryFrom<u8> for PieceType {
+    type Error = u8;
+
     #[inline(always)]
-    fn from(value: u8) -> Self {
-        debug_assert!(value < 6);
-        unsafe { std::mem::transmute(value) }
+    fn try_from(value: u8) -> Result<Self, u8> {
+        PieceType::ALL.get(value as usize).copied().ok_or(value)
     }
 }
 
@@ -260,7 +384,7 @@ impl Piece {
 
     #[inline(always)]
     pub const fn piece_type(self) -> PieceType {
-        unsafe { std::mem::transmute(self.0 & 7) }
+        PieceType::from_index(self.0 & 7)
     }
 
     #[inline(always)]
@@ -339,6 +463,20 @@ impl CastlingRights {
         self.0 as usize
     }
 
+    /// Slot of a single right in per-right tables: WK, WQ, BK, BQ
+    #[inline(always)]
+    pub const fn slot(self) -> usize {
+        self.0.trailing_zeros() as usize
+    }
+
+    /// The four single rights, in slot order
+    pub const SINGLE: [CastlingRights; 4] = [
+        CastlingRights::WHITE_KINGSIDE,
+        CastlingRights::WHITE_QUEENSIDE,
+        CastlingRights::BLACK_KINGSIDE,
+        CastlingRights::BLACK_QUEENSIDE,
+    ];
+
     pub fn from_fen(s: &str) -> Self {
         let mut rights = CastlingRights::NONE;
         for c in s.chars() {
@@ -463,9 +601,68 @@ mod tests {
         assert_eq!(Square::E4.rank(), 3);
     }
 
-    const _: Square = Square(28); // E4
-    impl Square {
-        pub const E4: Square = Square(28);
+    #[test]
+    fn test_square_iterators() {
+        assert_eq!(Square::iter().count(), 64);
+        assert!(Square::iter().enumerate().all(|(i, sq)| sq.0 as usize == i));
+
+        let first_rank: Vec<String> = Square::rank_squares(0).map(Square::to_algebraic).collect();
+        assert_eq!(first_rank, ["a1", "b1", "c1", "d1", "e1", "f1", "g1", "h1"]);
+        assert!(Square::rank_squares(7).all(|sq| sq.rank() == 7));
+
+        let e_file: Vec<Square> = Square::file_squares(4).collect();
+        assert_eq!(e_file.len(), 8);
+        assert_eq!((e_file[0], e_file[7]), (Square::E1, Square::E8));
+        assert!(e_file.iter().all(|sq| sq.file() == 4));
+    }
+
+    #[test]
+    fn test_all_constants_in_index_order() {
+        assert_eq!(PieceType::ALL.len(), PieceType::COUNT);
+        for (i, pt) in PieceType::ALL.iter().enumerate() {
+            assert_eq!(pt.index(), i);
+        }
+        for (i, color) in Color::ALL.iter().enumerate() {
+            assert_eq!(color.index(), i);
+        }
+    }
+
+    #[test]
+    fn test_piece_type_try_from() {
+        for i in 0..6u8 {
+            let pt = PieceType::try_from(i).unwrap();
+            assert_eq!(pt as u8, i);
+            assert_eq!(PieceType::from_index(i), pt);
+            assert_eq!(i.try_into(), Ok(pt));
+            assert_eq!(Piece::new(Color::Black, pt).piece_type(), pt);
+        }
+        assert_eq!(PieceType::try_from(6), Err(6));
+        assert_eq!(PieceType::try_from(255), Err(255));
+        assert_eq!(<PieceType as TryFrom<u8>>::try_from(7), Err(7));
+    }
+
+    #[test]
+    fn test_square_distances() {
+        assert_eq!(Square::E4.center_distance(), 0);
+        assert_eq!(Square::D5.center_distance(), 0);
+        assert_eq!(Square::C3.center_distance(), 1);
+        for corner in [Square::A1, Square::H1, Square::A8, Square::H8] {
+            assert_eq!(corner.center_distance(), 3);
+        }
+
+        assert_eq!(Square::A1.chebyshev_distance(Square::H8), 7);
+        assert_eq!(Square::A1.manhattan_distance(Square::H8), 14);
+        assert_eq!(Square::E1.chebyshev_distance(Square::G2), 2);
+        assert_eq!(Square::E1.manhattan_distance(Square::G2), 3);
+
+        for a in 0..64 {
+            for b in 0..64 {
+                let (a, b) = (Square(a), Square(b));
+                assert_eq!(a.chebyshev_distance(b), b.chebyshev_distance(a));
+                assert_eq!(a.manhattan_distance(b), b.manhattan_distance(a));
+                assert!(a.chebyshev_distance(b) <= a.manhattan_distance(b));
+            }
+        }
     }
 
     #[test]
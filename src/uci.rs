@@ -1,20 +1,90 @@
 /// UCI (Universal Chess Interface) protocol implementation
+use crate::debug_info;
+use crate::{ENGINE_AUTHOR, ENGINE_NAME, ENGINE_VERSION};
+use crate::eval::EvalParams;
 use crate::magic::init_magics;
+use crate::moves::Move;
+use crate::ordering::SearchHeuristics;
 use crate::position::Position;
+use crate::search::{search_threads, SearchInfo, SearchResult};
+use crate::timeman::{TimeBudget, DEFAULT_MOVE_OVERHEAD};
 use crate::tt::TranspositionTable;
 use crate::types::Color;
 use std::io::{self, BufRead, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// Global stop flag for search
 static STOP_FLAG: AtomicBool = AtomicBool::new(false);
 
+/// Default search depth for the "bench" command
+pub const BENCH_DEPTH: u8 = 8;
+
+/// Fixed positions searched by the "bench" command
+pub const BENCH_FENS: [&str; 20] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 10",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 11",
+    "4rrk1/pp1n3p/3q2pQ/2p1pb2/2PP4/2P3N1/P2B2PP/4RRK1 b - - 7 19",
+    "rq3rk1/ppp2ppp/1bnpb3/3N2B1/3NP3/7P/PPPQ1PP1/2KR3R w - - 7 14",
+    "r1bq1r1k/1pp1n1pp/1p1p4/4p2Q/4Pp2/1BNP4/PPP2PPP/3R1RK1 w - - 2 14",
+    "r3r1k1/2p2ppp/p1p1bn2/8/1q2P3/2NPQN2/PPP3PP/R4RK1 b - - 2 15",
+    "r1bbk1nr/pp3p1p/2n5/1N4p1/2Np1B2/8/PPP2PPP/2KR1B1R w kq - 0 13",
+    "r1bq1rk1/ppp1nppp/4n3/3p3Q/3P4/1BP1B3/PP1N2PP/R4RK1 w - - 1 16",
+    "4r1k1/r1q2ppp/ppp2n2/4P3/5Rb1/1N1BQ3/PPP3PP/R5K1 w - - 1 17",
+    "2rqkb1r/ppp2p2/2npb1p1/1N1Nn2p/2P1PP2/8/PP2B1PP/R1BQK2R b KQ - 0 11",
+    "r1bq1r1k/b1p1npp1/p2p3p/1p6/3PP3/1B2NN2/PP3PPP/R2Q1RK1 w - - 1 16",
+    "3r1rk1/p5pp/bpp1pp2/8/q1PP1P2/b3P3/P2NQRPP/1R2B1K1 b - - 6 22",
+    "r1q2rk1/2p1bppp/2Pp4/p6b/Q1PNp3/4B3/PP1R1PPP/2K4R w - - 2 18",
+    "4k2r/1pb2ppp/1p2p3/1R1p4/3P4/2r1PN2/P4PPP/1R4K1 b - - 3 22",
+    "3q2k1/pb3p1p/4pbp1/2r5/PpN2N2/1P2P2P/5PP1/Q2R2K1 b - - 4 26",
+    "6k1/6p1/6Pp/ppp5/3pn2P/1P3K2/1PP2P2/3N4 b - - 0 1",
+    "3b4/5kp1/1p1p1p1p/pP1PpP1P/P1P1P3/3KN3/8/8 w - - 0 1",
+    "8/8/8/8/5kp1/P7/8/1K1N4 w - - 0 1",
+    "8/3k4/8/8/8/4B3/4KB2/2B5 w - - 0 1",
+];
+
+/// Think time in milliseconds for a "go" with no clock, depth, movetime or infinite
+const DEFAULT_GO_MOVETIME: u64 = 1000;
+
+/// Eval weights exposed as spin options, as a percentage of their default
+const EVAL_WEIGHT_OPTIONS: [&str; 5] = [
+    "BishopPair",
+    "DoubledPawn",
+    "IsolatedPawn",
+    "PassedPawn",
+    "RookOpenFile",
+];
+
 /// UCI engine
 pub struct UciEngine {
     position: Position,
+    /// Position the current game started from
+    start_position: Position,
+    /// Moves played from the start position (for PGN export)
+    move_history: Vec<Move>,
+    /// Hashes of the positions before each played move, for repetition detection
+    game_history: Vec<u64>,
     tt: TranspositionTable,
     tt_size_mb: usize,
+    /// Move-ordering history carried from one search of the game to the next
+    heuristics: SearchHeuristics,
+    /// Draw avoidance in centipawns (Contempt option)
+    contempt: i16,
+    /// Evaluation weights (BishopPair, DoubledPawn, ... options)
+    eval_params: EvalParams,
+    /// Milliseconds reserved for GUI/network lag on every move (Move Overhead option)
+    move_overhead: u64,
+    /// Search threads (Threads option, clamped to what the search supports)
+    threads: usize,
+    /// Whether the GUI allows pondering (Ponder option)
+    ponder: bool,
+    /// Castling notation is king-takes-rook (UCI_Chess960 option)
+    chess960: bool,
+    /// Report win/draw/loss chances in info lines (UCI_ShowWDL option)
+    show_wdl: bool,
 }
 
 impl UciEngine {
@@ -25,61 +95,118 @@ impl UciEngine {
 
         UciEngine {
             position: Position::new(),
+            start_position: Position::new(),
+            move_history: Vec::new(),
+            game_history: Vec::new(),
             tt: TranspositionTable::new(64),
             tt_size_mb: 64,
+            heuristics: SearchHeuristics::new(),
+            contempt: 0,
+            eval_params: EvalParams::default(),
+            move_overhead: DEFAULT_MOVE_OVERHEAD,
+            threads: 1,
+            ponder: false,
+            chess960: false,
+            show_wdl: false,
         }
     }
 
     /// Run the UCI loop
     pub fn run(&mut self) {
-        let stdin = io::stdin();
         let mut stdout = io::stdout();
 
-        for line in stdin.lock().lines() {
-            let line = match line {
-                Ok(l) => l,
-                Err(_) => break,
-            };
-
+        for line in spawn_input_reader() {
             let tokens: Vec<&str> = line.split_whitespace().collect();
             if tokens.is_empty() {
                 continue;
             }
 
-            match tokens[0] {
-                "uci" => self.cmd_uci(&mut stdout),
-                "isready" => self.cmd_isready(&mut stdout),
-                "ucinewgame" => self.cmd_ucinewgame(),
-                "position" => self.cmd_position(&tokens[1..]),
-                "go" => self.cmd_go(&tokens[1..], &mut stdout),
-                "stop" => self.cmd_stop(),
-                "quit" => break,
-                "setoption" => self.cmd_setoption(&tokens[1..]),
-
-                // Non-standard commands for debugging
-                "d" | "display" => self.cmd_display(),
-                "perft" => self.cmd_perft(&tokens[1..]),
-                "eval" => self.cmd_eval(),
-
-                _ => {}
+            if tokens[0] == "quit" {
+                break;
+            }
+            if !self.execute(&tokens, &mut stdout) {
+                debug_info!(&mut stdout => "Unknown command: {}", line);
             }
         }
     }
 
+    /// Dispatch one command other than "quit"; false if the command is unknown
+    fn execute(&mut self, tokens: &[&str], stdout: &mut io::Stdout) -> bool {
+        match tokens[0] {
+            "uci" => self.cmd_uci(stdout),
+            "debug" => self.cmd_debug(&tokens[1..]),
+            "isready" => self.cmd_isready(stdout),
+            "ucinewgame" => self.cmd_ucinewgame(),
+            "position" => self.cmd_position(&tokens[1..], stdout),
+            "go" => self.cmd_go(&tokens[1..], stdout),
+            "stop" => self.cmd_stop(),
+            "setoption" => self.cmd_setoption(&tokens[1..]),
+            // Kai is free: "register later" and "register name ... code ..." need no reply
+            "register" => {}
+
+            // Non-standard commands for debugging
+            "d" | "display" => self.cmd_display(),
+            "perft" => self.cmd_perft(&tokens[1..]),
+            "bench" => self.cmd_bench(&tokens[1..]),
+            "pgn" => self.cmd_pgn(stdout),
+            "eval" => self.cmd_eval(),
+
+            _ => return false,
+        }
+        true
+    }
+
     /// Handle "uci" command
-    fn cmd_uci(&self, stdout: &mut io::Stdout) {
-        writeln!(stdout, "id name Kai 1.0").unwrap();
-        writeln!(stdout, "id author Sahith Jagarlamudi").unwrap();
+    pub fn cmd_uci(&self, stdout: &mut dyn Write) {
+        writeln!(stdout, "id name {} {}", ENGINE_NAME, ENGINE_VERSION).unwrap();
+        writeln!(stdout, "id author {}", ENGINE_AUTHOR).unwrap();
         writeln!(stdout).unwrap();
         writeln!(
             stdout,
             "option name Hash type spin default 64 min 1 max 4096"
         )
         .unwrap();
+        writeln!(stdout, "option name Clear Hash type button").unwrap();
+        writeln!(
+            stdout,
+            "option name Threads type spin default 1 min 1 max 512"
+        )
+        .unwrap();
+        writeln!(stdout, "option name Ponder type check default false").unwrap();
+        writeln!(stdout, "option name UCI_Chess960 type check default false").unwrap();
+        writeln!(stdout, "option name UCI_ShowWDL type check default false").unwrap();
+        writeln!(
+            stdout,
+            "option name Contempt type spin default 0 min -100 max 100"
+        )
+        .unwrap();
+        writeln!(
+            stdout,
+            "option name Move Overhead type spin default {} min 0 max 5000",
+            DEFAULT_MOVE_OVERHEAD
+        )
+        .unwrap();
+        for name in EVAL_WEIGHT_OPTIONS {
+            writeln!(
+                stdout,
+                "option name {} type spin default 100 min 0 max 400",
+                name
+            )
+            .unwrap();
+        }
         writeln!(stdout, "uciok").unwrap();
         stdout.flush().unwrap();
     }
 
+    /// Handle "debug" command
+    fn cmd_debug(&self, tokens: &[&str]) {
+        match tokens.first() {
+            Some(&"on") => crate::debug::set_debug(true),
+            Some(&"off") => crate::debug::set_debug(false),
+            _ => {}
+        }
+    }
+
     /// Handle "isready" command
     fn cmd_isready(&self, stdout: &mut io::Stdout) {
         writeln!(stdout, "readyok").unwrap();
@@ -89,11 +216,15 @@ impl UciEngine {
     /// Handle "ucinewgame" command
     fn cmd_ucinewgame(&mut self) {
         self.position = Position::new();
+        self.start_position = Position::new();
+        self.move_history.clear();
+        self.game_history.clear();
         self.tt.clear();
+        self.heuristics.clear();
     }
 
     /// Handle "position" command
-    fn cmd_position(&mut self, tokens: &[&str]) {
+    fn cmd_position(&mut self, tokens: &[&str], stdout: &mut dyn Write) {
         if tokens.is_empty() {
             return;
         }
@@ -112,24 +243,38 @@ impl UciEngine {
                 .copied()
                 .collect();
             let fen = fen_parts.join(" ");
-            self.position = Position::from_fen(&fen).unwrap_or_else(|_| Position::new());
+            match Position::from_fen(&fen) {
+                Ok(position) => self.position = position,
+                Err(err) => {
+                    // Keep the previous position rather than guess at the GUI's intent
+                    writeln!(stdout, "info string Invalid FEN ({}): {}", err, fen).unwrap();
+                    stdout.flush().unwrap();
+                    return;
+                }
+            }
             idx += fen_parts.len();
         }
 
+        self.start_position = self.position.clone();
+        self.move_history.clear();
+        self.game_history.clear();
+
         // Parse moves
         if idx < tokens.len() && tokens[idx] == "moves" {
             idx += 1;
             for move_str in &tokens[idx..] {
-                if let Some(new_pos) = self.position.make_uci_move(move_str) {
-                    self.position = new_pos;
+                if let Some(mv) = self.position.parse_uci_move(move_str) {
+                    self.game_history.push(self.position.hash);
+                    self.position = self.position.make_move(mv);
+                    self.move_history.push(mv);
                 }
             }
         }
     }
 
     /// Handle "go" command
-    fn cmd_go(&mut self, tokens: &[&str], stdout: &mut io::Stdout) {
-        let mut time_limit = None;
+    fn cmd_go(&mut self, tokens: &[&str], stdout: &mut dyn Write) {
+        let mut budget = None;
         let mut depth_limit = None;
         let mut wtime = None;
         let mut btime = None;
@@ -214,9 +359,9 @@ impl UciEngine {
             }
         }
 
-        // Calculate time limit
+        // Calculate soft and hard time limits
         if let Some(mt) = movetime {
-            time_limit = Some(Duration::from_millis(mt));
+            budget = Some(TimeBudget::fixed(mt, self.move_overhead));
         } else if !infinite {
             let (our_time, our_inc) = match self.position.side_to_move {
                 Color::White => (wtime, winc),
@@ -224,45 +369,76 @@ impl UciEngine {
             };
 
             if let Some(time) = our_time {
-                let inc = our_inc.unwrap_or(0);
-                let moves = movestogo.unwrap_or(30) as u64;
-
-                // Simple time management: use time/moves + most of increment
-                let base = time / moves.max(1);
-                let total = base + (inc * 3) / 4;
-
-                // Keep some buffer
-                let limit = total.min(time.saturating_sub(100));
-                time_limit = Some(Duration::from_millis(limit));
+                budget = Some(self.clock_budget(time, our_inc.unwrap_or(0), movestogo));
+            } else if depth_limit.is_none() {
+                // Nothing bounds the search, so think briefly rather than forever
+                budget = Some(TimeBudget::fixed(DEFAULT_GO_MOVETIME, self.move_overhead));
             }
         }
 
-        // Reset stop flag
-        STOP_FLAG.store(false, Ordering::SeqCst);
-
-        // Run search
+        // Run search; the input reader cleared STOP_FLAG when it read this "go"
         // STOP_FLAG is a static, so &STOP_FLAG already has 'static lifetime - no transmute needed
-        let result = self
-            .position
-            .search(&mut self.tt, time_limit, depth_limit, Some(&STOP_FLAG));
+        let mut info = SearchInfo::new(Instant::now());
+        if let Some(budget) = budget {
+            info.set_time_budget(budget);
+        }
+        info.depth_limit = depth_limit;
+        info.stop_flag = Some(&STOP_FLAG);
+        info.contempt = self.contempt;
+        info.eval_params = self.eval_params.clone();
+        info.history = self.game_history.clone();
+        info.chess960 = self.chess960;
+        info.show_wdl = self.show_wdl;
+        info.heuristics = std::mem::take(&mut self.heuristics);
+        let result = self.position.search_with_output(&mut self.tt, &mut info, stdout);
+        self.heuristics = std::mem::take(&mut info.heuristics);
+
+        // An infinite search may end early (mate found, or MAX_PLY reached), but
+        // "bestmove" must wait for the GUI's "stop"
+        while infinite && !STOP_FLAG.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(1));
+        }
 
         // Log bestmove for debugging
-        eprintln!(
-            "BESTMOVE: {} for side {:?}",
+        debug_info!(
+            stdout => "BESTMOVE: {} for side {:?}",
             result.best_move.to_uci(),
             self.position.side_to_move
         );
         if let Some(piece) = self.position.piece_at(result.best_move.from_sq()) {
-            eprintln!("  Piece at source: {:?}", piece);
+            debug_info!(stdout => "  Piece at source: {:?}", piece);
         } else {
-            eprintln!("  WARNING: No piece at source square!");
+            debug_info!(stdout => "  WARNING: No piece at source square!");
         }
 
         // Output best move
-        writeln!(stdout, "bestmove {}", result.best_move.to_uci()).unwrap();
+        writeln!(stdout, "{}", self.bestmove_line(&result)).unwrap();
         stdout.flush().unwrap();
     }
 
+    /// Format "bestmove", with the PV reply as the ponder move when pondering is enabled
+    fn bestmove_line(&self, result: &SearchResult) -> String {
+        let best = result.best_move.to_uci_with(self.chess960);
+        let ponder = match result.pv.as_slice() {
+            [first, second, ..] if self.ponder && *first == result.best_move => {
+                // The PV may come from the TT, so only suggest a reply that is legal
+                let after = self.position.make_move(*first);
+                after.legal_moves().contains(second).then_some(*second)
+            }
+            _ => None,
+        };
+
+        match ponder {
+            Some(mv) => format!("bestmove {} ponder {}", best, mv.to_uci_with(self.chess960)),
+            None => format!("bestmove {}", best),
+        }
+    }
+
+    /// Soft and hard limits for the remaining clock, keeping the Move Overhead in reserve
+    fn clock_budget(&self, time: u64, inc: u64, movestogo: Option<u32>) -> TimeBudget {
+        TimeBudget::from_clock(time, inc, movestogo, self.move_overhead)
+    }
+
     /// Handle "stop" command
     fn cmd_stop(&self) {
         STOP_FLAG.store(true, Ordering::SeqCst);
@@ -270,34 +446,81 @@ impl UciEngine {
 
     /// Handle "setoption" command
     fn cmd_setoption(&mut self, tokens: &[&str]) {
-        if tokens.len() < 4 {
+        if tokens.len() < 2 || tokens[0] != "name" {
             return;
         }
 
-        if tokens[0] != "name" {
+        // Buttons have no "value" token, so the name runs to the end
+        let value_idx = tokens.iter().position(|&t| t == "value");
+        let name_end = value_idx.unwrap_or(tokens.len());
+        let name = tokens[1..name_end].join(" ").to_lowercase();
+
+        if name == "clear hash" {
+            self.tt.clear();
             return;
         }
 
-        // Find "value" token
-        let value_idx = tokens.iter().position(|&t| t == "value");
-        if value_idx.is_none() {
+        let Some(value_idx) = value_idx else {
             return;
-        }
+        };
+        let value = tokens[value_idx + 1..].join(" ");
 
-        let name_parts = &tokens[1..value_idx.unwrap()];
-        let name = name_parts.join(" ").to_lowercase();
-        let value = tokens[value_idx.unwrap() + 1..].join(" ");
+        if name == "hash" {
+            if let Ok(size) = value.parse::<usize>() {
+                let size = size.clamp(1, 4096);
+                self.tt_size_mb = size;
+                self.tt.resize(size);
+            }
+        } else if name == "threads" {
+            if let Ok(threads) = value.parse::<usize>() {
+                self.threads = search_threads(threads);
+            }
+        } else if name == "ponder" {
+            if let Ok(ponder) = value.parse::<bool>() {
+                self.ponder = ponder;
+            }
+        } else if name == "uci_chess960" {
+            if let Ok(chess960) = value.parse::<bool>() {
+                self.chess960 = chess960;
+            }
+        } else if name == "uci_showwdl" {
+            if let Ok(show_wdl) = value.parse::<bool>() {
+                self.show_wdl = show_wdl;
+            }
+        } else if name == "contempt" {
+            if let Ok(contempt) = value.parse::<i16>() {
+                self.contempt = contempt.clamp(-100, 100);
+            }
+        } else if name == "move overhead" {
+            if let Ok(overhead) = value.parse::<u64>() {
+                self.move_overhead = overhead.min(5000);
+            }
+        } else if let Ok(percent) = value.parse::<i16>() {
+            self.set_eval_weight(&name, percent.clamp(0, 400));
+        }
+    }
 
-        match name.as_str() {
-            "hash" => {
-                if let Ok(size) = value.parse::<usize>() {
-                    let size = size.clamp(1, 4096);
-                    self.tt_size_mb = size;
-                    self.tt.resize(size);
+    /// Scale an eval weight to `percent` of its default, returning false for unknown names
+    fn set_eval_weight(&mut self, name: &str, percent: i16) -> bool {
+        let default = &EvalParams::DEFAULT;
+        let params = &mut self.eval_params;
+
+        match name {
+            "bishoppair" => params.bishop_pair = default.bishop_pair.scaled(percent),
+            "doubledpawn" => params.doubled_pawn = default.doubled_pawn.scaled(percent),
+            "isolatedpawn" => params.isolated_pawn = default.isolated_pawn.scaled(percent),
+            "passedpawn" => {
+                for (bonus, default) in params.passed_pawn.iter_mut().zip(default.passed_pawn) {
+                    *bonus = default.scaled(percent);
                 }
             }
-            _ => {}
+            "rookopenfile" => {
+                params.rook_open_file = default.rook_open_file.scaled(percent);
+                params.rook_semi_open_file = default.rook_semi_open_file.scaled(percent);
+            }
+            _ => return false,
         }
+        true
     }
 
     /// Handle "d" (display) command
@@ -318,12 +541,12 @@ impl UciEngine {
     }
 
     /// Run perft with divide output
-    fn run_perft(&self, depth: u32, stdout: &mut io::Stdout) {
-        use crate::perft::perft_divide;
+    fn run_perft(&self, depth: u32, stdout: &mut dyn Write) {
+        use crate::perft::print_divide;
         use std::time::Instant;
 
         let start = Instant::now();
-        let nodes = perft_divide(&self.position, depth);
+        let nodes = print_divide(&self.position, depth, stdout);
         let elapsed = start.elapsed();
 
         let nps = if elapsed.as_millis() > 0 {
@@ -339,13 +562,100 @@ impl UciEngine {
         stdout.flush().unwrap();
     }
 
+    /// Handle "bench" command
+    pub fn cmd_bench(&mut self, tokens: &[&str]) {
+        let depth = tokens
+            .first()
+            .and_then(|t| t.parse::<u8>().ok())
+            .unwrap_or(BENCH_DEPTH);
+        let mut stdout = io::stdout();
+        self.run_bench(depth, &mut stdout);
+    }
+
+    /// Search every bench position to a fixed depth and report total nodes
+    fn run_bench<W: Write>(&mut self, depth: u8, out: &mut W) -> u64 {
+        use std::time::Instant;
+
+        let start = Instant::now();
+        let mut total_nodes = 0u64;
+
+        for (i, fen) in BENCH_FENS.iter().enumerate() {
+            let pos = Position::from_fen(fen).expect("Invalid bench FEN");
+            writeln!(out, "Position {}/{}: {}", i + 1, BENCH_FENS.len(), fen).unwrap();
+
+            // Clears the TT and ignores the clock, so node counts are reproducible
+            let result = pos.search_deterministic_with_output(&mut self.tt, depth, out);
+            total_nodes += result.nodes;
+        }
+        self.tt.clear();
+
+        let elapsed = start.elapsed();
+        let nps = if elapsed.as_millis() > 0 {
+            (total_nodes as u128 * 1000) / elapsed.as_millis()
+        } else {
+            0
+        };
+
+        writeln!(out).unwrap();
+        writeln!(out, "Nodes: {}", total_nodes).unwrap();
+        writeln!(out, "Time: {} ms", elapsed.as_millis()).unwrap();
+        writeln!(out, "NPS: {}", nps).unwrap();
+        out.flush().unwrap();
+
+        total_nodes
+    }
+
+    /// Handle "pgn" command - dump the current game as PGN
+    fn cmd_pgn(&self, stdout: &mut io::Stdout) {
+        let tags = [("Event".to_string(), "Kai UCI game".to_string())];
+        let pgn = crate::pgn::to_pgn(&self.move_history, &self.start_position, &tags);
+        write!(stdout, "{}", pgn).unwrap();
+        stdout.flush().unwrap();
+    }
+
     /// Handle "eval" command
     fn cmd_eval(&self) {
-        let score = self.position.evaluate();
+        use crate::eval::{SCALE_NORMAL, TOTAL_PHASE};
+
+        let detailed = self.position.evaluate_detailed_with(&self.eval_params);
+        println!("{:<12} {:>6} {:>6}", "Term", "MG", "EG");
+        for (name, score) in detailed.terms() {
+            println!("{:<12} {:>6} {:>6}", name, score.mg, score.eg);
+        }
+        let total = detailed.total();
+        println!("{:<12} {:>6} {:>6}", "Total", total.mg, total.eg);
+        println!("Phase: {}/{}", detailed.phase, TOTAL_PHASE);
+        println!("Scale: {}/{}", detailed.scale, SCALE_NORMAL);
+        println!("Tapered (white): {} cp", detailed.tapered());
+
+        let score = self.position.evaluate_with(&self.eval_params);
         println!("Evaluation: {} cp", score);
     }
 }
 
+/// Read stdin on its own thread so "stop" and "quit" reach a running search
+///
+/// The search runs on the command loop's thread, so the reader sets STOP_FLAG
+/// itself. It also clears the flag on "go", in input order, so a "stop" sent
+/// right behind "go" cannot be lost.
+fn spawn_input_reader() -> Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            match line.split_whitespace().next() {
+                Some("go") => STOP_FLAG.store(false, Ordering::SeqCst),
+                Some("stop" | "quit") => STOP_FLAG.store(true, Ordering::SeqCst),
+                _ => {}
+            }
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
 impl Default for UciEngine {
     fn default() -> Self {
         Self::new()
@@ -355,21 +665,38 @@ impl Default for UciEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::search::MAX_SEARCH_THREADS;
+    use crate::tt::Bound;
+    use std::time::Duration;
 
     #[test]
     fn test_uci_engine_creation() {
         let _engine = UciEngine::new();
     }
 
+    #[test]
+    fn test_uci_identifies_engine() {
+        let engine = UciEngine::new();
+        let mut out = Vec::new();
+        engine.cmd_uci(&mut out);
+        let output = String::from_utf8(out).unwrap();
+
+        let id_name = output.lines().next().unwrap();
+        assert!(id_name.starts_with("id name ") && id_name.contains(ENGINE_NAME));
+        assert!(output.contains(&format!("id author {}", ENGINE_AUTHOR)));
+        assert!(output.ends_with("uciok\n"));
+    }
+
     #[test]
     fn test_position_parsing() {
         let mut engine = UciEngine::new();
 
-        engine.cmd_position(&["startpos"]);
+        engine.cmd_position(&["startpos"], &mut io::sink());
         assert_eq!(engine.position.to_fen(), Position::STARTPOS);
 
-        engine.cmd_position(&["startpos", "moves", "e2e4"]);
+        engine.cmd_position(&["startpos", "moves", "e2e4"], &mut io::sink());
         assert_eq!(engine.position.side_to_move, Color::Black);
+        assert_eq!(engine.move_history.len(), 1);
 
         engine.cmd_position(&[
             "fen",
@@ -379,10 +706,249 @@ mod tests {
             "-",
             "0",
             "1",
-        ]);
+        ], &mut io::sink());
         assert!(engine
             .position
             .castling
             .contains(crate::types::CastlingRights::ALL));
     }
+
+    #[test]
+    fn test_invalid_fen_keeps_position() {
+        let mut engine = UciEngine::new();
+        engine.cmd_position(&["startpos", "moves", "e2e4"], &mut io::sink());
+        let before = engine.position.to_fen();
+
+        let mut out = Vec::new();
+        let bad_fen = ["fen", "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX", "w", "-", "-", "0", "1"];
+        engine.cmd_position(&bad_fen, &mut out);
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.starts_with("info string Invalid FEN"), "{}", output);
+        assert!(output.contains("'X'"), "{}", output);
+        assert_eq!(engine.position.to_fen(), before);
+        assert_eq!(engine.move_history.len(), 1);
+    }
+
+    #[test]
+    fn test_position_records_game_history() {
+        let mut engine = UciEngine::new();
+        let start = Position::new().hash;
+
+        engine.cmd_position(&["startpos", "moves", "g1f3", "g8f6", "f3g1", "f6g8", "g1f3"], &mut io::sink());
+        assert_eq!(engine.game_history.len(), 5);
+        assert_eq!(engine.game_history[0], start);
+        assert_eq!(engine.game_history[4], start);
+        assert!(engine.position.is_repetition(&engine.game_history, 2));
+
+        // A new position command starts a fresh history
+        engine.cmd_position(&["startpos"], &mut io::sink());
+        assert!(engine.game_history.is_empty());
+    }
+
+    #[test]
+    fn test_setoption_contempt() {
+        let mut engine = UciEngine::new();
+
+        engine.cmd_setoption(&["name", "Contempt", "value", "25"]);
+        assert_eq!(engine.contempt, 25);
+
+        engine.cmd_setoption(&["name", "Contempt", "value", "500"]);
+        assert_eq!(engine.contempt, 100);
+    }
+
+    #[test]
+    fn test_threads_and_ponder_options() {
+        let mut engine = UciEngine::new();
+        engine.cmd_setoption(&["name", "Threads", "value", "4"]);
+        assert_eq!(engine.threads, 4.min(MAX_SEARCH_THREADS));
+        engine.cmd_setoption(&["name", "Threads", "value", "0"]);
+        assert_eq!(engine.threads, 1);
+
+        engine.cmd_setoption(&["name", "Ponder", "value", "true"]);
+        assert!(engine.ponder);
+        engine.cmd_setoption(&["name", "Ponder", "value", "false"]);
+        assert!(!engine.ponder);
+    }
+
+    #[test]
+    fn test_chess960_castling_notation() {
+        let mut engine = UciEngine::new();
+        let fen = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
+        let castle = Position::from_fen(fen).unwrap().parse_uci_move("e1g1").unwrap();
+        assert!(castle.is_castle());
+        let result = SearchResult {
+            best_move: castle,
+            score: 0,
+            depth: 1,
+            nodes: 0,
+            pv: vec![castle],
+        };
+        assert_eq!(engine.bestmove_line(&result), "bestmove e1g1");
+
+        engine.cmd_setoption(&["name", "UCI_Chess960", "value", "true"]);
+        assert_eq!(engine.bestmove_line(&result), "bestmove e1h1");
+
+        // King-takes-rook input castles in Chess960, here with the king staying on g1
+        engine.cmd_position(&[
+            "fen",
+            "1r4kr/8/8/8/8/8/8/1R4KR",
+            "w",
+            "HBhb",
+            "-",
+            "0",
+            "1",
+            "moves",
+            "g1h1",
+        ], &mut io::sink());
+        assert_eq!(engine.position.to_fen(), "1r4kr/8/8/8/8/8/8/1R3RK1 b hb - 1 1");
+    }
+
+    #[test]
+    fn test_move_overhead_reduces_budget() {
+        let mut engine = UciEngine::new();
+        engine.cmd_setoption(&["name", "Move", "Overhead", "value", "0"]);
+        let base = engine.clock_budget(30_000, 0, Some(10));
+
+        engine.cmd_setoption(&["name", "Move", "Overhead", "value", "250"]);
+        assert_eq!(engine.move_overhead, 250);
+        let lagged = engine.clock_budget(30_000, 0, Some(10));
+        assert_eq!(base.soft.unwrap() - lagged.soft.unwrap(), Duration::from_millis(250));
+
+        engine.cmd_setoption(&["name", "Move", "Overhead", "value", "99999"]);
+        assert_eq!(engine.move_overhead, 5000);
+    }
+
+    #[test]
+    fn test_bestmove_ponder_suffix() {
+        let mut engine = UciEngine::new();
+        engine.cmd_setoption(&["name", "Ponder", "value", "true"]);
+        let e2e4 = engine.position.parse_uci_move("e2e4").unwrap();
+        let e7e5 = engine.position.make_move(e2e4).parse_uci_move("e7e5").unwrap();
+        let result = |pv: Vec<Move>| SearchResult {
+            best_move: e2e4,
+            score: 0,
+            depth: 2,
+            nodes: 0,
+            pv,
+        };
+
+        assert_eq!(engine.bestmove_line(&result(vec![e2e4, e7e5])), "bestmove e2e4 ponder e7e5");
+        assert_eq!(engine.bestmove_line(&result(vec![e2e4])), "bestmove e2e4");
+
+        // An illegal reply is dropped rather than sent to the GUI
+        assert_eq!(engine.bestmove_line(&result(vec![e2e4, e2e4])), "bestmove e2e4");
+
+        engine.cmd_setoption(&["name", "Ponder", "value", "false"]);
+        assert_eq!(engine.bestmove_line(&result(vec![e2e4, e7e5])), "bestmove e2e4");
+    }
+
+    #[test]
+    fn test_go_writes_to_injected_writer() {
+        let mut engine = UciEngine::new();
+        let mut out = Vec::new();
+        engine.cmd_go(&["depth", "2"], &mut out);
+
+        let output = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("info depth 1 "));
+        assert!(lines.iter().any(|l| l.starts_with("info depth 2 ")));
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn test_bare_go_returns_move() {
+        let mut engine = UciEngine::new();
+        engine.cmd_position(&["startpos", "moves", "e2e4"], &mut io::sink());
+
+        let start = Instant::now();
+        let mut out = Vec::new();
+        engine.cmd_go(&[], &mut out);
+        assert!(start.elapsed() < Duration::from_millis(DEFAULT_GO_MOVETIME * 3));
+
+        let output = String::from_utf8(out).unwrap();
+        let bestmove = output.lines().last().unwrap().strip_prefix("bestmove ").unwrap();
+        let mv = bestmove.split_whitespace().next().unwrap();
+        assert!(engine.position.parse_uci_move(mv).is_some(), "illegal bestmove {}", mv);
+    }
+
+    #[test]
+    fn test_show_wdl_option() {
+        let mut engine = UciEngine::new();
+        engine.cmd_setoption(&["name", "UCI_ShowWDL", "value", "true"]);
+
+        let mut out = Vec::new();
+        engine.cmd_go(&["depth", "1"], &mut out);
+        let output = String::from_utf8(out).unwrap();
+        let info = output.lines().next().unwrap();
+        assert!(info.contains(" wdl "), "{}", info);
+    }
+
+    #[test]
+    fn test_ucinewgame_resets_game_state() {
+        let mut engine = UciEngine::new();
+        engine.cmd_position(&["startpos", "moves", "e2e4", "e7e5", "g1f3"], &mut io::sink());
+        engine.cmd_go(&["depth", "4"], &mut Vec::new());
+        assert!(!engine.game_history.is_empty());
+        assert!(engine.heuristics.history.iter().flatten().flatten().any(|&h| h != 0));
+
+        engine.cmd_ucinewgame();
+        assert!(engine.game_history.is_empty());
+        assert!(engine.move_history.is_empty());
+        assert!(engine.heuristics.history.iter().flatten().flatten().all(|&h| h == 0));
+        assert!(engine.heuristics.killers.iter().flatten().all(|mv| mv.is_null()));
+        assert!(engine.heuristics.countermoves.iter().flatten().all(|mv| mv.is_null()));
+        assert_eq!(engine.tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn test_register_is_accepted() {
+        let mut engine = UciEngine::new();
+        let mut stdout = io::stdout();
+        assert!(engine.execute(&["register", "later"], &mut stdout));
+        assert!(engine.execute(&["register", "name", "Kai", "code", "1234"], &mut stdout));
+        assert!(!engine.execute(&["registr"], &mut stdout));
+    }
+
+    #[test]
+    fn test_clear_hash_button() {
+        let mut engine = UciEngine::new();
+        for hash in 0..1000 {
+            engine.tt.store(hash, 4, 25, Bound::Exact, Move::NULL, 0);
+        }
+        assert!(engine.tt.hashfull() > 0);
+
+        engine.cmd_setoption(&["name", "Clear", "Hash"]);
+        assert_eq!(engine.tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn test_setoption_bishop_pair() {
+        let mut engine = UciEngine::new();
+        engine.cmd_position(&["fen", "1nn1k2r/8/8/3q4/8/8/8/2B1KB1R", "w", "-", "-", "0", "1"], &mut io::sink());
+        let before = engine.position.evaluate_with(&engine.eval_params);
+
+        engine.cmd_setoption(&["name", "BishopPair", "value", "0"]);
+        let after = engine.position.evaluate_with(&engine.eval_params);
+
+        // Phase 12 of 24: (30 * 12 + 40 * 12) / 24
+        assert_eq!(before - after, 35);
+
+        engine.cmd_setoption(&["name", "BishopPair", "value", "100"]);
+        assert_eq!(engine.position.evaluate_with(&engine.eval_params), before);
+    }
+
+    #[test]
+    fn test_bench() {
+        let mut engine = UciEngine::new();
+        let mut out = Vec::new();
+
+        let nodes = engine.run_bench(2, &mut out);
+        assert!(nodes > 0);
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("info depth 2"));
+        assert!(text.contains(&format!("Nodes: {}", nodes)));
+
+        // Cleared TT per position makes the count reproducible
+        assert_eq!(engine.run_bench(2, &mut io::sink()), nodes);
+    }
 }
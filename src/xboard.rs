@@ -1,24 +1,30 @@
-/// XBoard/WinBoard protocol implementation
-///
-/// This module implements the XBoard (also known as WinBoard or CECP - Chess Engine
-/// Communication Protocol) for compatibility with XBoard-based chess GUIs.
-///
-/// Reference: https://www.gnu.org/software/xboard/engine-intf.html
+//! XBoard/WinBoard protocol implementation
+//!
+//! This module implements the XBoard (also known as WinBoard or CECP - Chess Engine
+//! Communication Protocol) for compatibility with XBoard-based chess GUIs.
+//!
+//! Reference: https://www.gnu.org/software/xboard/engine-intf.html
 
 use crate::magic::init_magics;
 use crate::moves::Move;
+use crate::ordering::SearchHeuristics;
 use crate::position::Position;
+use crate::search::{search_threads, SearchInfo, SearchProgress, SharedProgress, MAX_SEARCH_THREADS};
+use crate::timeman::{TimeBudget, DEFAULT_MOVE_OVERHEAD};
 use crate::tt::TranspositionTable;
 use crate::types::Color;
+use crate::{ENGINE_NAME, ENGINE_VERSION};
 use std::io::{self, BufRead, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// Global stop flag for search
 static STOP_FLAG: AtomicBool = AtomicBool::new(false);
 
-/// XBoard protocol version we support
-const PROTOCOL_VERSION: u32 = 2;
+/// Set while an analysis search runs, so the input reader stops it on any command
+static ANALYZING: AtomicBool = AtomicBool::new(false);
 
 /// XBoard engine state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -31,6 +37,76 @@ pub enum EngineMode {
     Analyze,
 }
 
+/// Adjudication options as XBoard "feature option" descriptors (all off by default)
+pub const ADJUDICATION_OPTIONS: [&str; 4] = [
+    "Adjudicate Draw Score -spin 0 0 100",
+    "Adjudicate Draw Moves -spin 0 0 200",
+    "Adjudicate Resign Score -spin 0 0 10000",
+    "Adjudicate Resign Moves -spin 0 0 200",
+];
+
+/// Draw offers are only adjudicated at or below this game phase (24 = full material)
+const ADJUDICATION_MAX_PHASE: i32 = 8;
+
+/// Megabytes of the "memory" budget kept for everything but the hash table: attack
+/// tables, move ordering heuristics, evaluation caches and stacks
+const NON_HASH_MEMORY_MB: usize = 4;
+
+/// Outcome of feeding one search score to the adjudicator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verdict {
+    Continue,
+    OfferDraw,
+    Resign,
+}
+
+/// Score-based draw and resign adjudication, to shorten engine-vs-engine matches
+///
+/// A move count of 0 turns the corresponding rule off (the default).
+#[derive(Debug, Clone, Copy, Default)]
+struct Adjudicator {
+    /// Offer a draw once |score| stays within this many centipawns...
+    draw_score: i16,
+    /// ...for this many consecutive moves with little material left
+    draw_moves: u32,
+    /// Resign once the score stays at or below minus this many centipawns...
+    resign_score: i16,
+    /// ...for this many consecutive moves
+    resign_moves: u32,
+    draw_count: u32,
+    resign_count: u32,
+}
+
+impl Adjudicator {
+    /// Count one more search score (from the engine's point of view)
+    fn update(&mut self, score: i16, low_material: bool) -> Verdict {
+        self.draw_count = if low_material && score.abs() <= self.draw_score {
+            self.draw_count + 1
+        } else {
+            0
+        };
+        self.resign_count = if score <= -self.resign_score {
+            self.resign_count + 1
+        } else {
+            0
+        };
+
+        if self.resign_moves > 0 && self.resign_count >= self.resign_moves {
+            Verdict::Resign
+        } else if self.draw_moves > 0 && self.draw_count >= self.draw_moves {
+            Verdict::OfferDraw
+        } else {
+            Verdict::Continue
+        }
+    }
+
+    /// Forget the scores of the previous game
+    fn reset(&mut self) {
+        self.draw_count = 0;
+        self.resign_count = 0;
+    }
+}
+
 /// XBoard engine
 pub struct XBoardEngine {
     position: Position,
@@ -52,6 +128,16 @@ pub struct XBoardEngine {
     game_history: Vec<u64>,
     /// Computer's color
     computer_color: Color,
+    /// Milliseconds kept in reserve for interface lag ("Move Overhead" option)
+    move_overhead: u64,
+    /// Draw offers and resignation from the search score (Adjudicate options)
+    adjudicator: Adjudicator,
+    /// Progress of the running (or last) analysis search, for "stat01" reports
+    analysis_progress: SharedProgress,
+    /// Move ordering tables kept across the searches of a game
+    heuristics: SearchHeuristics,
+    /// Search threads ("cores" command, clamped to what the search supports)
+    threads: usize,
 }
 
 impl XBoardEngine {
@@ -74,21 +160,46 @@ impl XBoardEngine {
             ponder: false,
             game_history: Vec::new(),
             computer_color: Color::Black,
+            move_overhead: DEFAULT_MOVE_OVERHEAD,
+            adjudicator: Adjudicator::default(),
+            analysis_progress: SharedProgress::default(),
+            heuristics: SearchHeuristics::new(),
+            threads: 1,
         }
     }
 
     /// Run the XBoard protocol loop
     pub fn run(&mut self) {
-        let stdin = io::stdin();
         let mut stdout = io::stdout();
+        let input = spawn_input_reader(SharedProgress::clone(&self.analysis_progress));
+        let mut pending = None;
+        // Set by every command, as each may change what analyze mode should search
+        let mut analyze_pending = true;
 
         // XBoard sends "xboard" first, but we might have already consumed it in main
         // Just start accepting commands
 
-        for line in stdin.lock().lines() {
-            let line = match line {
-                Ok(l) => l,
-                Err(_) => break,
+        loop {
+            // In analyze mode, search until the next command arrives: the input reader
+            // stops the search, and the loop restarts it once the command is handled
+            if self.mode == EngineMode::Analyze && analyze_pending {
+                analyze_pending = false;
+                ANALYZING.store(true, Ordering::SeqCst);
+                STOP_FLAG.store(false, Ordering::SeqCst);
+                match input.try_recv() {
+                    Ok(line) => pending = Some(line),
+                    Err(TryRecvError::Empty) => self.analyze_position(&mut stdout),
+                    Err(TryRecvError::Disconnected) => break,
+                }
+                ANALYZING.store(false, Ordering::SeqCst);
+            }
+
+            let line = match pending.take() {
+                Some(line) => line,
+                None => match input.recv() {
+                    Ok(line) => line,
+                    Err(_) => break,
+                },
             };
 
             let line = line.trim();
@@ -103,6 +214,7 @@ impl XBoardEngine {
             }
 
             let cmd = tokens[0];
+            analyze_pending |= cmd != ".";
 
             match cmd {
                 "xboard" => {
@@ -110,7 +222,7 @@ impl XBoardEngine {
                     writeln!(stdout).unwrap();
                     stdout.flush().unwrap();
                 }
-                "protover" => self.cmd_protover(&tokens[1..], &mut stdout),
+                "protover" => self.cmd_protover(&mut stdout),
                 "accepted" | "rejected" => {
                     // Ignore feature acceptance/rejection
                 }
@@ -131,8 +243,8 @@ impl XBoardEngine {
                 "ping" => self.cmd_ping(&tokens[1..], &mut stdout),
                 "draw" => self.cmd_draw(&mut stdout),
                 "result" => self.cmd_result(&tokens[1..]),
-                "setboard" => self.cmd_setboard(&tokens[1..]),
-                "edit" => self.cmd_edit_mode(&stdin, &mut stdout),
+                "setboard" => self.cmd_setboard(&tokens[1..], &mut stdout),
+                "edit" => self.cmd_edit_mode(&input, &mut stdout),
                 "hint" => self.cmd_hint(&mut stdout),
                 "bk" => self.cmd_bk(&mut stdout),
                 "undo" => self.cmd_undo(),
@@ -141,7 +253,7 @@ impl XBoardEngine {
                 "easy" => self.ponder = false,
                 "post" => self.post = true,
                 "nopost" => self.post = false,
-                "analyze" => self.cmd_analyze(&mut stdout),
+                "analyze" => self.cmd_analyze(),
                 "exit" => self.cmd_exit_analyze(),
                 "." => self.cmd_analyze_status(&mut stdout),
                 "computer" => {
@@ -157,15 +269,11 @@ impl XBoardEngine {
                     // Playing on ICS
                 }
                 "memory" => self.cmd_memory(&tokens[1..]),
-                "cores" => {
-                    // Multi-threading (not implemented yet)
-                }
+                "cores" => self.cmd_cores(&tokens[1..]),
                 "egtpath" => {
                     // Endgame tablebase path
                 }
-                "option" => {
-                    // Custom option
-                }
+                "option" => self.cmd_option(&tokens[1..]),
                 // If it's not a recognized command, try to parse as a move
                 _ => {
                     // Try to interpret as a move in coordinate notation
@@ -181,14 +289,10 @@ impl XBoardEngine {
     }
 
     /// Handle "protover" command - send feature list
-    fn cmd_protover(&self, tokens: &[&str], stdout: &mut io::Stdout) {
-        let _version: u32 = tokens.first()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(1);
-
+    pub fn cmd_protover(&self, stdout: &mut io::Stdout) {
         // Send our features
         writeln!(stdout, "feature done=0").unwrap();
-        writeln!(stdout, "feature myname=\"Kai 1.0\"").unwrap();
+        writeln!(stdout, "feature myname=\"{} {}\"", ENGINE_NAME, ENGINE_VERSION).unwrap();
         writeln!(stdout, "feature variants=\"normal\"").unwrap();
         writeln!(stdout, "feature setboard=1").unwrap();
         writeln!(stdout, "feature ping=1").unwrap();
@@ -208,8 +312,17 @@ impl XBoardEngine {
         writeln!(stdout, "feature nps=0").unwrap();
         writeln!(stdout, "feature debug=1").unwrap();
         writeln!(stdout, "feature memory=1").unwrap();
-        writeln!(stdout, "feature smp=0").unwrap();
+        writeln!(stdout, "feature smp={}", (MAX_SEARCH_THREADS > 1) as u8).unwrap();
         writeln!(stdout, "feature egt=\"\"").unwrap();
+        writeln!(
+            stdout,
+            "feature option=\"Move Overhead -spin {} 0 5000\"",
+            DEFAULT_MOVE_OVERHEAD
+        )
+        .unwrap();
+        for option in ADJUDICATION_OPTIONS {
+            writeln!(stdout, "feature option=\"{}\"", option).unwrap();
+        }
         writeln!(stdout, "feature done=1").unwrap();
         stdout.flush().unwrap();
     }
@@ -219,19 +332,23 @@ impl XBoardEngine {
         self.position = Position::new();
         self.tt.clear();
         self.game_history.clear();
+        self.adjudicator.reset();
+        self.heuristics.clear();
         self.mode = EngineMode::Playing(Color::Black);
         self.computer_color = Color::Black;
         self.depth_limit = None;
+        self.clear_analysis_progress();
     }
 
     /// Handle "force" command - enter force mode
     fn cmd_force(&mut self) {
         self.mode = EngineMode::Force;
         STOP_FLAG.store(true, Ordering::SeqCst);
+        self.clear_analysis_progress();
     }
 
     /// Handle "go" command - start playing for the side to move
-    fn cmd_go(&mut self, stdout: &mut io::Stdout) {
+    fn cmd_go(&mut self, stdout: &mut dyn Write) {
         self.computer_color = self.position.side_to_move;
         self.mode = EngineMode::Playing(self.computer_color);
         self.think_and_move(stdout);
@@ -329,7 +446,7 @@ impl XBoardEngine {
     }
 
     /// Handle "usermove" command - opponent made a move
-    fn cmd_usermove(&mut self, tokens: &[&str], stdout: &mut io::Stdout) {
+    fn cmd_usermove(&mut self, tokens: &[&str], stdout: &mut dyn Write) {
         if tokens.is_empty() {
             return;
         }
@@ -341,7 +458,7 @@ impl XBoardEngine {
     }
 
     /// Try to parse and apply a user move
-    fn try_user_move(&mut self, move_str: &str, stdout: &mut io::Stdout) -> bool {
+    fn try_user_move(&mut self, move_str: &str, stdout: &mut dyn Write) -> bool {
         // Try to parse as coordinate notation (e.g., e2e4, e7e8q)
         if let Some(new_pos) = self.position.make_uci_move(move_str) {
             self.game_history.push(self.position.hash);
@@ -397,30 +514,12 @@ impl XBoardEngine {
     /// Handle "draw" command - offer/accept draw
     fn cmd_draw(&self, stdout: &mut io::Stdout) {
         // Check if position is actually a draw
-        if self.is_draw() {
+        if self.position.is_draw(&self.game_history) {
             writeln!(stdout, "offer draw").unwrap();
             stdout.flush().unwrap();
         }
     }
 
-    /// Check if current position is a draw
-    fn is_draw(&self) -> bool {
-        // 50-move rule
-        if self.position.halfmove_clock >= 100 {
-            return true;
-        }
-
-        // Threefold repetition
-        let current_hash = self.position.hash;
-        let count = self.game_history.iter().filter(|&&h| h == current_hash).count();
-        if count >= 2 {
-            return true;
-        }
-
-        // Insufficient material check would go here
-        false
-    }
-
     /// Handle "result" command - game ended
     fn cmd_result(&mut self, _tokens: &[&str]) {
         self.mode = EngineMode::Force;
@@ -428,23 +527,30 @@ impl XBoardEngine {
     }
 
     /// Handle "setboard" command - set position from FEN
-    fn cmd_setboard(&mut self, tokens: &[&str]) {
+    ///
+    /// The mode is kept: the engine never moves on its own here. Only "go" and
+    /// "playother" choose its side; when already playing, `computer_color` still holds,
+    /// and in analyze mode the command loop analyzes the new position.
+    fn cmd_setboard(&mut self, tokens: &[&str], stdout: &mut dyn Write) {
         let fen = tokens.join(" ");
-        if let Ok(pos) = Position::from_fen(&fen) {
-            self.position = pos;
-            self.game_history.clear();
+        match Position::from_fen(&fen) {
+            Ok(pos) => {
+                self.position = pos;
+                self.game_history.clear();
+                self.adjudicator.reset();
+            }
+            Err(err) => {
+                writeln!(stdout, "tellusererror Illegal position: {}", err).unwrap();
+                stdout.flush().unwrap();
+            }
         }
     }
 
     /// Handle edit mode (legacy)
-    fn cmd_edit_mode(&mut self, stdin: &io::Stdin, stdout: &mut io::Stdout) {
+    fn cmd_edit_mode(&mut self, input: &Receiver<String>, stdout: &mut io::Stdout) {
         // Edit mode is complex and mostly unused
         // Just read until "." is received
-        for line in stdin.lock().lines() {
-            let line = match line {
-                Ok(l) => l,
-                Err(_) => break,
-            };
+        for line in input.iter() {
             if line.trim() == "." {
                 break;
             }
@@ -489,78 +595,119 @@ impl XBoardEngine {
         // Would need position history to properly implement
     }
 
-    /// Handle "analyze" command - enter analysis mode
-    fn cmd_analyze(&mut self, stdout: &mut io::Stdout) {
+    /// Handle "analyze" command - enter analysis mode; the command loop runs the search
+    fn cmd_analyze(&mut self) {
         self.mode = EngineMode::Analyze;
-        self.analyze_position(stdout);
     }
 
     /// Handle "exit" command - exit analysis mode
     fn cmd_exit_analyze(&mut self) {
         self.mode = EngineMode::Force;
         STOP_FLAG.store(true, Ordering::SeqCst);
+        self.clear_analysis_progress();
     }
 
     /// Handle "." command - show analysis status
-    fn cmd_analyze_status(&self, stdout: &mut io::Stdout) {
-        writeln!(stdout, "stat01: 0 0 0 0 0").unwrap();
-        stdout.flush().unwrap();
+    ///
+    /// While analysis runs the input reader answers instead, as this thread is searching.
+    fn cmd_analyze_status(&self, stdout: &mut dyn Write) {
+        write_analysis_status(&self.analysis_progress, stdout);
+    }
+
+    /// Forget the last analysis, so "." outside analyze mode reports nothing stale
+    fn clear_analysis_progress(&mut self) {
+        *self.analysis_progress.lock().unwrap() = None;
+    }
+
+    /// Handle "option NAME=VALUE" command - set an engine-defined option
+    fn cmd_option(&mut self, tokens: &[&str]) {
+        let option = tokens.join(" ");
+        let Some((name, value)) = option.split_once('=') else {
+            return;
+        };
+
+        let value = value.trim();
+        let adjudicator = &mut self.adjudicator;
+        match name.trim() {
+            "Move Overhead" => {
+                if let Ok(overhead) = value.parse::<u64>() {
+                    self.move_overhead = overhead.min(5000);
+                }
+            }
+            "Adjudicate Draw Score" => {
+                if let Ok(score) = value.parse::<i16>() {
+                    adjudicator.draw_score = score.clamp(0, 100);
+                }
+            }
+            "Adjudicate Draw Moves" => {
+                if let Ok(moves) = value.parse::<u32>() {
+                    adjudicator.draw_moves = moves.min(200);
+                }
+            }
+            "Adjudicate Resign Score" => {
+                if let Ok(score) = value.parse::<i16>() {
+                    adjudicator.resign_score = score.clamp(0, 10000);
+                }
+            }
+            "Adjudicate Resign Moves" => {
+                if let Ok(moves) = value.parse::<u32>() {
+                    adjudicator.resign_moves = moves.min(200);
+                }
+            }
+            _ => {}
+        }
     }
 
-    /// Handle "memory" command - set hash table size
+    /// Handle "memory" command - size the hash table to fit the total memory budget
     fn cmd_memory(&mut self, tokens: &[&str]) {
-        if let Some(size) = tokens.first().and_then(|s| s.parse::<usize>().ok()) {
-            let size = size.clamp(1, 4096);
+        if let Some(total) = tokens.first().and_then(|s| s.parse::<usize>().ok()) {
+            let size = total.saturating_sub(NON_HASH_MEMORY_MB).clamp(1, 4096);
             self.tt_size_mb = size;
             self.tt.resize(size);
         }
     }
 
-    /// Think and make a move
-    fn think_and_move(&mut self, stdout: &mut io::Stdout) {
-        // Calculate time limit
-        let time_limit = self.calculate_time_limit();
+    /// Handle "cores" command - set the number of search threads
+    fn cmd_cores(&mut self, tokens: &[&str]) {
+        if let Some(cores) = tokens.first().and_then(|s| s.parse::<usize>().ok()) {
+            self.threads = search_threads(cores);
+        }
+    }
 
+    /// Think and make a move
+    fn think_and_move(&mut self, stdout: &mut dyn Write) {
         // Reset stop flag
         STOP_FLAG.store(false, Ordering::SeqCst);
 
-        // Track search time
-        let start_time = std::time::Instant::now();
-
-        // Run search
+        // Run search with soft and hard time limits
         let stop_flag: &'static AtomicBool = unsafe { std::mem::transmute(&STOP_FLAG) };
-        let result = self.position.search(
-            &mut self.tt,
-            time_limit,
-            self.depth_limit,
-            Some(stop_flag),
-        );
-
-        let elapsed_ms = start_time.elapsed().as_millis() as u64;
-
-        // Output thinking info if post is enabled
-        if self.post {
-            // XBoard thinking format: ply score time nodes pv
-            // time is in centiseconds
-            writeln!(
-                stdout,
-                "{} {} {} {} {}",
-                result.depth,
-                result.score,
-                elapsed_ms / 10,
-                result.nodes,
-                result.pv.iter()
-                    .map(|m| m.to_uci())
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            ).unwrap();
+        let mut info = SearchInfo::new(Instant::now());
+        if let Some(budget) = self.calculate_time_budget() {
+            info.set_time_budget(budget);
         }
-
-        // Check for game end conditions
-        if result.score >= 29000 {
-            // We're delivering mate
-        } else if result.score <= -29000 {
-            // We're getting mated
+        info.depth_limit = self.depth_limit;
+        info.stop_flag = Some(stop_flag);
+        info.history = self.game_history.clone();
+        info.heuristics = std::mem::take(&mut self.heuristics);
+
+        // Thinking output, one "ply score time nodes pv" line per depth, if post is enabled
+        info.xboard_output = true;
+        let mut sink = io::sink();
+        let thinking: &mut dyn Write = if self.post { &mut *stdout } else { &mut sink };
+        let result = self.position.search_with_output(&mut self.tt, &mut info, thinking);
+        self.heuristics = std::mem::take(&mut info.heuristics);
+
+        // Score-based adjudication for engine matches
+        let low_material = self.position.game_phase() <= ADJUDICATION_MAX_PHASE;
+        match self.adjudicator.update(result.score, low_material) {
+            Verdict::Resign => {
+                writeln!(stdout, "resign").unwrap();
+                stdout.flush().unwrap();
+                self.mode = EngineMode::Force;
+                return;
+            }
+            Verdict::OfferDraw => writeln!(stdout, "offer draw").unwrap(),
+            Verdict::Continue => {}
         }
 
         // Make the move
@@ -570,17 +717,26 @@ impl XBoardEngine {
 
         // Output the move
         writeln!(stdout, "move {}", move_str).unwrap();
-        stdout.flush().unwrap();
 
-        // Check for draw
-        if self.is_draw() {
+        // Claim the result when the move ends the game, and stop playing
+        if self.position.is_checkmate() {
+            let result = match self.position.side_to_move {
+                Color::White => "0-1 {Black mates}",
+                Color::Black => "1-0 {White mates}",
+            };
+            writeln!(stdout, "{}", result).unwrap();
+            self.mode = EngineMode::Force;
+        } else if self.position.is_stalemate() {
+            writeln!(stdout, "1/2-1/2 {{Stalemate}}").unwrap();
+            self.mode = EngineMode::Force;
+        } else if self.position.is_draw(&self.game_history) {
             writeln!(stdout, "offer draw").unwrap();
-            stdout.flush().unwrap();
         }
+        stdout.flush().unwrap();
     }
 
-    /// Calculate time limit for search
-    fn calculate_time_limit(&self) -> Option<Duration> {
+    /// Calculate soft and hard time limits for search
+    fn calculate_time_budget(&self) -> Option<TimeBudget> {
         let our_time = match self.computer_color {
             Color::White => self.time_white,
             Color::Black => self.time_black,
@@ -590,63 +746,106 @@ impl XBoardEngine {
             return None;
         }
 
-        let moves = if self.moves_per_tc > 0 {
-            self.moves_per_tc as u64
-        } else {
-            30 // Estimate for sudden death
-        };
+        // Sudden death uses the default estimate of moves left
+        let moves_to_go = (self.moves_per_tc > 0).then_some(self.moves_per_tc);
 
-        // Simple time management
-        let base = our_time / moves.max(1);
-        let total = base + (self.increment * 3) / 4;
-        let limit = total.min(our_time.saturating_sub(100));
-
-        Some(Duration::from_millis(limit))
+        Some(TimeBudget::from_clock(
+            our_time,
+            self.increment,
+            moves_to_go,
+            self.move_overhead,
+        ))
     }
 
     /// Analyze position continuously
-    fn analyze_position(&mut self, stdout: &mut io::Stdout) {
-        // In analysis mode, we search indefinitely until "exit" or "."/new command
-        STOP_FLAG.store(false, Ordering::SeqCst);
+    ///
+    /// One iterative-deepening search streams a thinking line per depth and runs until
+    /// the next command stops it (or to the "sd" limit, if one is set).
+    fn analyze_position(&mut self, out: &mut dyn Write) {
+        self.clear_analysis_progress();
 
         let stop_flag: &'static AtomicBool = unsafe { std::mem::transmute(&STOP_FLAG) };
-        let start_time = std::time::Instant::now();
+        let mut info = SearchInfo::new(Instant::now());
+        info.depth_limit = self.depth_limit;
+        info.stop_flag = Some(stop_flag);
+        info.history = self.game_history.clone();
+        info.xboard_output = true;
+        info.progress = Some(SharedProgress::clone(&self.analysis_progress));
+        info.heuristics = std::mem::take(&mut self.heuristics);
+        self.position.search_with_output(&mut self.tt, &mut info, out);
+        self.heuristics = std::mem::take(&mut info.heuristics);
+    }
+}
 
-        // Do iterative deepening, outputting after each depth
-        for depth in 1..=100 {
-            if STOP_FLAG.load(Ordering::SeqCst) {
-                break;
-            }
+/// Write a "stat01" line for the current or last analysis, or zeros if there is none
+fn write_analysis_status(progress: &SharedProgress, out: &mut dyn Write) {
+    let status = match *progress.lock().unwrap() {
+        Some(progress) => stat01_line(&progress, progress.start_time.elapsed()),
+        None => "stat01: 0 0 0 0 0".to_string(),
+    };
+    writeln!(out, "{}", status).unwrap();
+    out.flush().unwrap();
+}
 
-            let result = self.position.search(
-                &mut self.tt,
-                None,
-                Some(depth),
-                Some(stop_flag),
-            );
+/// Format an analysis progress report:
+/// "stat01: <centiseconds> <nodes> <depth> <moves left> <total moves> <current move>"
+fn stat01_line(progress: &SearchProgress, elapsed: Duration) -> String {
+    let root = &progress.root;
+    let mut line = format!(
+        "stat01: {} {} {} {} {}",
+        elapsed.as_millis() / 10,
+        progress.nodes,
+        root.depth,
+        root.total_moves.saturating_sub(root.move_number),
+        root.total_moves
+    );
+    if !root.current.is_null() {
+        line.push(' ');
+        line.push_str(&root.current.to_uci());
+    }
+    line
+}
 
-            if STOP_FLAG.load(Ordering::SeqCst) {
-                break;
+/// Read stdin on its own thread so commands reach a running search
+///
+/// The search runs on the command loop's thread, so the reader sets STOP_FLAG itself:
+/// on "?" and "quit", and while analyzing on any command but ".", which it answers from
+/// the shared analysis progress. Each line is queued before the flag is set, so a stopped
+/// search always finds the command that stopped it. Lines inside an "edit" block are
+/// passed through untouched.
+fn spawn_input_reader(progress: SharedProgress) -> Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut editing = false;
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            let cmd = line.split_whitespace().next();
+            let analyzing = ANALYZING.load(Ordering::SeqCst);
+            if !editing && analyzing && cmd == Some(".") {
+                write_analysis_status(&progress, &mut io::stdout());
+                continue;
             }
 
-            let elapsed_ms = start_time.elapsed().as_millis() as u64;
-
-            // Output thinking in XBoard format
-            writeln!(
-                stdout,
-                "{} {} {} {} {}",
-                result.depth,
-                result.score,
-                elapsed_ms / 10,
-                result.nodes,
-                result.pv.iter()
-                    .map(|m| m.to_uci())
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            ).unwrap();
-            stdout.flush().unwrap();
+            let interrupts = match cmd {
+                _ if editing => false,
+                Some("?" | "quit") => true,
+                Some(".") | None => false,
+                Some(_) => analyzing,
+            };
+            match cmd {
+                Some("edit") => editing = true,
+                Some(".") => editing = false,
+                _ => {}
+            }
+            if tx.send(line).is_err() {
+                break;
+            }
+            if interrupts {
+                STOP_FLAG.store(true, Ordering::SeqCst);
+            }
         }
-    }
+    });
+    rx
 }
 
 impl Default for XBoardEngine {
@@ -658,6 +857,7 @@ impl Default for XBoardEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Square;
 
     #[test]
     fn test_xboard_engine_creation() {
@@ -682,6 +882,253 @@ mod tests {
         assert_eq!(engine.increment, 12 * 1000);
     }
 
+    #[test]
+    fn test_move_overhead_option() {
+        let mut engine = XBoardEngine::new();
+        engine.cmd_level(&["0", "1", "0"]);
+        engine.computer_color = Color::White;
+
+        engine.cmd_option(&["Move", "Overhead=0"]);
+        let base = engine.calculate_time_budget().unwrap();
+        engine.cmd_option(&["Move", "Overhead=200"]);
+        assert_eq!(engine.move_overhead, 200);
+        let lagged = engine.calculate_time_budget().unwrap();
+        assert_eq!(base.soft.unwrap() - lagged.soft.unwrap(), std::time::Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_adjudication_counters() {
+        let mut engine = XBoardEngine::new();
+        engine.cmd_option(&["Adjudicate", "Draw", "Score=10"]);
+        engine.cmd_option(&["Adjudicate", "Draw", "Moves=3"]);
+        engine.cmd_option(&["Adjudicate", "Resign", "Score=800"]);
+        engine.cmd_option(&["Adjudicate", "Resign", "Moves=2"]);
+        let mut adjudicator = engine.adjudicator;
+
+        assert_eq!(adjudicator.update(5, true), Verdict::Continue);
+        assert_eq!(adjudicator.update(-3, true), Verdict::Continue);
+        // A clear score resets the run of drawish ones
+        assert_eq!(adjudicator.update(40, true), Verdict::Continue);
+        assert_eq!(adjudicator.update(0, true), Verdict::Continue);
+        assert_eq!(adjudicator.update(2, true), Verdict::Continue);
+        assert_eq!(adjudicator.update(-8, true), Verdict::OfferDraw);
+        // Not with plenty of material on the board
+        assert_eq!(adjudicator.update(0, false), Verdict::Continue);
+
+        assert_eq!(adjudicator.update(-900, false), Verdict::Continue);
+        assert_eq!(adjudicator.update(-1200, false), Verdict::Resign);
+
+        // Off by default
+        let mut off = Adjudicator::default();
+        for _ in 0..10 {
+            assert_eq!(off.update(0, true), Verdict::Continue);
+            assert_eq!(off.update(-5000, true), Verdict::Continue);
+        }
+    }
+
+    #[test]
+    fn test_stat01_line() {
+        init_magics();
+        let pos = Position::new();
+        let mut tt = TranspositionTable::new(1);
+        let shared = SharedProgress::default();
+        let mut info = SearchInfo::new(Instant::now());
+        info.depth_limit = Some(3);
+        info.progress = Some(SharedProgress::clone(&shared));
+        pos.search_with_output(&mut tt, &mut info, &mut io::sink());
+
+        let progress = shared.lock().unwrap().expect("search published no progress");
+        let line = stat01_line(&progress, Duration::from_millis(1234));
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        assert_eq!(fields[0], "stat01:");
+        assert_eq!(fields[1], "123");
+        assert_eq!(fields[2], info.nodes.to_string());
+        assert_eq!(fields[3], "3");
+        // The last root move was searched, leaving none of the 20
+        assert_eq!(fields[4], "0");
+        assert_eq!(fields[5], "20");
+        assert!(pos.parse_uci_move(fields[6]).is_some());
+    }
+
+    #[test]
+    fn test_setboard_then_go() {
+        let mut engine = XBoardEngine::new();
+        engine.cmd_new();
+        engine.cmd_force();
+        engine.cmd_sd(&["2"]);
+
+        // Black to move: setboard must not move, and "go" plays Black
+        let mut out = Vec::new();
+        engine.cmd_setboard(&["4k3/8/8/8/8/3q4/8/4K3", "b", "-", "-", "0", "1"], &mut out);
+        assert!(out.is_empty());
+        assert_eq!(engine.mode, EngineMode::Force);
+        assert_eq!(engine.position.side_to_move, Color::Black);
+
+        engine.cmd_go(&mut out);
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.lines().any(|l| l.starts_with("move ")), "{}", output);
+        assert_eq!(engine.computer_color, Color::Black);
+        assert_eq!(engine.mode, EngineMode::Playing(Color::Black));
+        assert_eq!(engine.position.side_to_move, Color::White);
+        assert_eq!(engine.game_history.len(), 1);
+
+        // A bad FEN leaves the position alone and tells the user why
+        let before = engine.position.hash;
+        let mut out = Vec::new();
+        engine.cmd_setboard(&["not", "a", "fen"], &mut out);
+        assert_eq!(engine.position.hash, before);
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.starts_with("tellusererror Illegal position: "), "{}", output);
+        assert_eq!(output.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_analyze_searches_once() {
+        let mut engine = XBoardEngine::new();
+        engine.cmd_sd(&["6"]);
+        engine.mode = EngineMode::Analyze;
+
+        let mut out = Vec::new();
+        engine.analyze_position(&mut out);
+        let output = String::from_utf8(out).unwrap();
+
+        // One thinking line per iteration, in XBoard format
+        let depths: Vec<&str> = output.lines().map(|l| l.split(' ').next().unwrap()).collect();
+        assert_eq!(depths, ["1", "2", "3", "4", "5", "6"]);
+        let nodes: u64 = output.lines().last().unwrap().split(' ').nth(3).unwrap().parse().unwrap();
+
+        // Restarting the search for every depth, as analysis used to, costs more nodes
+        let mut tt = TranspositionTable::new(64);
+        let restarted: u64 = (1..=6)
+            .map(|depth| {
+                let mut info = SearchInfo::new(Instant::now());
+                info.depth_limit = Some(depth);
+                engine.position.search_with_output(&mut tt, &mut info, &mut io::sink()).nodes
+            })
+            .sum();
+        assert!(nodes < restarted, "single {} vs restarted {}", nodes, restarted);
+    }
+
+    #[test]
+    fn test_exit_clears_analysis_status() {
+        let mut engine = XBoardEngine::new();
+        engine.cmd_sd(&["3"]);
+        engine.mode = EngineMode::Analyze;
+        engine.analyze_position(&mut io::sink());
+
+        let mut out = Vec::new();
+        engine.cmd_analyze_status(&mut out);
+        let status = String::from_utf8(out).unwrap();
+        assert!(status.starts_with("stat01: "));
+        assert_ne!(status.split(' ').nth(2), Some("0"), "no nodes in {:?}", status);
+
+        engine.cmd_exit_analyze();
+        let mut out = Vec::new();
+        engine.cmd_analyze_status(&mut out);
+        assert_eq!(String::from_utf8(out).unwrap(), "stat01: 0 0 0 0 0\n");
+    }
+
+    #[test]
+    fn test_heuristics_persist_within_game() {
+        let mut engine = XBoardEngine::new();
+        let mut out = Vec::new();
+        engine.cmd_new();
+        engine.cmd_force();
+        engine.cmd_sd(&["5"]);
+
+        engine.cmd_go(&mut out);
+        assert!(engine.heuristics.history.iter().flatten().flatten().any(|&h| h != 0));
+
+        // Mark entries no search can touch (a1 to a1): the next search keeps the countermove
+        // and ages the history score rather than starting over
+        let marker = Move::quiet(Square::H1, Square::H2);
+        engine.heuristics.countermoves[0][0] = marker;
+        engine.heuristics.history[0][0][0] = 1000;
+        engine.cmd_force();
+        engine.cmd_go(&mut out);
+        assert_eq!(engine.heuristics.countermoves[0][0], marker);
+        assert_eq!(engine.heuristics.history[0][0][0], 500);
+        let output = String::from_utf8(out).unwrap();
+        assert_eq!(output.lines().filter(|l| l.starts_with("move ")).count(), 2, "{}", output);
+
+        // A new game starts from scratch
+        engine.cmd_new();
+        assert!(engine.heuristics.history.iter().flatten().flatten().all(|&h| h == 0));
+        assert!(engine.heuristics.killers.iter().flatten().all(|mv| mv.is_null()));
+        assert!(engine.heuristics.countermoves.iter().flatten().all(|mv| mv.is_null()));
+    }
+
+    #[test]
+    fn test_cores_sets_threads() {
+        let mut engine = XBoardEngine::new();
+        engine.cmd_cores(&["4"]);
+        assert_eq!(engine.threads, 4.min(MAX_SEARCH_THREADS));
+        engine.cmd_cores(&["0"]);
+        assert_eq!(engine.threads, 1);
+        engine.cmd_cores(&["many"]);
+        assert_eq!(engine.threads, 1);
+    }
+
+    #[test]
+    fn test_memory_reserves_non_hash_memory() {
+        let mut engine = XBoardEngine::new();
+        engine.cmd_memory(&["68"]);
+        assert_eq!(engine.tt.size_mb(), 68 - NON_HASH_MEMORY_MB);
+
+        for total in [5, 16, 100, 1000] {
+            engine.cmd_memory(&[&total.to_string()]);
+            assert_eq!(engine.tt.size_mb(), total - NON_HASH_MEMORY_MB);
+            assert_eq!(engine.tt.size_mb(), engine.tt_size_mb);
+        }
+
+        // Too small a budget still leaves a working table
+        engine.cmd_memory(&["2"]);
+        assert_eq!(engine.tt.size_mb(), 1);
+    }
+
+    #[test]
+    fn test_mate_in_one_claims_result() {
+        let mut engine = XBoardEngine::new();
+        engine.cmd_new();
+        engine.cmd_sd(&["3"]);
+        engine.cmd_setboard(&["k7/8/1K6/8/8/8/8/6Q1", "w", "-", "-", "0", "1"], &mut io::sink());
+        engine.mode = EngineMode::Playing(Color::White);
+
+        let mut out = Vec::new();
+        engine.think_and_move(&mut out);
+        let output = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        // Thinking lines give the mate in XBoard's units
+        assert!(lines.iter().any(|l| l.starts_with("1 100001 ")), "{}", output);
+        let n = lines.len();
+        assert_eq!(lines[n - 2], "move g1g8");
+        assert_eq!(lines[n - 1], "1-0 {White mates}");
+        assert_eq!(engine.mode, EngineMode::Force);
+    }
+
+    #[test]
+    fn test_game_search_sees_earlier_repetition() {
+        let mut engine = XBoardEngine::new();
+        engine.cmd_new();
+        engine.cmd_force();
+        engine.cmd_sd(&["4"]);
+        engine.cmd_setboard(&["4k3/8/8/8/8/8/q7/4K3", "w", "-", "-", "0", "1"], &mut io::sink());
+
+        // Queen down, but Kf1 repeats the position after the first Kf1
+        for mv in ["e1f1", "a2a3", "f1e1", "a3a2"] {
+            engine.cmd_usermove(&[mv], &mut io::sink());
+        }
+        let mut out = Vec::new();
+        engine.cmd_go(&mut out);
+        let output = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[lines.len() - 1], "move e1f1", "{}", output);
+        let score: i32 = lines[lines.len() - 2].split(' ').nth(1).unwrap().parse().unwrap();
+        assert!(score.abs() < 100, "{}", output);
+    }
+
     #[test]
     fn test_new_game() {
         let mut engine = XBoardEngine::new();
@@ -0,0 +1,64 @@
+//! End-to-end check that "stop" interrupts "go infinite" in the UCI binary
+
+use kai::magic::init_magics;
+use kai::position::Position;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_stop_ends_infinite_search() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_kai"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start engine");
+
+    let mut stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+
+    // Forward output lines so the test can time out instead of hanging
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    writeln!(stdin, "uci").unwrap();
+    writeln!(stdin, "position startpos moves e2e4").unwrap();
+    writeln!(stdin, "go infinite").unwrap();
+    stdin.flush().unwrap();
+
+    // Let the search get under way, then stop it
+    loop {
+        let line = rx.recv_timeout(Duration::from_secs(10)).expect("no search output");
+        assert!(!line.starts_with("bestmove"), "infinite search ended on its own");
+        if line.starts_with("info depth 3 ") {
+            break;
+        }
+    }
+    writeln!(stdin, "stop").unwrap();
+    stdin.flush().unwrap();
+
+    let bestmove = loop {
+        let line = rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("stop did not end the search");
+        if let Some(rest) = line.strip_prefix("bestmove ") {
+            break rest.split_whitespace().next().unwrap().to_string();
+        }
+    };
+
+    writeln!(stdin, "quit").unwrap();
+    child.wait().unwrap();
+
+    init_magics();
+    let mut pos = Position::new();
+    pos = pos.make_move(pos.parse_uci_move("e2e4").unwrap());
+    assert!(pos.parse_uci_move(&bestmove).is_some(), "illegal bestmove {}", bestmove);
+}
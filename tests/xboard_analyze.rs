@@ -0,0 +1,75 @@
+//! End-to-end check that commands interrupt XBoard analysis in the binary
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_exit_ends_analysis() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_kai"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start engine");
+
+    let mut stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+
+    // Forward output lines so the test can time out instead of hanging
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let wait_for = |prefix: &str, what: &str| loop {
+        let line = rx.recv_timeout(Duration::from_secs(10)).expect(what);
+        if line.starts_with(prefix) {
+            break line;
+        }
+    };
+
+    writeln!(stdin, "xboard").unwrap();
+    writeln!(stdin, "analyze").unwrap();
+    stdin.flush().unwrap();
+    wait_for("3 ", "no analysis output");
+
+    // A move restarts the analysis on the new position
+    writeln!(stdin, "usermove e2e4").unwrap();
+    stdin.flush().unwrap();
+    let line = wait_for("1 ", "analysis did not restart after a move");
+    let pv_move = line.split_whitespace().nth(4).unwrap();
+    assert!(pv_move.as_bytes()[1] > b'4', "{} is not a black move", pv_move);
+
+    // "." is answered from the running search without stopping it
+    let mut status_nodes = || {
+        writeln!(stdin, ".").unwrap();
+        stdin.flush().unwrap();
+        let status = wait_for("stat01:", "no status during analysis");
+        status.split_whitespace().nth(2).unwrap().parse::<u64>().unwrap()
+    };
+    let first = status_nodes();
+    thread::sleep(Duration::from_millis(100));
+    let second = status_nodes();
+    assert!(first > 0 && second > first, "no live progress: {} then {}", first, second);
+
+    // "exit" ends the unbounded search, and the engine answers the next command
+    writeln!(stdin, "exit").unwrap();
+    writeln!(stdin, "ping 7").unwrap();
+    stdin.flush().unwrap();
+    wait_for("pong 7", "exit did not end the analysis");
+
+    // Outside analyze mode there is no progress to report
+    writeln!(stdin, ".").unwrap();
+    stdin.flush().unwrap();
+    let status = wait_for("stat01:", "no status after exit");
+    assert_eq!(status, "stat01: 0 0 0 0 0");
+
+    writeln!(stdin, "quit").unwrap();
+    stdin.flush().unwrap();
+    child.wait().unwrap();
+}